@@ -0,0 +1,174 @@
+// sphere and cylinder impostors: a camera facing quad per instance, the shape is ray cast in the fragment shader.
+// the instances are in the local space of the entity, transformed with its mesh uniform
+#import bevy_pbr::mesh_view_bindings::view
+#import bevy_pbr::mesh_functions::get_world_from_local
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    // center (sphere) or start (cylinder) and radius
+    @location(3) i_start_radius: vec4<f32>,
+    // end of the cylinder, not used by spheres
+    @location(4) i_end: vec4<f32>,
+    @location(5) i_color: vec4<f32>,
+    // index of the entity in the mesh uniforms, as the instance index is used for the instances
+    @location(6) mesh_index: u32,
+};
+
+// start, end and radius of the instance in world space
+struct Instance {
+    start: vec3<f32>,
+    end: vec3<f32>,
+    radius: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) start: vec3<f32>,
+    @location(2) end: vec3<f32>,
+    @location(3) radius: f32,
+    @location(4) color: vec4<f32>,
+};
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+};
+
+// the quad is larger than the shape, as in perspective its outline is larger than its radius
+const QUAD_SCALE: f32 = 1.5;
+const AMBIENT: f32 = 0.3;
+const SPECULAR: f32 = 0.25;
+const SHININESS: f32 = 32.0;
+
+fn is_orthographic() -> bool {
+    return view.clip_from_view[3].w == 1.0;
+}
+
+// direction of the ray from the camera through the point
+fn ray_direction(point: vec3<f32>) -> vec3<f32> {
+    if is_orthographic() {
+        return -normalize(view.world_from_view[2].xyz);
+    }
+    return normalize(point - view.world_position);
+}
+
+fn world_instance(vertex: Vertex) -> Instance {
+    let world_from_local = get_world_from_local(vertex.mesh_index);
+    var instance: Instance;
+    instance.start = (world_from_local * vec4(vertex.i_start_radius.xyz, 1.0)).xyz;
+    instance.end = (world_from_local * vec4(vertex.i_end.xyz, 1.0)).xyz;
+    // the molecule isn't scaled non uniformly
+    instance.radius = vertex.i_start_radius.w * length(world_from_local[0].xyz);
+    return instance;
+}
+
+fn vertex_output(world_position: vec3<f32>, instance: Instance, vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = view.clip_from_world * vec4(world_position, 1.0);
+    out.world_position = world_position;
+    out.start = instance.start;
+    out.end = instance.end;
+    out.radius = instance.radius;
+    out.color = vertex.i_color;
+    return out;
+}
+
+// headlight shading, with the depth of the hit point
+fn shade(hit: vec3<f32>, normal: vec3<f32>, direction: vec3<f32>, color: vec4<f32>) -> FragmentOutput {
+    // slightly from the top left, so the shapes don't look flat
+    let light = normalize(-direction + (view.world_from_view[1].xyz - view.world_from_view[0].xyz) * 0.3);
+    let diffuse = max(dot(normal, light), 0.0);
+    let specular = pow(max(dot(reflect(-light, normal), -direction), 0.0), SHININESS);
+
+    var out: FragmentOutput;
+    out.color = vec4(color.rgb * (AMBIENT + (1.0 - AMBIENT) * diffuse) + vec3(SPECULAR * specular), color.a);
+    let clip = view.clip_from_world * vec4(hit, 1.0);
+    out.depth = clip.z / clip.w;
+    return out;
+}
+
+@vertex
+fn sphere_vertex(vertex: Vertex) -> VertexOutput {
+    let instance = world_instance(vertex);
+    let center = instance.start;
+    let radius = instance.radius;
+    let right = normalize(view.world_from_view[0].xyz);
+    let up = normalize(view.world_from_view[1].xyz);
+    let offset = (right * vertex.position.x + up * vertex.position.y) * radius * QUAD_SCALE;
+    return vertex_output(center + offset, instance, vertex);
+}
+
+@fragment
+fn sphere_fragment(in: VertexOutput) -> FragmentOutput {
+    let direction = ray_direction(in.world_position);
+    let oc = in.world_position - in.start;
+    let b = dot(oc, direction);
+    let c = dot(oc, oc) - in.radius * in.radius;
+    let h = b * b - c;
+    if h < 0.0 {
+        discard;
+    }
+    let hit = in.world_position + direction * (-b - sqrt(h));
+    return shade(hit, normalize(hit - in.start), direction, in.color);
+}
+
+@vertex
+fn cylinder_vertex(vertex: Vertex) -> VertexOutput {
+    let instance = world_instance(vertex);
+    let start = instance.start;
+    let end = instance.end;
+    let radius = instance.radius;
+    let middle = (start + end) / 2.0;
+
+    // the quad faces the camera, along the projected axis
+    let forward = -ray_direction(middle);
+    let axis = end - start;
+    let projected = axis - forward * dot(axis, forward);
+    let projected_length = length(projected);
+    var along = normalize(view.world_from_view[0].xyz);
+    if projected_length > 0.0001 {
+        along = projected / projected_length;
+    }
+    let across = cross(forward, along);
+
+    let offset = along * (projected_length / 2.0 + radius * QUAD_SCALE) * vertex.position.y
+        + across * radius * QUAD_SCALE * vertex.position.x;
+    return vertex_output(middle + offset, instance, vertex);
+}
+
+// capped cylinder intersection, see https://iquilezles.org/articles/intersectors
+@fragment
+fn cylinder_fragment(in: VertexOutput) -> FragmentOutput {
+    let direction = ray_direction(in.world_position);
+    let ba = in.end - in.start;
+    let oc = in.world_position - in.start;
+    let baba = dot(ba, ba);
+    let bard = dot(ba, direction);
+    let baoc = dot(ba, oc);
+    let k2 = baba - bard * bard;
+    let k1 = baba * dot(oc, direction) - baoc * bard;
+    let k0 = baba * dot(oc, oc) - baoc * baoc - in.radius * in.radius * baba;
+    var h = k1 * k1 - k2 * k0;
+    if h < 0.0 {
+        discard;
+    }
+    h = sqrt(h);
+
+    // body
+    var t = (-k1 - h) / k2;
+    let y = baoc + t * bard;
+    if y > 0.0 && y < baba {
+        let hit = in.world_position + direction * t;
+        let normal = (oc + direction * t - ba * y / baba) / in.radius;
+        return shade(hit, normalize(normal), direction, in.color);
+    }
+
+    // caps
+    t = (select(baba, 0.0, y < 0.0) - baoc) / bard;
+    if abs(k1 + k2 * t) >= h {
+        discard;
+    }
+    let hit = in.world_position + direction * t;
+    return shade(hit, normalize(ba * sign(y)), direction, in.color);
+}
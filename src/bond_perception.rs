@@ -0,0 +1,434 @@
+use bevy::{
+    math::{IVec3, Vec3},
+    utils::HashMap,
+};
+
+use crate::{
+    element::Element,
+    molecule::{Atom, Bond, BondType},
+};
+
+/// tolerance added to the sum of covalent radii when inferring bonds
+const BOND_TOLERANCE: f32 = 0.45;
+/// atoms closer than this are overlapping (e.g. alternate locations), not bonded
+const MIN_BOND_DISTANCE: f32 = 0.4;
+
+/// whether two atoms are close enough to be bonded, based on their covalent radii
+pub fn is_bond_distance(element1: Element, pos1: Vec3, element2: Element, pos2: Vec3) -> bool {
+    let max = element1.covalent_radius() + element2.covalent_radius() + BOND_TOLERANCE;
+    let distance = pos1.distance(pos2);
+    distance > MIN_BOND_DISTANCE && distance < max
+}
+
+/// bonds between all the atoms within bond distance, for formats without connectivity (e.g. xyz),
+/// with the bond orders guessed from the geometry (see `assign_bond_orders`)
+pub fn perceive_bonds(atoms: &[Atom]) -> Vec<Bond> {
+    let mut bonds: Vec<Bond> = bonded_pairs(atoms)
+        .into_iter()
+        .map(|(atom1, atom2)| Bond {
+            atom1,
+            atom2,
+            type_: BondType::Single,
+        })
+        .collect();
+    assign_bond_orders(atoms, &mut bonds);
+    bonds
+}
+
+/// pairs of atoms within bond distance, sorted.
+/// the atoms are put in a grid with cells as large as the longest possible bond,
+/// such that each atom has to be compared only with the atoms of the neighboring cells
+fn bonded_pairs(atoms: &[Atom]) -> Vec<(usize, usize)> {
+    let Some(max_radius) = atoms
+        .iter()
+        .map(|a| a.element.covalent_radius())
+        .reduce(f32::max)
+    else {
+        return vec![];
+    };
+    let cell_size = 2.0 * max_radius + BOND_TOLERANCE;
+    let cell = |position: Vec3| (position / cell_size).floor().as_ivec3();
+
+    let mut grid: HashMap<IVec3, Vec<usize>> = HashMap::new();
+    for (index, atom) in atoms.iter().enumerate() {
+        grid.entry(cell(atom.position)).or_default().push(index);
+    }
+
+    let mut pairs = vec![];
+    for (i, atom1) in atoms.iter().enumerate() {
+        let center = cell(atom1.position);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let Some(cell_atoms) = grid.get(&(center + IVec3::new(x, y, z))) else {
+                        continue;
+                    };
+                    for &j in cell_atoms {
+                        let atom2 = &atoms[j];
+                        if j > i
+                            && is_bond_distance(
+                                atom1.element,
+                                atom1.position,
+                                atom2.element,
+                                atom2.position,
+                            )
+                        {
+                            pairs.push((i, j));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// of an atom, see `hybridization`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hybridization {
+    Sp,
+    Sp2,
+    Sp3,
+}
+
+/// max deviation (Å) of the atoms of an aromatic ring from its plane
+const MAX_RING_DEVIATION: f32 = 0.1;
+
+/// sets double, triple, aromatic and amide bonds for bonds perceived from distances, which are all single.
+/// the hybridization of an atom follows from its bond angles (for terminal atoms the bond length),
+/// planar 5 and 6 rings are aromatic, and the remaining sp2 / sp atoms are paired with double / triple bonds,
+/// shortest bonds first. covers the common groups (carbonyl, carboxyl, nitrile, alkene, alkyne, aromatic rings),
+/// also without hydrogens (e.g. pdb). only single bonds are changed, bonds with a known order (e.g. from CONECT) are kept
+pub fn assign_bond_orders(atoms: &[Atom], bonds: &mut [Bond]) {
+    let mut neighbors: Vec<Vec<(usize, usize)>> = vec![vec![]; atoms.len()];
+    for (index, bond) in bonds.iter().enumerate() {
+        neighbors[bond.atom1].push((bond.atom2, index));
+        neighbors[bond.atom2].push((bond.atom1, index));
+    }
+
+    let hybridizations: Vec<Hybridization> = atoms
+        .iter()
+        .enumerate()
+        .map(|(index, atom)| hybridization(atoms, atom, &neighbors[index], bonds))
+        .collect();
+
+    let mut aromatic = vec![false; atoms.len()];
+    for ring in aromatic_rings(atoms, &neighbors) {
+        for (i, atom) in ring.iter().enumerate() {
+            let next = ring[(i + 1) % ring.len()];
+            if let Some((_, bond)) = neighbors[*atom].iter().find(|(n, _)| *n == next) {
+                if bonds[*bond].type_ == BondType::Single {
+                    bonds[*bond].type_ = BondType::Aromatic;
+                }
+            }
+            aromatic[*atom] = true;
+        }
+    }
+
+    // the bonds (beyond single) each atom can have
+    let mut capacity: Vec<u8> = hybridizations
+        .iter()
+        .zip(&aromatic)
+        .map(|(hybridization, aromatic)| match hybridization {
+            _ if *aromatic => 0,
+            Hybridization::Sp => 2,
+            Hybridization::Sp2 => 1,
+            Hybridization::Sp3 => 0,
+        })
+        .collect();
+    // known multiple bonds use up the capacity
+    for bond in bonds.iter() {
+        let used = match bond.type_ {
+            BondType::Double => 1,
+            BondType::Triple => 2,
+            _ => 0,
+        };
+        for atom in [bond.atom1, bond.atom2] {
+            capacity[atom] = capacity[atom].saturating_sub(used);
+        }
+    }
+    let is_terminal = |atom: usize| neighbors[atom].len() == 1;
+
+    let mut candidates: Vec<(usize, f32)> = bonds
+        .iter()
+        .enumerate()
+        .filter(|(_, bond)| bond.type_ == BondType::Single)
+        .map(|(index, bond)| (index, length_ratio(atoms, bond)))
+        .collect();
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    for (index, ratio) in candidates {
+        let Bond { atom1, atom2, .. } = bonds[index];
+        // exocyclic carbonyls of aromatic rings (e.g. nucleobases)
+        let ring_carbonyl = |ring: usize, other: usize| {
+            aromatic[ring]
+                && is_terminal(other)
+                && matches!(atoms[other].element, Element::O | Element::S)
+                && neighbors[ring].len() == 3
+        };
+        if ring_carbonyl(atom1, atom2) || ring_carbonyl(atom2, atom1) {
+            let other = if aromatic[atom1] { atom2 } else { atom1 };
+            if capacity[other] > 0 && ratio < 0.95 {
+                bonds[index].type_ = BondType::Double;
+                capacity[other] -= 1;
+            }
+            continue;
+        }
+
+        if capacity[atom1] == 2 && capacity[atom2] == 2 && ratio < 0.86 {
+            bonds[index].type_ = BondType::Triple;
+            capacity[atom1] = 0;
+            capacity[atom2] = 0;
+        } else if capacity[atom1] > 0 && capacity[atom2] > 0 && ratio < 0.95 {
+            bonds[index].type_ = BondType::Double;
+            capacity[atom1] -= 1;
+            capacity[atom2] -= 1;
+        }
+    }
+
+    // C(=O)-N
+    let is_carbonyl_carbon = |atom: usize| {
+        atoms[atom].element == Element::C
+            && neighbors[atom].iter().any(|(n, bond)| {
+                atoms[*n].element == Element::O && bonds[*bond].type_ == BondType::Double
+            })
+    };
+    let amides: Vec<usize> = bonds
+        .iter()
+        .enumerate()
+        .filter(|(_, bond)| {
+            bond.type_ == BondType::Single
+                && match (atoms[bond.atom1].element, atoms[bond.atom2].element) {
+                    (Element::C, Element::N) => is_carbonyl_carbon(bond.atom1),
+                    (Element::N, Element::C) => is_carbonyl_carbon(bond.atom2),
+                    _ => false,
+                }
+        })
+        .map(|(index, _)| index)
+        .collect();
+    for index in amides {
+        bonds[index].type_ = BondType::Amide;
+    }
+}
+
+/// bond length relative to the single bond length (sum of covalent radii)
+fn length_ratio(atoms: &[Atom], bond: &Bond) -> f32 {
+    let (a, b) = (&atoms[bond.atom1], &atoms[bond.atom2]);
+    a.position.distance(b.position) / (a.element.covalent_radius() + b.element.covalent_radius())
+}
+
+/// from the bond angles (for terminal atoms the bond length), so it doesn't depend on the bond orders.
+/// `neighbors` are the bonded atoms with the index of the bond in `bonds`.
+/// only C, N, O and S get multiple bonds, the other atoms are sp3
+pub fn hybridization(
+    atoms: &[Atom],
+    atom: &Atom,
+    neighbors: &[(usize, usize)],
+    bonds: &[Bond],
+) -> Hybridization {
+    if !matches!(
+        atom.element,
+        Element::C | Element::N | Element::O | Element::S
+    ) {
+        return Hybridization::Sp3;
+    }
+    match neighbors {
+        [] => Hybridization::Sp3,
+        [(_, bond)] => {
+            let ratio = length_ratio(atoms, &bonds[*bond]);
+            if ratio < 0.83 && matches!(atom.element, Element::C | Element::N) {
+                Hybridization::Sp
+            } else if ratio < 0.93 {
+                Hybridization::Sp2
+            } else {
+                Hybridization::Sp3
+            }
+        }
+        [_, _] | [_, _, _] => {
+            let directions: Vec<Vec3> = neighbors
+                .iter()
+                .map(|(n, _)| (atoms[*n].position - atom.position).normalize_or_zero())
+                .collect();
+            let mut angles = vec![];
+            for (i, d1) in directions.iter().enumerate() {
+                for d2 in &directions[i + 1..] {
+                    angles.push(d1.angle_between(*d2).to_degrees());
+                }
+            }
+            let average = angles.iter().sum::<f32>() / angles.len() as f32;
+            if average > 155.0 && neighbors.len() == 2 {
+                Hybridization::Sp
+            } else if average > 115.0 {
+                Hybridization::Sp2
+            } else {
+                Hybridization::Sp3
+            }
+        }
+        _ => Hybridization::Sp3,
+    }
+}
+
+/// planar 5 and 6 rings of C, N, O and S with at most 3 neighbors (which excludes sp3 atoms with hydrogens).
+/// the atoms of each ring are in ring order
+fn aromatic_rings(atoms: &[Atom], neighbors: &[Vec<(usize, usize)>]) -> Vec<Vec<usize>> {
+    let candidate = |atom: usize| {
+        matches!(
+            atoms[atom].element,
+            Element::C | Element::N | Element::O | Element::S
+        ) && (2..=3).contains(&neighbors[atom].len())
+    };
+
+    let mut rings = vec![];
+    for start in (0..atoms.len()).filter(|a| candidate(*a)) {
+        // paths from the start atom through atoms with a higher index, such that each ring is found from its lowest atom
+        let mut paths = vec![vec![start]];
+        while let Some(path) = paths.pop() {
+            let last = path[path.len() - 1];
+            for (next, _) in &neighbors[last] {
+                // each ring is found in both directions, only one is kept
+                if *next == start && path.len() >= 5 && path[1] < last {
+                    rings.push(path.clone());
+                } else if *next > start
+                    && path.len() < 6
+                    && candidate(*next)
+                    && !path.contains(next)
+                {
+                    let mut path = path.clone();
+                    path.push(*next);
+                    paths.push(path);
+                }
+            }
+        }
+    }
+    rings.retain(|ring| is_planar(atoms, ring));
+    rings
+}
+
+fn is_planar(atoms: &[Atom], ring: &[usize]) -> bool {
+    let positions: Vec<Vec3> = ring.iter().map(|a| atoms[*a].position).collect();
+    let center = positions.iter().sum::<Vec3>() / positions.len() as f32;
+    // Newell's method
+    let normal = positions
+        .iter()
+        .zip(positions.iter().cycle().skip(1))
+        .map(|(a, b)| (*a - center).cross(*b - center))
+        .sum::<Vec3>()
+        .normalize_or_zero();
+    normal != Vec3::ZERO
+        && positions
+            .iter()
+            .all(|p| (*p - center).dot(normal).abs() < MAX_RING_DEVIATION)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bonded_pairs, is_bond_distance, perceive_bonds};
+    use crate::{
+        element::Element,
+        molecule::{Atom, BondType},
+    };
+    use bevy::math::Vec3;
+
+    fn atoms(atoms: &[(Element, [f32; 3])]) -> Vec<Atom> {
+        atoms
+            .iter()
+            .enumerate()
+            .map(|(i, (element, p))| {
+                Atom::new(
+                    i as i32 + 1,
+                    element.symbol().to_string(),
+                    *element,
+                    Vec3::from(*p),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_grid_finds_the_same_pairs_as_all_pairs() {
+        // a loose lattice, with atoms on the cell borders
+        let mut positions = vec![];
+        for i in 0..6 {
+            for j in 0..6 {
+                for k in 0..6 {
+                    let element = if (i + j + k) % 3 == 0 {
+                        Element::C
+                    } else {
+                        Element::H
+                    };
+                    let p = [
+                        i as f32 * 1.1,
+                        j as f32 * 1.3 - 2.0,
+                        k as f32 * 0.9 + 0.05 * i as f32,
+                    ];
+                    positions.push((element, p));
+                }
+            }
+        }
+        let atoms = atoms(&positions);
+
+        let mut expected = vec![];
+        for (i, a) in atoms.iter().enumerate() {
+            for (j, b) in atoms.iter().enumerate().skip(i + 1) {
+                if is_bond_distance(a.element, a.position, b.element, b.position) {
+                    expected.push((i, j));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(bonded_pairs(&atoms), expected);
+    }
+
+    #[test]
+    fn test_assigns_aromatic_ring_and_carbonyl() {
+        // benzaldehyde without hydrogens
+        let mut positions: Vec<(Element, [f32; 3])> = (0..6)
+            .map(|i| {
+                let angle = i as f32 * std::f32::consts::PI / 3.0;
+                (Element::C, [1.39 * angle.cos(), 1.39 * angle.sin(), 0.0])
+            })
+            .collect();
+        positions.push((Element::C, [2.87, 0.0, 0.0]));
+        positions.push((Element::O, [3.47, 1.04, 0.0]));
+        let bonds = perceive_bonds(&atoms(&positions));
+
+        let type_of = |a: usize, b: usize| {
+            bonds
+                .iter()
+                .find(|bond| (bond.atom1, bond.atom2) == (a.min(b), a.max(b)))
+                .map(|bond| bond.type_)
+        };
+        assert_eq!(bonds.len(), 8);
+        for i in 0..6 {
+            assert_eq!(type_of(i, (i + 1) % 6), Some(BondType::Aromatic));
+        }
+        assert_eq!(type_of(0, 6), Some(BondType::Single));
+        assert_eq!(type_of(6, 7), Some(BondType::Double));
+    }
+
+    #[test]
+    fn test_assigns_triple_and_amide_bonds() {
+        // acetonitrile
+        let nitrile = atoms(&[
+            (Element::C, [0.0, 0.0, 0.0]),
+            (Element::C, [1.46, 0.0, 0.0]),
+            (Element::N, [2.62, 0.0, 0.0]),
+        ]);
+        let types: Vec<BondType> = perceive_bonds(&nitrile).iter().map(|b| b.type_).collect();
+        assert_eq!(types, vec![BondType::Single, BondType::Triple]);
+
+        // acetamide without hydrogens
+        let amide = atoms(&[
+            (Element::C, [0.0, 0.0, 0.0]),
+            (Element::C, [1.52, 0.0, 0.0]),
+            (Element::O, [2.13, 1.06, 0.0]),
+            (Element::N, [2.18, -1.15, 0.0]),
+        ]);
+        let types: Vec<BondType> = perceive_bonds(&amide).iter().map(|b| b.type_).collect();
+        assert_eq!(
+            types,
+            vec![BondType::Single, BondType::Double, BondType::Amide]
+        );
+    }
+}
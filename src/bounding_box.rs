@@ -40,6 +40,30 @@ impl BoundingBox {
     pub fn mid_z(&self) -> f32 {
         self.min_z + self.dist_z() / 2.
     }
+
+    /// the box grown by the margin on each side
+    pub fn padded(&self, margin: f32) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x - margin,
+            max_x: self.max_x + margin,
+            min_y: self.min_y - margin,
+            max_y: self.max_y + margin,
+            min_z: self.min_z - margin,
+            max_z: self.max_z + margin,
+        }
+    }
+
+    /// smallest box containing both boxes
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x.min(other.min_x),
+            max_x: self.max_x.max(other.max_x),
+            min_y: self.min_y.min(other.min_y),
+            max_y: self.max_y.max(other.max_y),
+            min_z: self.min_z.min(other.min_z),
+            max_z: self.max_z.max(other.max_z),
+        }
+    }
 }
 
 pub fn bounding_box_for(vertices: &[[f32; 3]]) -> BoundingBox {
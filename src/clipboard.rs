@@ -0,0 +1,35 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+
+/// kept alive, as on linux the copied text is only available while the clipboard owner exists
+#[cfg(not(target_arch = "wasm32"))]
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+/// copies text to the system clipboard
+pub fn copy_text(text: &str) {
+    if let Err(e) = write_clipboard(text) {
+        eprintln!("Couldn't copy to clipboard: {}", e);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_clipboard(text: &str) -> Result<()> {
+    let mut clipboard = CLIPBOARD.lock().map_err(|e| anyhow!("{}", e))?;
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new()?);
+    }
+    if let Some(clipboard) = clipboard.as_mut() {
+        clipboard.set_text(text)?;
+    }
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_clipboard(text: &str) -> Result<()> {
+    let window = web_sys::window().ok_or_else(|| anyhow!("No window"))?;
+    // the promise isn't awaited: the browser may still reject the write, e.g. if the page isn't focused
+    let _ = window.navigator().clipboard().write_text(text);
+    Ok(())
+}
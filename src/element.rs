@@ -1,11 +1,456 @@
-#[derive(Debug, Clone)]
+/// chemical elements, the discriminant is the atomic number.
+/// `Du` (dummy atom) and `Lp` (lone pair) are pseudo-atoms used by mol2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Element {
-    H,
-    C,
-    N,
-    O,
-    F,
-    P,
-    S,
-    Ca,
+    Du = 0,
+    H = 1,
+    He = 2,
+    Li = 3,
+    Be = 4,
+    B = 5,
+    C = 6,
+    N = 7,
+    O = 8,
+    F = 9,
+    Ne = 10,
+    Na = 11,
+    Mg = 12,
+    Al = 13,
+    Si = 14,
+    P = 15,
+    S = 16,
+    Cl = 17,
+    Ar = 18,
+    K = 19,
+    Ca = 20,
+    Sc = 21,
+    Ti = 22,
+    V = 23,
+    Cr = 24,
+    Mn = 25,
+    Fe = 26,
+    Co = 27,
+    Ni = 28,
+    Cu = 29,
+    Zn = 30,
+    Ga = 31,
+    Ge = 32,
+    As = 33,
+    Se = 34,
+    Br = 35,
+    Kr = 36,
+    Rb = 37,
+    Sr = 38,
+    Y = 39,
+    Zr = 40,
+    Nb = 41,
+    Mo = 42,
+    Tc = 43,
+    Ru = 44,
+    Rh = 45,
+    Pd = 46,
+    Ag = 47,
+    Cd = 48,
+    In = 49,
+    Sn = 50,
+    Sb = 51,
+    Te = 52,
+    I = 53,
+    Xe = 54,
+    Cs = 55,
+    Ba = 56,
+    La = 57,
+    Ce = 58,
+    Pr = 59,
+    Nd = 60,
+    Pm = 61,
+    Sm = 62,
+    Eu = 63,
+    Gd = 64,
+    Tb = 65,
+    Dy = 66,
+    Ho = 67,
+    Er = 68,
+    Tm = 69,
+    Yb = 70,
+    Lu = 71,
+    Hf = 72,
+    Ta = 73,
+    W = 74,
+    Re = 75,
+    Os = 76,
+    Ir = 77,
+    Pt = 78,
+    Au = 79,
+    Hg = 80,
+    Tl = 81,
+    Pb = 82,
+    Bi = 83,
+    Po = 84,
+    At = 85,
+    Rn = 86,
+    Fr = 87,
+    Ra = 88,
+    Ac = 89,
+    Th = 90,
+    Pa = 91,
+    U = 92,
+    Np = 93,
+    Pu = 94,
+    Am = 95,
+    Cm = 96,
+    Bk = 97,
+    Cf = 98,
+    Es = 99,
+    Fm = 100,
+    Md = 101,
+    No = 102,
+    Lr = 103,
+    Rf = 104,
+    Db = 105,
+    Sg = 106,
+    Bh = 107,
+    Hs = 108,
+    Mt = 109,
+    Ds = 110,
+    Rg = 111,
+    Cn = 112,
+    Nh = 113,
+    Fl = 114,
+    Mc = 115,
+    Lv = 116,
+    Ts = 117,
+    Og = 118,
+    Lp = 119,
+}
+
+struct ElementData {
+    symbol: &'static str,
+    /// single bond covalent radius in angstrom
+    covalent_radius: f32,
+    /// van der waals radius in angstrom
+    van_der_waals_radius: f32,
+    /// jmol (cpk) color, rgb
+    color: [u8; 3],
+}
+
+const fn data(
+    symbol: &'static str,
+    covalent_radius: f32,
+    van_der_waals_radius: f32,
+    color: [u8; 3],
+) -> ElementData {
+    ElementData {
+        symbol,
+        covalent_radius,
+        van_der_waals_radius,
+        color,
+    }
+}
+
+/// indexed by discriminant (atomic number)
+const ELEMENTS: [ElementData; 120] = [
+    data("Du", 0.0, 1.0, [255, 20, 147]),
+    data("H", 0.31, 1.2, [255, 255, 255]),
+    data("He", 0.28, 1.4, [217, 255, 255]),
+    data("Li", 1.28, 1.82, [204, 128, 255]),
+    data("Be", 0.96, 1.53, [194, 255, 0]),
+    data("B", 0.84, 1.92, [255, 181, 181]),
+    data("C", 0.76, 1.7, [144, 144, 144]),
+    data("N", 0.71, 1.55, [48, 80, 248]),
+    data("O", 0.66, 1.52, [255, 13, 13]),
+    data("F", 0.57, 1.47, [144, 224, 80]),
+    data("Ne", 0.58, 1.54, [179, 227, 245]),
+    data("Na", 1.66, 2.27, [171, 92, 242]),
+    data("Mg", 1.41, 1.73, [138, 255, 0]),
+    data("Al", 1.21, 1.84, [191, 166, 166]),
+    data("Si", 1.11, 2.1, [240, 200, 160]),
+    data("P", 1.07, 1.8, [255, 128, 0]),
+    data("S", 1.05, 1.8, [255, 255, 48]),
+    data("Cl", 1.02, 1.75, [31, 240, 31]),
+    data("Ar", 1.06, 1.88, [128, 209, 227]),
+    data("K", 2.03, 2.75, [143, 64, 212]),
+    data("Ca", 1.76, 2.31, [61, 255, 0]),
+    data("Sc", 1.7, 2.11, [230, 230, 230]),
+    data("Ti", 1.6, 1.87, [191, 194, 199]),
+    data("V", 1.53, 1.79, [166, 166, 171]),
+    data("Cr", 1.39, 1.89, [138, 153, 199]),
+    data("Mn", 1.39, 1.97, [156, 122, 199]),
+    data("Fe", 1.32, 1.94, [224, 102, 51]),
+    data("Co", 1.26, 1.92, [240, 144, 160]),
+    data("Ni", 1.24, 1.63, [80, 208, 80]),
+    data("Cu", 1.32, 1.4, [200, 128, 51]),
+    data("Zn", 1.22, 1.39, [125, 128, 176]),
+    data("Ga", 1.22, 1.87, [194, 143, 143]),
+    data("Ge", 1.2, 2.11, [102, 143, 143]),
+    data("As", 1.19, 1.85, [189, 128, 227]),
+    data("Se", 1.2, 1.9, [255, 161, 0]),
+    data("Br", 1.2, 1.85, [166, 41, 41]),
+    data("Kr", 1.16, 2.02, [92, 184, 209]),
+    data("Rb", 2.2, 3.03, [112, 46, 176]),
+    data("Sr", 1.95, 2.49, [0, 255, 0]),
+    data("Y", 1.9, 2.19, [148, 255, 255]),
+    data("Zr", 1.75, 1.86, [148, 224, 224]),
+    data("Nb", 1.64, 2.07, [115, 194, 201]),
+    data("Mo", 1.54, 2.09, [84, 181, 181]),
+    data("Tc", 1.47, 2.09, [59, 158, 158]),
+    data("Ru", 1.46, 2.07, [36, 143, 143]),
+    data("Rh", 1.42, 1.95, [10, 125, 140]),
+    data("Pd", 1.39, 1.63, [0, 105, 133]),
+    data("Ag", 1.45, 1.72, [192, 192, 192]),
+    data("Cd", 1.44, 1.58, [255, 217, 143]),
+    data("In", 1.42, 1.93, [166, 117, 115]),
+    data("Sn", 1.39, 2.17, [102, 128, 128]),
+    data("Sb", 1.39, 2.06, [158, 99, 181]),
+    data("Te", 1.38, 2.06, [212, 122, 0]),
+    data("I", 1.39, 1.98, [148, 0, 148]),
+    data("Xe", 1.4, 2.16, [66, 158, 176]),
+    data("Cs", 2.44, 3.43, [87, 23, 143]),
+    data("Ba", 2.15, 2.68, [0, 201, 0]),
+    data("La", 2.07, 2.4, [112, 212, 255]),
+    data("Ce", 2.04, 2.35, [255, 255, 199]),
+    data("Pr", 2.03, 2.39, [217, 255, 199]),
+    data("Nd", 2.01, 2.29, [199, 255, 199]),
+    data("Pm", 1.99, 2.36, [163, 255, 199]),
+    data("Sm", 1.98, 2.29, [143, 255, 199]),
+    data("Eu", 1.98, 2.33, [97, 255, 199]),
+    data("Gd", 1.96, 2.37, [69, 255, 199]),
+    data("Tb", 1.94, 2.21, [48, 255, 199]),
+    data("Dy", 1.92, 2.29, [31, 255, 199]),
+    data("Ho", 1.92, 2.16, [0, 255, 156]),
+    data("Er", 1.89, 2.35, [0, 230, 117]),
+    data("Tm", 1.9, 2.27, [0, 212, 82]),
+    data("Yb", 1.87, 2.42, [0, 191, 56]),
+    data("Lu", 1.87, 2.21, [0, 171, 36]),
+    data("Hf", 1.75, 2.12, [77, 194, 255]),
+    data("Ta", 1.7, 2.17, [77, 166, 255]),
+    data("W", 1.62, 2.1, [33, 148, 214]),
+    data("Re", 1.51, 2.17, [38, 125, 171]),
+    data("Os", 1.44, 2.16, [38, 102, 150]),
+    data("Ir", 1.41, 2.02, [23, 84, 135]),
+    data("Pt", 1.36, 1.75, [208, 208, 224]),
+    data("Au", 1.36, 1.66, [255, 209, 35]),
+    data("Hg", 1.32, 1.55, [184, 184, 208]),
+    data("Tl", 1.45, 1.96, [166, 84, 77]),
+    data("Pb", 1.46, 2.02, [87, 89, 97]),
+    data("Bi", 1.48, 2.07, [158, 79, 181]),
+    data("Po", 1.4, 1.97, [171, 92, 0]),
+    data("At", 1.5, 2.02, [117, 79, 69]),
+    data("Rn", 1.5, 2.2, [66, 130, 150]),
+    data("Fr", 2.6, 3.48, [66, 0, 102]),
+    data("Ra", 2.21, 2.83, [0, 125, 0]),
+    data("Ac", 2.15, 2.47, [112, 171, 250]),
+    data("Th", 2.06, 2.45, [0, 186, 255]),
+    data("Pa", 2.0, 2.43, [0, 161, 255]),
+    data("U", 1.96, 1.86, [0, 143, 255]),
+    data("Np", 1.9, 2.39, [0, 128, 255]),
+    data("Pu", 1.87, 2.43, [0, 107, 255]),
+    data("Am", 1.8, 2.44, [84, 92, 242]),
+    data("Cm", 1.69, 2.45, [120, 92, 227]),
+    data("Bk", 1.5, 2.44, [138, 79, 227]),
+    data("Cf", 1.5, 2.45, [161, 54, 212]),
+    data("Es", 1.5, 2.45, [179, 31, 212]),
+    data("Fm", 1.5, 2.45, [179, 31, 186]),
+    data("Md", 1.5, 2.46, [179, 13, 166]),
+    data("No", 1.5, 2.46, [189, 13, 135]),
+    data("Lr", 1.5, 2.46, [199, 0, 102]),
+    data("Rf", 1.5, 2.0, [204, 0, 89]),
+    data("Db", 1.5, 2.0, [209, 0, 79]),
+    data("Sg", 1.5, 2.0, [217, 0, 69]),
+    data("Bh", 1.5, 2.0, [224, 0, 56]),
+    data("Hs", 1.5, 2.0, [230, 0, 46]),
+    data("Mt", 1.5, 2.0, [235, 0, 38]),
+    data("Ds", 1.5, 2.0, [235, 0, 38]),
+    data("Rg", 1.5, 2.0, [235, 0, 38]),
+    data("Cn", 1.5, 2.0, [235, 0, 38]),
+    data("Nh", 1.5, 2.0, [235, 0, 38]),
+    data("Fl", 1.5, 2.0, [235, 0, 38]),
+    data("Mc", 1.5, 2.0, [235, 0, 38]),
+    data("Lv", 1.5, 2.0, [235, 0, 38]),
+    data("Ts", 1.5, 2.0, [235, 0, 38]),
+    data("Og", 1.5, 2.0, [235, 0, 38]),
+    data("LP", 0.0, 0.5, [255, 192, 203]),
+];
+
+/// all elements, in discriminant order
+const ALL: [Element; 120] = [
+    Element::Du,
+    Element::H,
+    Element::He,
+    Element::Li,
+    Element::Be,
+    Element::B,
+    Element::C,
+    Element::N,
+    Element::O,
+    Element::F,
+    Element::Ne,
+    Element::Na,
+    Element::Mg,
+    Element::Al,
+    Element::Si,
+    Element::P,
+    Element::S,
+    Element::Cl,
+    Element::Ar,
+    Element::K,
+    Element::Ca,
+    Element::Sc,
+    Element::Ti,
+    Element::V,
+    Element::Cr,
+    Element::Mn,
+    Element::Fe,
+    Element::Co,
+    Element::Ni,
+    Element::Cu,
+    Element::Zn,
+    Element::Ga,
+    Element::Ge,
+    Element::As,
+    Element::Se,
+    Element::Br,
+    Element::Kr,
+    Element::Rb,
+    Element::Sr,
+    Element::Y,
+    Element::Zr,
+    Element::Nb,
+    Element::Mo,
+    Element::Tc,
+    Element::Ru,
+    Element::Rh,
+    Element::Pd,
+    Element::Ag,
+    Element::Cd,
+    Element::In,
+    Element::Sn,
+    Element::Sb,
+    Element::Te,
+    Element::I,
+    Element::Xe,
+    Element::Cs,
+    Element::Ba,
+    Element::La,
+    Element::Ce,
+    Element::Pr,
+    Element::Nd,
+    Element::Pm,
+    Element::Sm,
+    Element::Eu,
+    Element::Gd,
+    Element::Tb,
+    Element::Dy,
+    Element::Ho,
+    Element::Er,
+    Element::Tm,
+    Element::Yb,
+    Element::Lu,
+    Element::Hf,
+    Element::Ta,
+    Element::W,
+    Element::Re,
+    Element::Os,
+    Element::Ir,
+    Element::Pt,
+    Element::Au,
+    Element::Hg,
+    Element::Tl,
+    Element::Pb,
+    Element::Bi,
+    Element::Po,
+    Element::At,
+    Element::Rn,
+    Element::Fr,
+    Element::Ra,
+    Element::Ac,
+    Element::Th,
+    Element::Pa,
+    Element::U,
+    Element::Np,
+    Element::Pu,
+    Element::Am,
+    Element::Cm,
+    Element::Bk,
+    Element::Cf,
+    Element::Es,
+    Element::Fm,
+    Element::Md,
+    Element::No,
+    Element::Lr,
+    Element::Rf,
+    Element::Db,
+    Element::Sg,
+    Element::Bh,
+    Element::Hs,
+    Element::Mt,
+    Element::Ds,
+    Element::Rg,
+    Element::Cn,
+    Element::Nh,
+    Element::Fl,
+    Element::Mc,
+    Element::Lv,
+    Element::Ts,
+    Element::Og,
+    Element::Lp,
+];
+
+impl Element {
+    fn data(&self) -> &'static ElementData {
+        &ELEMENTS[*self as usize]
+    }
+
+    /// element for a symbol as written in mol2 / sdf files (e.g. "C", "Cl", "Du", "LP")
+    pub fn from_symbol(symbol: &str) -> Option<Element> {
+        ALL.iter().find(|e| e.symbol() == symbol).copied()
+    }
+
+    /// element for an atomic number, none for the pseudo-atoms
+    pub fn from_number(number: u32) -> Option<Element> {
+        ALL.get(number as usize)
+            .copied()
+            .filter(|e| !matches!(e, Element::Du | Element::Lp))
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        self.data().symbol
+    }
+
+    /// single bond covalent radius in angstrom, used to infer bonds by distance.
+    /// 0 for the pseudo-atoms, so they never get inferred bonds
+    pub fn covalent_radius(&self) -> f32 {
+        self.data().covalent_radius
+    }
+
+    /// van der waals radius in angstrom
+    pub fn van_der_waals_radius(&self) -> f32 {
+        self.data().van_der_waals_radius
+    }
+
+    /// jmol (cpk) color as srgb bytes
+    pub fn color(&self) -> [u8; 3] {
+        self.data().color
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Element, ALL};
+
+    #[test]
+    fn test_table_in_discriminant_order() {
+        for (index, element) in ALL.iter().enumerate() {
+            assert_eq!(*element as usize, index);
+        }
+    }
+
+    #[test]
+    fn test_from_symbol() {
+        assert_eq!(Element::from_symbol("Cl"), Some(Element::Cl));
+        assert_eq!(Element::from_symbol("Zn"), Some(Element::Zn));
+        assert_eq!(Element::from_symbol("LP"), Some(Element::Lp));
+        assert_eq!(Element::from_symbol("Du"), Some(Element::Du));
+        assert_eq!(Element::from_symbol("Xx"), None);
+    }
 }
@@ -19,7 +19,10 @@ impl Plugin for EmbeddedAssetPlugin {
         embedded_asset!(app, "asset/fonts/FiraMono-Medium.ttf");
         embedded_asset!(app, "asset/117_ideal.mol2");
         embedded_asset!(app, "asset/1ubq.mol2");
+        embedded_asset!(app, "asset/1ubq.pdb");
         embedded_asset!(app, "asset/benzene.mol2");
         embedded_asset!(app, "asset/2bbv.mol2");
+        embedded_asset!(app, "asset/examples.sdf");
+        embedded_asset!(app, "asset/shaders/impostor.wgsl");
     }
 }
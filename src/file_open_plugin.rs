@@ -0,0 +1,245 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::asset::io::file::FileAssetReader;
+use bevy::{
+    asset::{
+        io::{
+            memory::{Dir, MemoryAssetReader},
+            AssetSource,
+        },
+        LoadState,
+    },
+    prelude::*,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{scene::resource::MolScene, ui::system::load_mol_file};
+
+/// extensions offered by the file picker, see `load_mol_file`
+const OPEN_FILE_EXTENSIONS: [&str; 11] = [
+    "mol2", "sdf", "sd", "mol", "pdb", "ent", "cif", "mmcif", "xyz", "dcd", "xtc",
+];
+
+/// files passed from js, picked up by `load_opened_files` on the next frame
+static PENDING_FILES: Mutex<Vec<(String, Vec<u8>)>> = Mutex::new(Vec::new());
+/// files picked in the native file dialog, picked up by `handle_opened_paths` on the next frame
+static PENDING_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// opens a file selected by the user (file picker, drag-and-drop on the page, ...)
+/// the extension of `name` determines the loader
+#[wasm_bindgen]
+pub fn load_file_bytes(name: String, bytes: Vec<u8>) {
+    queue_file(name, bytes);
+}
+
+/// loads files opened by the user (file dialog or drag-and-drop on native, file input or bytes passed from js on wasm)
+/// via an in-memory asset source, so they go through the same loaders as the embedded examples.
+/// must be added before `DefaultPlugins`, as asset sources have to be registered before the `AssetPlugin`
+pub struct FileOpenPlugin;
+
+impl Plugin for FileOpenPlugin {
+    fn build(&self, app: &mut App) {
+        let dir = Dir::default();
+        let reader_dir = dir.clone();
+        app.register_asset_source(
+            "memory",
+            AssetSource::build().with_reader(move || {
+                Box::new(MemoryAssetReader {
+                    root: reader_dir.clone(),
+                })
+            }),
+        );
+        // opened trajectories are read from the file system, as they can be too large to copy into memory
+        #[cfg(not(target_arch = "wasm32"))]
+        app.register_asset_source(
+            "file",
+            AssetSource::build().with_reader(|| Box::new(FileAssetReader::new(""))),
+        );
+        app.insert_resource(OpenedFiles {
+            dir,
+            count: 0,
+            in_memory: vec![],
+        })
+        .add_systems(
+            Update,
+            (handle_opened_paths, load_opened_files, release_opened_files).chain(),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct OpenedFiles {
+    dir: Dir,
+    // used to give each opened file its own path,
+    // as the asset server would otherwise return the cached asset when opening a file again
+    count: usize,
+    /// paths in `dir` of the files whose data hasn't been released yet
+    in_memory: Vec<String>,
+}
+
+fn queue_file(name: String, bytes: Vec<u8>) {
+    match PENDING_FILES.lock() {
+        Ok(mut files) => files.push((name, bytes)),
+        Err(e) => eprintln!("Couldn't queue opened file: {}", e),
+    }
+}
+
+/// shows a file picker: a native file dialog, or a file input on wasm
+pub fn open_file_dialog() {
+    #[cfg(target_arch = "wasm32")]
+    if let Err(e) = show_file_input() {
+        eprintln!("Couldn't show file picker: {}", e);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = rfd::FileDialog::new()
+        .add_filter("Molecule files", &OPEN_FILE_EXTENSIONS)
+        .pick_file()
+    {
+        match PENDING_PATHS.lock() {
+            Ok(mut paths) => paths.push(path),
+            Err(e) => eprintln!("Couldn't queue opened file: {}", e),
+        }
+    }
+}
+
+/// clicks a temporary file input, whose selected file is read and passed to `queue_file`
+#[cfg(target_arch = "wasm32")]
+fn show_file_input() -> anyhow::Result<()> {
+    use anyhow::anyhow;
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use wasm_bindgen_futures::JsFuture;
+
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| anyhow!("No document"))?;
+    let input: web_sys::HtmlInputElement = document
+        .create_element("input")
+        .map_err(|e| anyhow!("{:?}", e))?
+        .dyn_into()
+        .map_err(|e| anyhow!("{:?}", e))?;
+    input.set_type("file");
+    let accept: Vec<String> = OPEN_FILE_EXTENSIONS
+        .iter()
+        .map(|e| format!(".{}", e))
+        .collect();
+    input.set_accept(&accept.join(","));
+
+    let onchange = Closure::once_into_js(move |event: web_sys::Event| {
+        let Some(file) = event
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+            .and_then(|input| input.files())
+            .and_then(|files| files.get(0))
+        else {
+            return;
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            match JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => queue_file(file.name(), js_sys::Uint8Array::new(&buffer).to_vec()),
+                Err(e) => eprintln!("Couldn't read {}: {:?}", file.name(), e),
+            }
+        });
+    });
+    input.set_onchange(Some(onchange.unchecked_ref()));
+    input.click();
+    Ok(())
+}
+
+/// files dropped onto the window or picked in the file dialog (native)
+fn handle_opened_paths(
+    mut events: EventReader<FileDragAndDrop>,
+    asset_server: Res<AssetServer>,
+    mut scene: ResMut<MolScene>,
+) {
+    let mut paths: Vec<PathBuf> = events
+        .read()
+        .filter_map(|event| match event {
+            FileDragAndDrop::DroppedFile { path_buf, .. } => Some(path_buf.clone()),
+            _ => None,
+        })
+        .collect();
+    if let Ok(mut picked) = PENDING_PATHS.lock() {
+        paths.append(&mut picked);
+    }
+
+    for path_buf in paths {
+        let extension = path_buf
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if let "dcd" | "xtc" = extension.as_str() {
+            let path = format!("file://{}", path_buf.display());
+            if let Err(e) = load_mol_file(&asset_server, &mut scene, path) {
+                eprintln!("Couldn't open {:?}: {}", path_buf, e);
+            }
+            continue;
+        }
+
+        let name = path_buf
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match std::fs::read(&path_buf) {
+            Ok(bytes) => queue_file(name, bytes),
+            Err(e) => {
+                eprintln!("Couldn't read opened file {:?}: {}", path_buf, e);
+                scene.open_error = Some(format!("Couldn't read {}: {}", name, e));
+            }
+        }
+    }
+}
+
+fn load_opened_files(
+    mut opened_files: ResMut<OpenedFiles>,
+    asset_server: Res<AssetServer>,
+    mut scene: ResMut<MolScene>,
+) {
+    let files = match PENDING_FILES.lock() {
+        Ok(mut files) => std::mem::take(&mut *files),
+        Err(_) => return,
+    };
+    for (name, bytes) in files {
+        opened_files.count += 1;
+        let path = format!("{}/{}", opened_files.count, name);
+        opened_files.dir.insert_asset(Path::new(&path), bytes);
+        opened_files.in_memory.push(path.clone());
+
+        if let Err(e) = load_mol_file(&asset_server, &mut scene, format!("memory://{}", path)) {
+            eprintln!("Couldn't open {}: {}", name, e);
+        }
+    }
+}
+
+/// frees the data of opened files once loaded, so memory doesn't grow with every opened file.
+/// the shown trajectory is kept, as its frames are read from the file while playing
+fn release_opened_files(
+    mut opened_files: ResMut<OpenedFiles>,
+    asset_server: Res<AssetServer>,
+    scene: Res<MolScene>,
+) {
+    let trajectory_path = scene
+        .trajectory
+        .as_ref()
+        .and_then(|h| h.path())
+        .map(|p| p.to_string());
+    let OpenedFiles { dir, in_memory, .. } = &mut *opened_files;
+    in_memory.retain(|path| {
+        let asset_path = format!("memory://{}", path);
+        // no id if the file wasn't loaded (e.g. unsupported extension) or the asset was dropped
+        let loading = asset_server
+            .get_path_id(asset_path.as_str())
+            .and_then(|id| asset_server.get_load_state(id))
+            .is_some_and(|state| matches!(state, LoadState::NotLoaded | LoadState::Loading));
+        if loading || trajectory_path.as_deref() == Some(asset_path.as_str()) {
+            return true;
+        }
+        // `Dir` can't remove files, so the data is replaced with an empty file
+        dir.insert_asset(Path::new(path), Vec::<u8>::new());
+        false
+    });
+}
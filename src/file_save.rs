@@ -0,0 +1,54 @@
+use anyhow::Result;
+#[cfg(target_arch = "wasm32")]
+use {anyhow::anyhow, wasm_bindgen::JsCast};
+
+/// saves a file exported by the user: downloaded on wasm,
+/// written to a path picked in a save dialog on native
+pub fn save_file(name: &str, contents: &str) -> Result<()> {
+    #[cfg(target_arch = "wasm32")]
+    download(name, contents)?;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let dialog = rfd::FileDialog::new().set_file_name(name);
+        let dialog = match name.rsplit_once('.') {
+            Some((_, extension)) => dialog.add_filter(extension, &[extension]),
+            None => dialog,
+        };
+        // none if the user cancelled the dialog
+        if let Some(path) = dialog.save_file() {
+            std::fs::write(&path, contents)?;
+            println!("Saved {}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// offers `contents` as a download named `name`, via a temporary object url and anchor element
+#[cfg(target_arch = "wasm32")]
+fn download(name: &str, contents: &str) -> Result<()> {
+    let js_err = |e: wasm_bindgen::JsValue| anyhow!("{:?}", e);
+
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| anyhow!("No document"))?;
+
+    let parts = js_sys::Array::of1(&contents.into());
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type("text/plain");
+    let blob =
+        web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).map_err(js_err)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_err)?;
+
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(js_err)?
+        .dyn_into()
+        .map_err(|e| anyhow!("{:?}", e))?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+
+    web_sys::Url::revoke_object_url(&url).map_err(js_err)
+}
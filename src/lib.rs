@@ -1,16 +1,30 @@
+mod bond_perception;
 mod bounding_box;
 #[cfg(test)]
 mod chemcore_exploration_tests;
+mod clipboard;
 mod debug;
 mod defocus;
 mod element;
 mod embedded_asset_plugin;
+mod file_open_plugin;
+mod file_save;
 mod init_wasm_log;
 mod mol2_asset_plugin;
+mod mol2_writer;
+mod mol_collection;
+mod molecule;
+mod pdb_asset_plugin;
 mod rotator;
 mod scene;
+mod sdf_asset_plugin;
+mod secondary_structure;
+mod smiles;
+mod surface;
 mod system_3d;
+mod trajectory_asset_plugin;
 mod ui;
+mod xyz_asset_plugin;
 
 use bevy::app::App;
 use scene::add_mol_scene;
@@ -1,27 +1,35 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
 use anyhow::{anyhow, Result};
 use bevy::app::{App, Plugin};
 use bevy::asset::io::Reader;
-use bevy::asset::{Asset, AssetApp, AssetLoader, LoadContext};
+use bevy::asset::{AssetApp, AssetLoader, LoadContext};
+use bevy::math::Vec3;
 use bevy::tasks::futures_lite::io::BufReader;
 use bevy::tasks::futures_lite::{AsyncBufReadExt, StreamExt};
-use bevy::{math::Vec3, reflect::TypePath};
 
-use crate::bounding_box::BoundingBox;
 use crate::element::Element;
+use crate::mol_collection::MolCollection;
+use crate::molecule::{Atom, AtomSet, Bond, BondType, Molecule, UnitCell};
 
 pub struct Mol2AssetPlugin;
 
 impl Plugin for Mol2AssetPlugin {
     fn build(&self, app: &mut App) {
-        app.init_asset::<Mol2Molecule>()
+        app.init_asset::<Molecule>()
+            .init_asset::<MolCollection>()
             .register_asset_loader(Mol2AssetLoader);
     }
 }
 
+/// loads all the molecules of a mol2 file,
+/// each molecule is added as a labeled `Molecule` ("Molecule0", "Molecule1", ...)
 pub struct Mol2AssetLoader;
 
 impl AssetLoader for Mol2AssetLoader {
-    type Asset = Mol2Molecule;
+    type Asset = MolCollection;
     type Settings = ();
     type Error = anyhow::Error;
 
@@ -29,99 +37,411 @@ impl AssetLoader for Mol2AssetLoader {
         &'a self,
         reader: &'a mut Reader<'_>,
         _settings: &'a (),
-        _load_context: &'a mut LoadContext<'_>,
-    ) -> Result<Mol2Molecule, Self::Error> {
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<MolCollection, Self::Error> {
         let buffered_reader = BufReader::new(reader);
 
-        let mut parsing_atoms = false;
-        let mut parsing_bonds = false;
-        let mut parsing_mol = false;
-
-        let mut atoms = vec![];
-        let mut bonds = vec![];
-
         let mut lines = buffered_reader.lines();
 
-        let mut mol_name = None;
-
+        let mut parser = Mol2Parser::default();
         while let Some(line) = lines.next().await {
             let line = line?;
+            parser.parse_line(&line)?;
+        }
+        let mols = parser.finish()?;
 
-            match parse_mol2_line(&line) {
-                ProcessMol2LineResult::Empty => continue,
-                ProcessMol2LineResult::Header(header) => match header {
-                    Header::Atom => {
-                        parsing_atoms = true;
-                        parsing_bonds = false;
-                        parsing_mol = false;
-                    }
-                    Header::Bond => {
-                        parsing_bonds = true;
-                        parsing_atoms = false;
-                        parsing_mol = false;
-                    }
-                    Header::Mol => {
-                        parsing_bonds = false;
-                    parsing_atoms = false;
-                        parsing_mol = true;
-                    }
-                    // we don't use this yet, ignore
-                    // entries belonging to section will also be ignored
-                    Header::Other => {
-                        parsing_bonds = false;
-                        parsing_atoms = false;
-                        parsing_mol = false;
+        println!("finished parsing mol2 file: molecules: {}", mols.len());
+        for mol in &mols {
+            println!(
+                "{}: atoms: {}, bonds: {}",
+                mol.name,
+                mol.atoms.len(),
+                mol.bonds.len()
+            );
+        }
+
+        let mut molecules = vec![];
+        for (index, mol) in mols.iter().enumerate() {
+            let molecule = mol.to_molecule()?;
+            molecules.push(load_context.add_labeled_asset(format!("Molecule{}", index), molecule));
+        }
+        Ok(MolCollection { molecules })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["mol2"]
+    }
+}
+
+/// line by line mol2 parser. a new MOLECULE record finishes the current molecule
+#[derive(Default)]
+struct Mol2Parser {
+    /// 1-based number of the line being parsed
+    line: usize,
+    section: Mol2Section,
+    /// number of entries parsed in the current section
+    section_entries: usize,
+
+    mol_name: Option<String>,
+    header: Mol2Header,
+    atoms: Vec<Mol2Atom>,
+    bonds: Vec<Mol2Bond>,
+    substructures: Vec<Mol2Substructure>,
+    crysin: Option<Mol2Crysin>,
+    sets: Vec<Mol2Set>,
+    /// index in `atoms` by atom id, of the current molecule, to validate the atoms referenced by other records
+    atom_indices: HashMap<i32, usize>,
+
+    /// set whose members (or rule) are on the next line(s),
+    /// with the number of members (known after the first line of members)
+    pending_set: Option<(Mol2Set, Option<usize>)>,
+    /// atom (index) whose attributes are on the next lines, with the number of remaining attributes
+    pending_atom_attrs: Option<(usize, usize)>,
+
+    molecules: Vec<Mol2Molecule>,
+}
+
+impl Mol2Parser {
+    fn parse_line(&mut self, line: &str) -> Result<(), Mol2ParseError> {
+        self.line += 1;
+        match parse_mol2_line(line) {
+            ProcessMol2LineResult::Empty => {}
+            ProcessMol2LineResult::Header(header) => {
+                self.finish_section()?;
+                if header == Mol2Section::Molecule {
+                    self.finish_molecule()?;
+                }
+                self.section = header;
+                self.section_entries = 0;
+            }
+            ProcessMol2LineResult::Entity { parts } => {
+                self.parse_entry(&parts).map_err(|e| self.error(e))?;
+                self.section_entries += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_entry(&mut self, parts: &[&str]) -> Result<(), ColumnError> {
+        match self.section {
+            Mol2Section::Molecule => self.parse_molecule_entry(parts)?,
+            Mol2Section::Atom => {
+                let atom = parse_atom_line(parts)?;
+                if self.atom_indices.contains_key(&atom.id) {
+                    return Err(ColumnError::new(0, "Duplicate atom id", parts[0]));
+                }
+                self.atom_indices.insert(atom.id, self.atoms.len());
+                self.atoms.push(atom);
+            }
+            Mol2Section::Bond => {
+                let bond = parse_bond_line(parts)?;
+                for (column, atom) in [(1, bond.atom1), (2, bond.atom2)] {
+                    self.atom_index(atom as i32, parts, column)?;
+                }
+                self.bonds.push(bond);
+            }
+            Mol2Section::Substructure => {
+                let substructure = parse_substructure_line(parts)?;
+                self.substructures.push(substructure);
+            }
+            // a single entry, further lines are ignored
+            Mol2Section::Crysin if self.crysin.is_none() => {
+                self.crysin = Some(parse_crysin_line(parts)?);
+            }
+            Mol2Section::Set => match self.pending_set.take() {
+                Some((mut set, mut count)) => {
+                    // lines ending with a backslash are continued on the next line
+                    let continued = parts.last() == Some(&"\\");
+                    let parts = if continued {
+                        &parts[..parts.len() - 1]
+                    } else {
+                        parts
+                    };
+                    if set.type_ == "STATIC" {
+                        // the first line starts with the number of members
+                        let first = if count.is_none() {
+                            count = Some(parse_column(parts, 0, "number of members")?);
+                            1
+                        } else {
+                            0
+                        };
+                        for index in first..parts.len() {
+                            set.members.push(parse_column(parts, index, "member id")?);
+                        }
+                    } else {
+                        let rule = set.rule.get_or_insert_with(String::new);
+                        if !rule.is_empty() {
+                            rule.push(' ');
+                        }
+                        rule.push_str(&parts.join(" "));
                     }
-                },
-                // assumption: &parts has correct length for respective handlers
-                ProcessMol2LineResult::Entity { parts } => {
-                    if parsing_atoms {
-                        let atom = parse_atom_line(&parts)?;
-                        atoms.push(atom);
-                    } else if parsing_bonds {
-                        let bond = parse_bond_line(&parts)?;
-                        bonds.push(bond);
-                    } else if parsing_mol {
-                        // we just care about the name for now: finish parsing this section
-                        parsing_mol = false;
-                        let parsed_mol_name = parse_mol_name_line(&parts)?;
-                        mol_name = Some(parsed_mol_name);
+                    if continued || set.members.len() < count.unwrap_or(0) {
+                        self.pending_set = Some((set, count));
+                    } else {
+                        self.sets.push(set);
                     }
                 }
+                None => self.pending_set = Some((parse_set_line(parts)?, None)),
+            },
+            Mol2Section::UnityAtomAttr => match self.pending_atom_attrs {
+                Some((index, remaining)) => {
+                    let name = column(parts, 0, "attribute name")?.to_string();
+                    let value = parts[1..].join(" ");
+                    self.atoms[index].attributes.push((name, value));
+                    self.pending_atom_attrs = (remaining > 1).then_some((index, remaining - 1));
+                }
+                None => {
+                    let index = self.atom_index(parse_column(parts, 0, "atom id")?, parts, 0)?;
+                    let count: usize = parse_column(parts, 1, "number of attributes")?;
+                    self.pending_atom_attrs = (count > 0).then_some((index, count));
+                }
+            },
+            // entries of sections we don't use are ignored
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// mol_name / num_atoms [num_bonds [num_subst [num_feat [num_sets]]]] / mol_type / charge_type
+    /// [/ status_bits [/ mol_comment]]. each on its own line
+    fn parse_molecule_entry(&mut self, parts: &[&str]) -> Result<(), ColumnError> {
+        match self.section_entries {
+            0 => self.mol_name = Some(parse_mol_name_line(parts)),
+            1 => {
+                self.header.num_atoms = parse_optional_column(parts, 0, "number of atoms")?;
+                self.header.num_bonds = parse_optional_column(parts, 1, "number of bonds")?;
+                self.header.num_subst = parse_optional_column(parts, 2, "number of substructures")?;
+                self.header.num_feat = parse_optional_column(parts, 3, "number of features")?;
+                self.header.num_sets = parse_optional_column(parts, 4, "number of sets")?;
             }
+            2 => self.header.mol_type = Some(parts.join(" ")),
+            3 => self.header.charge_type = Some(parts.join(" ")),
+            4 => self.header.status_bits = Some(parts.join(" ")),
+            5 => self.header.comment = Some(parts.join(" ")),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// index of the atom with id, error if it doesn't exist
+    fn atom_index(&self, id: i32, parts: &[&str], column: usize) -> Result<usize, ColumnError> {
+        self.atom_indices
+            .get(&id)
+            .copied()
+            .ok_or_else(|| ColumnError::new(column, "Unknown atom", parts[column]))
+    }
+
+    fn error(&self, e: ColumnError) -> Mol2ParseError {
+        Mol2ParseError {
+            line: self.line,
+            section: self.section,
+            column: Some(e.column + 1),
+            message: e.message,
         }
+    }
+
+    /// checks that entries spanning multiple lines are complete
+    fn finish_section(&mut self) -> Result<(), Mol2ParseError> {
+        let message = if let Some((set, _)) = self.pending_set.take() {
+            format!("Set {} is incomplete", set.name)
+        } else if let Some((index, remaining)) = self.pending_atom_attrs.take() {
+            format!(
+                "Atom {} is missing {} attributes",
+                self.atoms[index].id, remaining
+            )
+        } else {
+            return Ok(());
+        };
+        Err(Mol2ParseError {
+            line: self.line,
+            section: self.section,
+            column: None,
+            message,
+        })
+    }
 
+    /// adds the molecule parsed so far (if any) to the result
+    fn finish_molecule(&mut self) -> Result<(), Mol2ParseError> {
+        if self.mol_name.is_none() && self.atoms.is_empty() && self.bonds.is_empty() {
+            return Ok(());
+        }
         // mol name seems mandatory, so err if not found
-        let mol_name = mol_name.ok_or_else(|| anyhow!("File has no molecule name."))?;
+        let mol_name = self.mol_name.take().ok_or_else(|| Mol2ParseError {
+            line: self.line,
+            section: self.section,
+            column: None,
+            message: format!("Molecule {} has no name", self.molecules.len() + 1),
+        })?;
 
-        println!(
-            "finished parsing mol2 file: atoms: {}, bonds: {}",
-            atoms.len(),
-            bonds.len()
-        );
-        let mol = Mol2Molecule {
+        self.atom_indices.clear();
+        self.molecules.push(Mol2Molecule {
             name: mol_name,
-            atoms,
-            bonds,
-        };
-        Ok(mol)
+            header: std::mem::take(&mut self.header),
+            atoms: std::mem::take(&mut self.atoms),
+            bonds: std::mem::take(&mut self.bonds),
+            substructures: std::mem::take(&mut self.substructures),
+            crysin: self.crysin.take(),
+            sets: std::mem::take(&mut self.sets),
+        });
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<Mol2Molecule>, Mol2ParseError> {
+        self.finish_section()?;
+        self.finish_molecule()?;
+        if self.molecules.is_empty() {
+            return Err(Mol2ParseError {
+                line: self.line,
+                section: self.section,
+                column: None,
+                message: "File has no molecule".to_string(),
+            });
+        }
+        Ok(self.molecules)
     }
 }
 
-enum ProcessMol2LineResult<'a> {
-    Empty,
-    Header(Header),
-    Entity { parts: Vec<&'a str> },
+/// error parsing a mol2 file, with the position of the offending entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mol2ParseError {
+    /// 1-based
+    pub line: usize,
+    pub section: Mol2Section,
+    /// 1-based index of the whitespace separated column, if the error is about a column
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Mol2ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, {} section", self.line, self.section)?;
+        if let Some(column) = self.column {
+            write!(f, ", column {}", column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
 }
 
-enum Header {
+impl std::error::Error for Mol2ParseError {}
+
+/// the record type of the section being parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mol2Section {
+    /// before the first record type indicator
+    #[default]
+    None,
+    Molecule,
     Atom,
     Bond,
-    Mol,
-    Other, // for now just ignoring these
+    Substructure,
+    Crysin,
+    Set,
+    UnityAtomAttr,
+    /// a record type we don't use, its entries are ignored
+    Other(&'static str),
+    /// not a tripos record type, its entries are ignored
+    Unknown,
+}
+
+/// the record types that aren't used (yet)
+const IGNORED_RECORD_TYPES: &[&str] = &[
+    "ALT_TYPE",
+    "ANCHOR_ATOM",
+    "ASSOCIATED_ANNOTATION",
+    "CENTER_OF_MASS",
+    "CENTROID",
+    "COMMENT",
+    "DICT",
+    "DATA_FILE",
+    "EXTENSION_POINT",
+    "FF_PBC",
+    "FFCON_ANGLE",
+    "FFCON_DIST",
+    "FFCON_MULTI",
+    "FFCON_RANGE",
+    "FFCON_TORSION",
+    "LINE",
+    "LSPLANE",
+    "NORMAL",
+    "QSAR_ALIGN_RULE",
+    "RING_CLOSURE",
+    "ROTATABLE_BOND",
+    "SEARCH_DIST",
+    "SEARCH_OPTIONS",
+    "U_FEAT",
+    "UNITY_BOND_ATTR",
+];
+
+impl Mol2Section {
+    /// the section for a record type indicator, e.g. "@<TRIPOS>ATOM"
+    fn from_header(header: &str) -> Option<Mol2Section> {
+        let record_type = header.strip_prefix("@<TRIPOS>")?;
+        Some(match record_type {
+            "MOLECULE" => Mol2Section::Molecule,
+            "ATOM" => Mol2Section::Atom,
+            "BOND" => Mol2Section::Bond,
+            "SUBSTRUCTURE" => Mol2Section::Substructure,
+            "CRYSIN" => Mol2Section::Crysin,
+            "SET" => Mol2Section::Set,
+            "UNITY_ATOM_ATTR" => Mol2Section::UnityAtomAttr,
+            _ => IGNORED_RECORD_TYPES
+                .iter()
+                .find(|t| **t == record_type)
+                .map(|t| Mol2Section::Other(t))
+                .unwrap_or(Mol2Section::Unknown),
+        })
+    }
+}
+
+impl fmt::Display for Mol2Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Mol2Section::None => "no",
+            Mol2Section::Molecule => "MOLECULE",
+            Mol2Section::Atom => "ATOM",
+            Mol2Section::Bond => "BOND",
+            Mol2Section::Substructure => "SUBSTRUCTURE",
+            Mol2Section::Crysin => "CRYSIN",
+            Mol2Section::Set => "SET",
+            Mol2Section::UnityAtomAttr => "UNITY_ATOM_ATTR",
+            Mol2Section::Other(name) => name,
+            Mol2Section::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// error in a column of a line, the position of the line is added by the parser
+#[derive(Debug)]
+struct ColumnError {
+    /// 0-based
+    column: usize,
+    message: String,
+}
+
+impl ColumnError {
+    fn new(column: usize, message: &str, value: &str) -> ColumnError {
+        ColumnError {
+            column,
+            message: format!("{}: \"{}\"", message, value),
+        }
+    }
+
+    fn missing(column: usize, field: &str) -> ColumnError {
+        ColumnError {
+            column,
+            message: format!("Missing {}", field),
+        }
+    }
 }
 
-fn parse_mol2_line(line: &str) -> ProcessMol2LineResult {
+enum ProcessMol2LineResult<'a> {
+    Empty,
+    Header(Mol2Section),
+    Entity { parts: Vec<&'a str> },
+}
+
+fn parse_mol2_line(line: &str) -> ProcessMol2LineResult<'_> {
     // println!("{}", line);
 
     if line.trim().is_empty() {
@@ -130,40 +450,87 @@ fn parse_mol2_line(line: &str) -> ProcessMol2LineResult {
 
     let parts: Vec<&str> = line.split_whitespace().collect();
 
-    match parts[0] {
-        "@<TRIPOS>ATOM" => {
-            return ProcessMol2LineResult::Header(Header::Atom);
-        }
-        "@<TRIPOS>BOND" => {
-            return ProcessMol2LineResult::Header(Header::Bond);
-        }
-        "@<TRIPOS>MOLECULE" => {
-            return ProcessMol2LineResult::Header(Header::Mol);
-        }
-        "@<TRIPOS>SUBSTRUCTURE" => {
-            return ProcessMol2LineResult::Header(Header::Other);
-        }
-        _ => {}
+    match Mol2Section::from_header(parts[0]) {
+        Some(section) => ProcessMol2LineResult::Header(section),
+        None => ProcessMol2LineResult::Entity { parts },
     }
+}
+
+/// the column at `index`, error if the line is too short
+fn column<'a>(parts: &[&'a str], index: usize, field: &str) -> Result<&'a str, ColumnError> {
+    parts
+        .get(index)
+        .copied()
+        .ok_or_else(|| ColumnError::missing(index, field))
+}
 
-    ProcessMol2LineResult::Entity { parts }
+/// parses the column at `index`, error if missing or invalid
+fn parse_column<T: FromStr>(parts: &[&str], index: usize, field: &str) -> Result<T, ColumnError> {
+    let value = column(parts, index, field)?;
+    value
+        .parse()
+        .map_err(|_| ColumnError::new(index, &format!("Invalid {}", field), value))
 }
 
-fn parse_atom_line(parts: &[&str]) -> Result<Mol2Atom> {
-    let type_ = parts[5];
+/// parses the column at `index` if present, error if invalid
+fn parse_optional_column<T: FromStr>(
+    parts: &[&str],
+    index: usize,
+    field: &str,
+) -> Result<Option<T>, ColumnError> {
+    if parts.len() > index {
+        parse_column(parts, index, field).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// atom_id atom_name x y z atom_type [subst_id [subst_name [charge [status_bit]]]]
+fn parse_atom_line(parts: &[&str]) -> Result<Mol2Atom, ColumnError> {
+    // columns are parsed in order, so the first invalid one is reported
+    let id = parse_column(parts, 0, "atom id")?;
+    let name = column(parts, 1, "atom name")?.to_string();
+    let x = parse_column(parts, 2, "x coordinate")?;
+    let y = parse_column(parts, 3, "y coordinate")?;
+    let z = parse_column(parts, 4, "z coordinate")?;
+    let type_ = column(parts, 5, "atom type")?;
     Ok(Mol2Atom {
-        id: parts[0].parse()?,
-        name: parts[1].to_string(),
-        element: parse_element_from_type(type_)?,
-        x: parts[2].parse()?,
-        y: parts[3].parse()?,
-        z: parts[4].parse()?,
+        id,
+        name,
+        element: parse_element_from_type(type_)
+            .map_err(|_| ColumnError::new(5, "Unknown element in atom type", type_))?,
+        x,
+        y,
+        z,
         type_: type_.to_string(),
-        bond_count: parts[6].parse()?,
-        mol_name: parts[7].to_string(),
+        subst_id: parse_optional_column(parts, 6, "substructure id")?,
+        subst_name: parts.get(7).map(|p| p.to_string()),
+        charge: parse_optional_column(parts, 8, "charge")?,
+        status_bits: parts
+            .get(9)
+            .map(|p| parse_status_bits(p))
+            .unwrap_or_default(),
+        attributes: vec![],
     })
 }
 
+/// e.g. "BACKBONE|DICT". unknown bits are ignored
+fn parse_status_bits(bits: &str) -> Vec<Mol2StatusBit> {
+    bits.split('|')
+        .filter_map(|bit| match bit {
+            "DSPMOD" => Some(Mol2StatusBit::Dspmod),
+            "TYPECOL" => Some(Mol2StatusBit::Typecol),
+            "CAP" => Some(Mol2StatusBit::Cap),
+            "BACKBONE" => Some(Mol2StatusBit::Backbone),
+            "DICT" => Some(Mol2StatusBit::Dict),
+            "ESSENTIAL" => Some(Mol2StatusBit::Essential),
+            "WATER" => Some(Mol2StatusBit::Water),
+            "DIRECT" => Some(Mol2StatusBit::Direct),
+            _ => None,
+        })
+        .collect()
+}
+
 fn parse_element_from_type(type_: &str) -> Result<Element> {
     let parts: Vec<&str> = type_.split(".").collect();
     if parts.is_empty() {
@@ -172,39 +539,266 @@ fn parse_element_from_type(type_: &str) -> Result<Element> {
     parse_element(parts[0])
 }
 
-fn parse_element(element: &str) -> Result<Element> {
-    match element {
-        "H" => Ok(Element::H),
-        "C" => Ok(Element::C),
-        "N" => Ok(Element::N),
-        "O" => Ok(Element::O),
-        "F" => Ok(Element::F),
-        "P" => Ok(Element::P),
-        "S" => Ok(Element::S),
-        "Ca" => Ok(Element::Ca),
-        _ => Err(anyhow!("Not handled element str: {}", element)),
-    }
+pub fn parse_element(element: &str) -> Result<Element> {
+    Element::from_symbol(element).ok_or_else(|| anyhow!("Not handled element str: {}", element))
 }
 
-fn parse_bond_line(parts: &[&str]) -> Result<Mol2Bond> {
+/// bond_id origin_atom_id target_atom_id bond_type [status_bits]
+fn parse_bond_line(parts: &[&str]) -> Result<Mol2Bond, ColumnError> {
     Ok(Mol2Bond {
-        id: parts[0].parse()?,
-        atom1: parts[1].parse()?,
-        atom2: parts[2].parse()?,
-        type_: parts[3].to_string(),
+        id: parse_column(parts, 0, "bond id")?,
+        atom1: parse_column(parts, 1, "origin atom id")?,
+        atom2: parse_column(parts, 2, "target atom id")?,
+        type_: parse_bond_type(column(parts, 3, "bond type")?)
+            .ok_or_else(|| ColumnError::new(3, "Invalid bond type", parts[3]))?,
     })
 }
 
-fn parse_mol_name_line(parts: &[&str]) -> Result<String> {
-    Ok(parts[0].to_string())
+fn parse_mol_name_line(parts: &[&str]) -> String {
+    parts[0].to_string()
 }
 
-// TODO performance: remove clone from these
-#[derive(Default, Debug, Clone, Asset, TypePath)]
+/// subst_id subst_name root_atom [subst_type [dict_type [chain [sub_type [inter_bonds [status [comment]]]]]]]
+fn parse_substructure_line(parts: &[&str]) -> Result<Mol2Substructure, ColumnError> {
+    Ok(Mol2Substructure {
+        id: parse_column(parts, 0, "substructure id")?,
+        name: column(parts, 1, "substructure name")?.to_string(),
+        root_atom: parse_column(parts, 2, "root atom")?,
+        type_: optional_field(parts, 3),
+        dict_type: parse_optional_column(parts, 4, "dictionary type")?,
+        chain: optional_field(parts, 5),
+        sub_type: optional_field(parts, 6),
+        inter_bonds: parse_optional_column(parts, 7, "number of inter bonds")?,
+    })
+}
+
+/// a b c alpha beta gamma space_grp setting
+fn parse_crysin_line(parts: &[&str]) -> Result<Mol2Crysin, ColumnError> {
+    Ok(Mol2Crysin {
+        lengths: Vec3::new(
+            parse_column(parts, 0, "cell length a")?,
+            parse_column(parts, 1, "cell length b")?,
+            parse_column(parts, 2, "cell length c")?,
+        ),
+        angles: Vec3::new(
+            parse_column(parts, 3, "cell angle alpha")?,
+            parse_column(parts, 4, "cell angle beta")?,
+            parse_column(parts, 5, "cell angle gamma")?,
+        ),
+        space_group: parse_column(parts, 6, "space group")?,
+        setting: parse_column(parts, 7, "space group setting")?,
+    })
+}
+
+/// set_name set_type subtype [status [comment]].
+/// followed by the members (static sets) or the rule (dynamic sets) on the next line(s)
+fn parse_set_line(parts: &[&str]) -> Result<Mol2Set, ColumnError> {
+    let type_ = column(parts, 1, "set type")?;
+    if type_ != "STATIC" && type_ != "DYNAMIC" {
+        return Err(ColumnError::new(1, "Invalid set type", type_));
+    }
+    Ok(Mol2Set {
+        name: column(parts, 0, "set name")?.to_string(),
+        type_: type_.to_string(),
+        subtype: column(parts, 2, "set subtype")?.to_string(),
+        members: vec![],
+        rule: None,
+    })
+}
+
+/// the column at `index` if present and not empty ("****")
+fn optional_field(parts: &[&str], index: usize) -> Option<String> {
+    parts
+        .get(index)
+        .filter(|p| **p != "****")
+        .map(|p| p.to_string())
+}
+
+/// the records of a mol2 molecule, converted to a `Molecule` to be used in the app
+#[derive(Default, Debug, Clone)]
 pub struct Mol2Molecule {
     pub name: String,
+    pub header: Mol2Header,
     pub atoms: Vec<Mol2Atom>,
     pub bonds: Vec<Mol2Bond>,
+    pub substructures: Vec<Mol2Substructure>,
+    pub crysin: Option<Mol2Crysin>,
+    pub sets: Vec<Mol2Set>,
+}
+
+impl Mol2Molecule {
+    /// bonds and sets reference atoms by id, which are mapped to atom indices
+    pub fn to_molecule(&self) -> Result<Molecule> {
+        let mut molecule = Molecule {
+            name: self.name.clone(),
+            unit_cell: self.crysin.as_ref().map(|crysin| UnitCell {
+                lengths: crysin.lengths,
+                angles: crysin.angles,
+                space_group: Some(crysin.space_group.to_string()),
+            }),
+            properties: self.header.properties(),
+            ..Default::default()
+        };
+
+        let chain_for_subst: HashMap<i32, &str> = self
+            .substructures
+            .iter()
+            .filter_map(|s| s.chain.as_deref().map(|chain| (s.id, chain)))
+            .collect();
+
+        let mut index_for_id: HashMap<i32, usize> = HashMap::new();
+        for atom in &self.atoms {
+            // "****" is the empty substructure
+            let residue = atom
+                .subst_id
+                .zip(atom.subst_name.as_deref())
+                .filter(|(_, name)| *name != "****");
+            let chain = residue.and_then(|(id, _)| chain_for_subst.get(&id).copied());
+            let formal_charge = atom
+                .attributes
+                .iter()
+                .find(|(name, _)| name == "charge")
+                .and_then(|(_, value)| value.parse().ok());
+            let index = molecule.add_atom(
+                Atom {
+                    type_: atom.type_.clone(),
+                    partial_charge: atom.charge,
+                    formal_charge,
+                    ..Atom::new(atom.id, atom.name.clone(), atom.element, atom.loc_vec3())
+                },
+                residue,
+                chain,
+            );
+            index_for_id.insert(atom.id, index);
+        }
+
+        for bond in &self.bonds {
+            let index = |atom_id: usize| {
+                index_for_id
+                    .get(&(atom_id as i32))
+                    .copied()
+                    .ok_or_else(|| anyhow!("Bond {} references unknown atom: {}", bond.id, atom_id))
+            };
+            molecule.bonds.push(Bond {
+                atom1: index(bond.atom1)?,
+                atom2: index(bond.atom2)?,
+                type_: bond.type_,
+            });
+        }
+
+        // sets of other records (bonds, substructures, ...) aren't used
+        for set in self.sets.iter().filter(|s| s.subtype == "ATOMS") {
+            let atoms =
+                set.members
+                    .iter()
+                    .map(|id| {
+                        index_for_id.get(id).copied().ok_or_else(|| {
+                            anyhow!("Set {} references unknown atom: {}", set.name, id)
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+            molecule.atom_sets.push(AtomSet {
+                name: set.name.clone(),
+                atoms,
+            });
+        }
+
+        Ok(molecule)
+    }
+}
+
+/// the entries of the MOLECULE record after the name
+#[allow(unused)]
+#[derive(Default, Debug, Clone)]
+pub struct Mol2Header {
+    pub num_atoms: Option<usize>,
+    pub num_bonds: Option<usize>,
+    pub num_subst: Option<usize>,
+    pub num_feat: Option<usize>,
+    pub num_sets: Option<usize>,
+    /// e.g. SMALL, PROTEIN
+    pub mol_type: Option<String>,
+    /// e.g. NO_CHARGES, GASTEIGER
+    pub charge_type: Option<String>,
+    pub status_bits: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl Mol2Header {
+    /// the descriptive fields, as molecule properties
+    fn properties(&self) -> Vec<(String, String)> {
+        [
+            ("mol_type", &self.mol_type),
+            ("charge_type", &self.charge_type),
+            ("status_bits", &self.status_bits),
+            ("comment", &self.comment),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| {
+            value
+                .as_ref()
+                .filter(|v| *v != "****")
+                .map(|v| (name.to_string(), v.clone()))
+        })
+        .collect()
+    }
+}
+
+/// an entry of the SUBSTRUCTURE record, usually a residue
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct Mol2Substructure {
+    pub id: i32,
+    pub name: String,
+    pub root_atom: i32,
+    /// e.g. RESIDUE, GROUP
+    pub type_: Option<String>,
+    pub dict_type: Option<u32>,
+    pub chain: Option<String>,
+    /// e.g. the residue type
+    pub sub_type: Option<String>,
+    pub inter_bonds: Option<u32>,
+}
+
+/// the CRYSIN record: unit cell and space group
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct Mol2Crysin {
+    /// a, b, c
+    pub lengths: Vec3,
+    /// alpha, beta, gamma in degrees
+    pub angles: Vec3,
+    pub space_group: u32,
+    pub setting: u32,
+}
+
+/// an entry of the SET record: a named group of atoms, bonds, substructures, ...
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct Mol2Set {
+    pub name: String,
+    /// STATIC (members listed) or DYNAMIC (members defined by a rule)
+    pub type_: String,
+    /// e.g. ATOMS, BONDS, SUBSTS
+    pub subtype: String,
+    /// ids of the members, for static sets
+    pub members: Vec<i32>,
+    pub rule: Option<String>,
+}
+
+/// 1 (single), 2 (double), 3 (triple), am (amide), ar (aromatic), du (dummy), un (unknown), nc (not connected)
+fn parse_bond_type(type_: &str) -> Option<BondType> {
+    Some(match type_.to_lowercase().as_str() {
+        "1" => BondType::Single,
+        "2" => BondType::Double,
+        "3" => BondType::Triple,
+        "ar" => BondType::Aromatic,
+        "am" => BondType::Amide,
+        "du" => BondType::Dummy,
+        "un" => BondType::Unknown,
+        "nc" => BondType::NotConnected,
+        _ => return None,
+    })
 }
 
 #[allow(unused)]
@@ -216,9 +810,28 @@ pub struct Mol2Atom {
     pub y: f32,
     pub z: f32,
     pub type_: String,
-    pub bond_count: i32,
-    pub mol_name: String,
+    /// id of the substructure (usually the residue number)
+    pub subst_id: Option<i32>,
+    /// name of the substructure (usually the residue name)
+    pub subst_name: Option<String>,
+    /// partial charge
+    pub charge: Option<f32>,
+    pub status_bits: Vec<Mol2StatusBit>,
     pub element: Element,
+    /// name / value pairs of the UNITY_ATOM_ATTR record
+    pub attributes: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mol2StatusBit {
+    Dspmod,
+    Typecol,
+    Cap,
+    Backbone,
+    Dict,
+    Essential,
+    Water,
+    Direct,
 }
 
 impl Mol2Atom {
@@ -233,49 +846,337 @@ pub struct Mol2Bond {
     pub id: u32,
     pub atom1: usize,
     pub atom2: usize,
-    pub type_: String,
+    pub type_: BondType,
 }
 
-pub fn bounding_box_for_mol(mol2_mol: &Mol2Molecule) -> BoundingBox {
-    let mut min_x = f32::INFINITY;
-    let mut max_x = f32::NEG_INFINITY;
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec3;
 
-    let mut min_y = f32::INFINITY;
-    let mut max_y = f32::NEG_INFINITY;
+    use super::{parse_atom_line, Mol2ParseError, Mol2Parser, Mol2Section, Mol2StatusBit};
+    use crate::{
+        element::Element,
+        mol2_writer::write_mol2,
+        molecule::{Atom, AtomSet, Bond, BondType, Molecule, UnitCell},
+    };
 
-    let mut min_z = f32::INFINITY;
-    let mut max_z = f32::NEG_INFINITY;
+    #[test]
+    fn test_parses_each_molecule_block() {
+        let text = "\
+@<TRIPOS>MOLECULE
+first
+ 2 1 0 0 0
+@<TRIPOS>ATOM
+1 C1 0.0 0.0 0.0 C.3 1 RES 0.0
+2 O1 1.4 0.0 0.0 O.3 1 RES 0.0
+@<TRIPOS>BOND
+1 1 2 1
+@<TRIPOS>MOLECULE
+second
+ 1 0 0 0 0
+@<TRIPOS>ATOM
+1 N1 5.0 0.0 0.0 N.3 1 RES 0.0
+";
+        let mut parser = Mol2Parser::default();
+        for line in text.lines() {
+            parser.parse_line(line).unwrap();
+        }
+        let mols = parser.finish().unwrap();
 
-    for m in &mol2_mol.atoms {
-        let x = m.x;
-        let y = m.y;
-        let z = m.z;
+        assert_eq!(mols.len(), 2);
+        assert_eq!(mols[0].name, "first");
+        assert_eq!(mols[0].atoms.len(), 2);
+        assert_eq!(mols[0].bonds.len(), 1);
+        assert_eq!(mols[1].name, "second");
+        assert_eq!(mols[1].atoms.len(), 1);
+        assert_eq!(mols[1].atoms[0].id, 1);
+        assert!(mols[1].bonds.is_empty());
+    }
 
-        if x < min_x {
-            min_x = x;
-        };
-        if x > max_x {
-            max_x = x;
-        };
-        if y < min_y {
-            min_y = y;
-        };
-        if y > max_y {
-            max_y = y;
-        };
-        if z < min_z {
-            min_z = z;
-        };
-        if z > max_z {
-            max_z = z;
-        };
+    #[test]
+    fn test_parses_atom_substructure_and_charge() {
+        let atom = parse_atom_line(&[
+            "1",
+            "N",
+            "27.34",
+            "24.43",
+            "2.61",
+            "N.4",
+            "1",
+            "MET",
+            "-0.35",
+            "BACKBONE|DICT",
+        ])
+        .unwrap();
+        assert_eq!(atom.subst_id, Some(1));
+        assert_eq!(atom.subst_name, Some("MET".to_string()));
+        assert_eq!(atom.charge, Some(-0.35));
+        assert_eq!(
+            atom.status_bits,
+            vec![Mol2StatusBit::Backbone, Mol2StatusBit::Dict]
+        );
+    }
+
+    #[test]
+    fn test_parses_atom_without_optional_columns() {
+        let atom = parse_atom_line(&["1", "C1", "1.2", "2.1", "0.0", "C.ar"]).unwrap();
+        assert_eq!(atom.subst_id, None);
+        assert_eq!(atom.subst_name, None);
+        assert_eq!(atom.charge, None);
+        assert!(atom.status_bits.is_empty());
+    }
+
+    fn parse(text: &str) -> Result<Vec<super::Mol2Molecule>, Mol2ParseError> {
+        let mut parser = Mol2Parser::default();
+        for line in text.lines() {
+            parser.parse_line(line)?;
+        }
+        parser.finish()
+    }
+
+    #[test]
+    fn test_reports_position_of_truncated_line() {
+        let text = "\
+@<TRIPOS>MOLECULE
+truncated
+@<TRIPOS>ATOM
+1 C1 0.0 0.0 0.0 C.3
+2 O1 1.4 0.0
+";
+        assert_eq!(
+            parse(text).unwrap_err(),
+            Mol2ParseError {
+                line: 5,
+                section: Mol2Section::Atom,
+                column: Some(5),
+                message: "Missing z coordinate".to_string(),
+            }
+        );
     }
-    BoundingBox {
-        min_x,
-        max_x,
-        min_y,
-        max_y,
-        min_z,
-        max_z,
+
+    #[test]
+    fn test_maps_bond_atom_ids_with_gaps() {
+        let text = "\
+@<TRIPOS>MOLECULE
+gaps
+@<TRIPOS>ATOM
+3 C1 0.0 0.0 0.0 C.3
+7 O1 1.4 0.0 0.0 O.3
+@<TRIPOS>BOND
+1 3 7 1
+2 3 8 1
+";
+        let err = parse(text).unwrap_err();
+        assert_eq!(
+            (err.line, err.section, err.column),
+            (8, Mol2Section::Bond, Some(3))
+        );
+
+        let mols = parse(&text.replace("2 3 8 1\n", "")).unwrap();
+        let mol = mols[0].to_molecule().unwrap();
+        assert_eq!((mol.bonds[0].atom1, mol.bonds[0].atom2), (0, 1));
+    }
+
+    #[test]
+    fn test_parses_bond_types() {
+        let text = "\
+@<TRIPOS>MOLECULE
+types
+@<TRIPOS>ATOM
+1 C1 0.0 0.0 0.0 C.ar
+2 C2 1.4 0.0 0.0 C.ar
+3 N1 2.8 0.0 0.0 N.am
+4 Du 4.2 0.0 0.0 Du
+@<TRIPOS>BOND
+1 1 2 ar
+2 2 3 AM
+3 3 4 nc
+4 1 4 un
+";
+        let mols = parse(text).unwrap();
+        let types: Vec<BondType> = mols[0].bonds.iter().map(|b| b.type_).collect();
+        assert_eq!(
+            types,
+            vec![
+                BondType::Aromatic,
+                BondType::Amide,
+                BondType::NotConnected,
+                BondType::Unknown
+            ]
+        );
+
+        let err = parse(&text.replace("3 3 4 nc", "3 3 4 x")).unwrap_err();
+        assert_eq!(
+            (err.line, err.section, err.column),
+            (11, Mol2Section::Bond, Some(4))
+        );
+    }
+
+    #[test]
+    fn test_parses_records_besides_atoms_and_bonds() {
+        let text = "\
+@<TRIPOS>MOLECULE
+peptide
+ 4 3 2 0 1
+PROTEIN
+USER_CHARGES
+****
+two residues
+@<TRIPOS>ATOM
+1 N 0.0 0.0 0.0 N.4 1 GLY1 -0.3
+2 CA 1.4 0.0 0.0 C.3 1 GLY1 0.1
+3 C 2.0 1.2 0.0 C.2 2 ALA2 0.5
+4 O 3.2 1.2 0.0 O.co2 2 ALA2 -0.5
+@<TRIPOS>BOND
+1 1 2 1
+2 2 3 1
+3 3 4 ar
+@<TRIPOS>COMMENT
+1 2 3 4
+@<TRIPOS>SUBSTRUCTURE
+1 GLY1 1 RESIDUE 1 A GLY 0 ROOT
+2 ALA2 3 RESIDUE 1 **** ALA 0
+@<TRIPOS>CRYSIN
+10.0 12.5 15.0 90.0 100.0 90.0 4 1
+@<TRIPOS>UNITY_ATOM_ATTR
+1 2
+charge 1
+color red
+@<TRIPOS>SET
+BACKBONE STATIC ATOMS <user> ****
+3 1 \\
+2 3
+";
+        let mols = parse(text).unwrap();
+        let mol2 = &mols[0];
+        assert_eq!(mol2.header.num_atoms, Some(4));
+        assert_eq!(mol2.header.num_sets, Some(1));
+        assert_eq!(mol2.bonds.len(), 3);
+        assert_eq!(mol2.substructures.len(), 2);
+        assert_eq!(mol2.substructures[0].type_.as_deref(), Some("RESIDUE"));
+        assert_eq!(mol2.crysin.as_ref().unwrap().space_group, 4);
+        assert_eq!(mol2.sets[0].members, vec![1, 2, 3]);
+
+        let mol = mol2.to_molecule().unwrap();
+        assert_eq!(mol.residues.len(), 2);
+        assert_eq!(mol.chains.len(), 1);
+        assert_eq!(mol.chain(&mol.atoms[1]).unwrap().name, "A");
+        assert!(mol.chain(&mol.atoms[2]).is_none());
+        assert_eq!(mol.atoms[0].formal_charge, Some(1));
+        let cell = mol.unit_cell.unwrap();
+        assert_eq!(cell.lengths, Vec3::new(10.0, 12.5, 15.0));
+        assert_eq!(cell.angles.y, 100.0);
+        assert_eq!(mol.atom_sets[0].name, "BACKBONE");
+        assert_eq!(mol.atom_sets[0].atoms, vec![0, 1, 2]);
+        assert!(mol
+            .properties
+            .contains(&("mol_type".to_string(), "PROTEIN".to_string())));
+        assert!(mol
+            .properties
+            .contains(&("comment".to_string(), "two residues".to_string())));
+    }
+
+    #[test]
+    fn test_written_mol2_loads_same_molecule() {
+        let mut mol = Molecule {
+            name: "ala".to_string(),
+            ..Default::default()
+        };
+        let atoms = [
+            (
+                5,
+                "N",
+                Element::N,
+                "N.am",
+                Vec3::new(-1.2345, 0.5, 0.0),
+                -0.4157,
+            ),
+            (
+                6,
+                "CA",
+                Element::C,
+                "C.3",
+                Vec3::new(0.0, 1.25, -0.0001),
+                0.0337,
+            ),
+            (7, "C", Element::C, "C.2", Vec3::new(1.2, 0.4, 0.3), 0.5973),
+            (
+                9,
+                "O",
+                Element::O,
+                "O.2",
+                Vec3::new(1.3, -0.8, 100.125),
+                -0.5679,
+            ),
+        ];
+        for (id, name, element, type_, position, charge) in atoms {
+            mol.add_atom(
+                Atom {
+                    type_: type_.to_string(),
+                    partial_charge: Some(charge),
+                    ..Atom::new(id, name.to_string(), element, position)
+                },
+                Some((1, "ALA")),
+                Some("A"),
+            );
+        }
+        mol.add_atom(
+            Atom::new(10, "O".to_string(), Element::O, Vec3::new(4.0, 4.0, 4.0)),
+            None,
+            None,
+        );
+        for (atom1, atom2, type_) in [
+            (0, 1, BondType::Single),
+            (1, 2, BondType::Single),
+            (2, 3, BondType::Double),
+            (0, 2, BondType::Amide),
+        ] {
+            mol.bonds.push(Bond {
+                atom1,
+                atom2,
+                type_,
+            });
+        }
+
+        mol.unit_cell = Some(UnitCell {
+            lengths: Vec3::new(20.0, 21.5, 30.25),
+            angles: Vec3::new(90.0, 90.0, 120.0),
+            space_group: Some("146".to_string()),
+        });
+        mol.atom_sets.push(AtomSet {
+            name: "polar".to_string(),
+            atoms: vec![0, 3, 4],
+        });
+
+        let text = write_mol2(&mol);
+        let mut parser = Mol2Parser::default();
+        for line in text.lines() {
+            parser.parse_line(line).unwrap();
+        }
+        let mols = parser.finish().unwrap();
+        assert_eq!(mols.len(), 1);
+        let loaded = mols[0].to_molecule().unwrap();
+
+        assert_eq!(loaded.name, "ala");
+        assert_eq!(loaded.atoms.len(), mol.atoms.len());
+        for (a, b) in mol.atoms.iter().zip(&loaded.atoms) {
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.element, b.element);
+            assert_eq!(a.type_, b.type_);
+            assert!(a.position.distance(b.position) < 1e-4);
+            assert_eq!(a.partial_charge, b.partial_charge);
+        }
+        assert_eq!(loaded.bonds.len(), mol.bonds.len());
+        for (a, b) in mol.bonds.iter().zip(&loaded.bonds) {
+            assert_eq!((a.atom1, a.atom2, a.type_), (b.atom1, b.atom2, b.type_));
+        }
+        assert_eq!(loaded.residues.len(), 1);
+        assert_eq!(loaded.residues[0].name, "ALA");
+        assert_eq!(loaded.residues[0].atoms, vec![0, 1, 2, 3]);
+        assert!(loaded.residue(&loaded.atoms[4]).is_none());
+        assert_eq!(loaded.unit_cell, mol.unit_cell);
+        assert_eq!(loaded.atom_sets[0].name, "polar");
+        assert_eq!(loaded.atom_sets[0].atoms, vec![0, 3, 4]);
     }
 }
@@ -0,0 +1,180 @@
+use std::{collections::HashSet, fmt::Write};
+
+use crate::molecule::{BondType, Molecule};
+
+/// serializes a molecule as mol2 (MOLECULE, ATOM, BOND, SUBSTRUCTURE, CRYSIN and SET sections),
+/// such that it can be loaded again with `Mol2AssetLoader`
+pub fn write_mol2(mol: &Molecule) -> String {
+    let ids = atom_ids(mol);
+    let has_charges = mol.atoms.iter().any(|a| a.partial_charge.is_some());
+
+    let mut out = String::new();
+
+    out.push_str("@<TRIPOS>MOLECULE\n");
+    out.push_str(&field(&mol.name));
+    out.push('\n');
+    out.push_str(&format!(
+        "{} {} {} 0 0\n",
+        mol.atoms.len(),
+        mol.bonds.len(),
+        mol.residues.len()
+    ));
+    // mol2 header fields of a loaded mol2 file are kept in the properties
+    let property = |name: &str| {
+        mol.properties
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    };
+    let mol_type = property("mol_type").unwrap_or(if mol.chains.is_empty() {
+        "SMALL"
+    } else {
+        "BIOPOLYMER"
+    });
+    let charge_type = match property("charge_type") {
+        Some(charge_type) if has_charges => charge_type,
+        _ if has_charges => "USER_CHARGES",
+        _ => "NO_CHARGES",
+    };
+    let _ = writeln!(out, "{}\n{}", mol_type, charge_type);
+    if let Some(comment) = property("comment") {
+        let _ = writeln!(
+            out,
+            "{}\n{}",
+            property("status_bits").unwrap_or("****"),
+            comment
+        );
+    }
+
+    out.push_str("\n@<TRIPOS>ATOM\n");
+    for (atom, id) in mol.atoms.iter().zip(&ids) {
+        let name = if atom.name.trim().is_empty() {
+            atom.element.symbol()
+        } else {
+            &atom.name
+        };
+        let _ = write!(
+            out,
+            "{:>7} {:<8} {:>10.4} {:>10.4} {:>10.4} {:<6}",
+            id,
+            field(name),
+            atom.position.x,
+            atom.position.y,
+            atom.position.z,
+            field(&atom.type_),
+        );
+        // the columns are positional: an atom without residue gets the empty substructure if it has a charge
+        match (mol.residue(atom), atom.partial_charge) {
+            (Some(residue), charge) => {
+                let _ = write!(out, " {:>4} {:<8}", residue.id, field(&residue.name));
+                if let Some(charge) = charge {
+                    let _ = write!(out, " {:>9.4}", charge);
+                }
+            }
+            (None, Some(charge)) => {
+                let _ = write!(out, " {:>4} {:<8} {:>9.4}", 0, "****", charge);
+            }
+            (None, None) => {}
+        }
+        out.push('\n');
+    }
+
+    out.push_str("@<TRIPOS>BOND\n");
+    for (index, bond) in mol.bonds.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "{:>6} {:>5} {:>5} {}",
+            index + 1,
+            ids[bond.atom1],
+            ids[bond.atom2],
+            bond_type(bond.type_)
+        );
+    }
+
+    if !mol.residues.is_empty() {
+        out.push_str("@<TRIPOS>SUBSTRUCTURE\n");
+        for residue in &mol.residues {
+            // the root atom is the first atom of the residue
+            let root = residue.atoms.first().map(|a| ids[*a]).unwrap_or(0);
+            let _ = write!(
+                out,
+                "{:>6} {:<8} {:>6} RESIDUE",
+                residue.id,
+                field(&residue.name),
+                root
+            );
+            if let Some(chain) = residue.chain.and_then(|c| mol.chains.get(c)) {
+                let _ = write!(out, " 1 {} {}", field(&chain.name), field(&residue.name));
+            }
+            out.push('\n');
+        }
+    }
+
+    if let Some(cell) = &mol.unit_cell {
+        // the space group is a number in mol2
+        let space_group = cell
+            .space_group
+            .as_deref()
+            .and_then(|g| g.parse::<u32>().ok())
+            .unwrap_or(1);
+        let _ = writeln!(
+            out,
+            "@<TRIPOS>CRYSIN\n{:.4} {:.4} {:.4} {:.4} {:.4} {:.4} {} 1",
+            cell.lengths.x,
+            cell.lengths.y,
+            cell.lengths.z,
+            cell.angles.x,
+            cell.angles.y,
+            cell.angles.z,
+            space_group
+        );
+    }
+
+    if !mol.atom_sets.is_empty() {
+        out.push_str("@<TRIPOS>SET\n");
+        for set in &mol.atom_sets {
+            let _ = writeln!(out, "{} STATIC ATOMS <user> ****", field(&set.name));
+            let _ = write!(out, "{}", set.atoms.len());
+            for atom in &set.atoms {
+                let _ = write!(out, " {}", ids[*atom]);
+            }
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+/// the ids of the source file if they can be used to reference the atoms (positive and unique),
+/// otherwise the atoms are numbered from 1
+fn atom_ids(mol: &Molecule) -> Vec<i32> {
+    let mut seen = HashSet::new();
+    if mol.atoms.iter().all(|a| a.id > 0 && seen.insert(a.id)) {
+        mol.atoms.iter().map(|a| a.id).collect()
+    } else {
+        (1..=mol.atoms.len() as i32).collect()
+    }
+}
+
+/// mol2 fields are whitespace separated, "****" is used for an empty field
+fn field(value: &str) -> String {
+    let value = value.trim();
+    if value.is_empty() {
+        "****".to_string()
+    } else {
+        value.split_whitespace().collect::<Vec<_>>().join("_")
+    }
+}
+
+fn bond_type(type_: BondType) -> &'static str {
+    match type_ {
+        BondType::Single => "1",
+        BondType::Double => "2",
+        BondType::Triple => "3",
+        BondType::Aromatic => "ar",
+        BondType::Amide => "am",
+        BondType::Dummy => "du",
+        BondType::Unknown => "un",
+        BondType::NotConnected => "nc",
+    }
+}
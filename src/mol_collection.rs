@@ -0,0 +1,12 @@
+use bevy::asset::{Asset, Handle};
+use bevy::reflect::TypePath;
+
+use crate::molecule::Molecule;
+
+/// a file with multiple molecules (e.g. the records of a sdf file).
+/// each molecule is a labeled sub-asset of the file, so it can also be loaded directly
+/// with a "path#label" asset path
+#[derive(Default, Debug, Clone, Asset, TypePath)]
+pub struct MolCollection {
+    pub molecules: Vec<Handle<Molecule>>,
+}
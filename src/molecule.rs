@@ -0,0 +1,365 @@
+use bevy::{asset::Asset, math::Vec3, reflect::TypePath, utils::HashMap};
+
+use crate::{bond_perception::perceive_bonds, bounding_box::BoundingBox, element::Element};
+
+/// format independent molecule: all the loaders convert into this, and the scene renders it
+#[derive(Default, Debug, Clone, Asset, TypePath)]
+pub struct Molecule {
+    pub name: String,
+    pub atoms: Vec<Atom>,
+    pub bonds: Vec<Bond>,
+    /// the bonds of the file, while `bonds` are perceived from the distances instead
+    pub file_bonds: Option<Vec<Bond>>,
+    pub residues: Vec<Residue>,
+    pub chains: Vec<Chain>,
+    /// positions of the atoms for each frame of a trajectory (the atom positions are the first frame).
+    /// empty for a single structure
+    pub frames: Vec<Vec<Vec3>>,
+    pub unit_cell: Option<UnitCell>,
+    /// named groups of atoms
+    pub atom_sets: Vec<AtomSet>,
+    /// name / value pairs (e.g. sdf data items, mol2 molecule type)
+    pub properties: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Atom {
+    /// id in the source file
+    pub id: i32,
+    pub name: String,
+    pub element: Element,
+    pub position: Vec3,
+    /// atom type of the source file (e.g. sybyl type in mol2), the element symbol if it has none
+    pub type_: String,
+    /// index in `Molecule::residues`
+    pub residue: Option<usize>,
+    pub partial_charge: Option<f32>,
+    pub formal_charge: Option<i32>,
+    pub isotope: Option<u32>,
+    pub alt_loc: Option<char>,
+    pub b_factor: Option<f32>,
+}
+
+impl Atom {
+    /// atom with only the mandatory fields set
+    pub fn new(id: i32, name: String, element: Element, position: Vec3) -> Atom {
+        Atom {
+            id,
+            name,
+            element,
+            position,
+            type_: element.symbol().to_string(),
+            residue: None,
+            partial_charge: None,
+            formal_charge: None,
+            isotope: None,
+            alt_loc: None,
+            b_factor: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Bond {
+    /// index in `Molecule::atoms`
+    pub atom1: usize,
+    /// index in `Molecule::atoms`
+    pub atom2: usize,
+    pub type_: BondType,
+}
+
+/// bond types, as in mol2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BondType {
+    Single,
+    Double,
+    Triple,
+    Aromatic,
+    Amide,
+    Dummy,
+    Unknown,
+    NotConnected,
+}
+
+#[derive(Debug, Clone)]
+pub struct Residue {
+    /// residue number in the source file
+    pub id: i32,
+    pub name: String,
+    /// index in `Molecule::chains`
+    pub chain: Option<usize>,
+    /// indices in `Molecule::atoms`
+    pub atoms: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chain {
+    pub name: String,
+    /// indices in `Molecule::residues`
+    pub residues: Vec<usize>,
+}
+
+/// crystallographic unit cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitCell {
+    /// a, b, c
+    pub lengths: Vec3,
+    /// alpha, beta, gamma in degrees
+    pub angles: Vec3,
+    pub space_group: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AtomSet {
+    pub name: String,
+    /// indices in `Molecule::atoms`
+    pub atoms: Vec<usize>,
+}
+
+impl Molecule {
+    /// adds an atom to the molecule, and to its residue and chain (these are created if needed).
+    /// consecutive atoms with the same residue id and name (and chain) are in the same residue.
+    /// returns the index of the atom
+    pub fn add_atom(
+        &mut self,
+        mut atom: Atom,
+        residue: Option<(i32, &str)>,
+        chain: Option<&str>,
+    ) -> usize {
+        let index = self.atoms.len();
+
+        let chain = chain.map(|name| {
+            self.chains
+                .iter()
+                .position(|c| c.name == name)
+                .unwrap_or_else(|| {
+                    self.chains.push(Chain {
+                        name: name.to_string(),
+                        residues: vec![],
+                    });
+                    self.chains.len() - 1
+                })
+        });
+
+        atom.residue = residue.map(|(id, name)| {
+            let residue_index = match self.residues.last() {
+                Some(last) if last.id == id && last.name == name && last.chain == chain => {
+                    self.residues.len() - 1
+                }
+                _ => {
+                    self.residues.push(Residue {
+                        id,
+                        name: name.to_string(),
+                        chain,
+                        atoms: vec![],
+                    });
+                    let residue_index = self.residues.len() - 1;
+                    if let Some(chain) = chain {
+                        self.chains[chain].residues.push(residue_index);
+                    }
+                    residue_index
+                }
+            };
+            self.residues[residue_index].atoms.push(index);
+            residue_index
+        });
+
+        self.atoms.push(atom);
+        index
+    }
+
+    /// uses bonds perceived from the distances if `force`, or if the file has no bonds,
+    /// otherwise (again) the bonds of the file. returns whether the bonds changed
+    pub fn update_perceived_bonds(&mut self, force: bool) -> bool {
+        let file_bonds = self.file_bonds.as_ref().unwrap_or(&self.bonds);
+        let perceive = force || (file_bonds.is_empty() && self.atoms.len() > 1);
+        match (perceive, self.file_bonds.is_some()) {
+            (true, false) => {
+                self.file_bonds = Some(std::mem::replace(
+                    &mut self.bonds,
+                    perceive_bonds(&self.atoms),
+                ));
+                true
+            }
+            (false, true) => {
+                self.bonds = self.file_bonds.take().unwrap_or_default();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// indices of the bonds of each atom
+    pub fn bonds_per_atom(&self) -> Vec<Vec<usize>> {
+        let mut bonds = vec![vec![]; self.atoms.len()];
+        for (index, bond) in self.bonds.iter().enumerate() {
+            bonds[bond.atom1].push(index);
+            bonds[bond.atom2].push(index);
+        }
+        bonds
+    }
+
+    /// atoms of the smallest ring (up to `max_size` atoms) containing the bond, in ring order starting with its atoms.
+    /// `bonds_per_atom` as returned by `bonds_per_atom`
+    pub fn smallest_ring(
+        &self,
+        bond: usize,
+        bonds_per_atom: &[Vec<usize>],
+        max_size: usize,
+    ) -> Option<Vec<usize>> {
+        let Bond { atom1, atom2, .. } = self.bonds[bond];
+        // breadth first search for the shortest path from atom2 back to atom1, without the bond itself
+        let mut previous: HashMap<usize, usize> = HashMap::from([(atom2, atom1)]);
+        let mut current = vec![atom2];
+        for _ in 1..max_size {
+            let mut next = vec![];
+            for atom in current {
+                for b in &bonds_per_atom[atom] {
+                    if *b == bond {
+                        continue;
+                    }
+                    let other = &self.bonds[*b];
+                    let neighbor = if other.atom1 == atom {
+                        other.atom2
+                    } else {
+                        other.atom1
+                    };
+                    if neighbor == atom1 {
+                        let mut ring = vec![atom];
+                        while let Some(p) = previous.get(ring.last().unwrap()).copied() {
+                            ring.push(p);
+                            if p == atom1 {
+                                break;
+                            }
+                        }
+                        ring.reverse();
+                        return Some(ring);
+                    }
+                    if !previous.contains_key(&neighbor) {
+                        previous.insert(neighbor, atom);
+                        next.push(neighbor);
+                    }
+                }
+            }
+            current = next;
+        }
+        None
+    }
+
+    /// number of frames of a trajectory, 1 for a single structure
+    pub fn frame_count(&self) -> usize {
+        self.frames.len().max(1)
+    }
+
+    pub fn residue(&self, atom: &Atom) -> Option<&Residue> {
+        atom.residue.and_then(|r| self.residues.get(r))
+    }
+
+    pub fn chain(&self, atom: &Atom) -> Option<&Chain> {
+        self.residue(atom)
+            .and_then(|r| r.chain)
+            .and_then(|c| self.chains.get(c))
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        let mut min_x = f32::INFINITY;
+        let mut max_x = f32::NEG_INFINITY;
+
+        let mut min_y = f32::INFINITY;
+        let mut max_y = f32::NEG_INFINITY;
+
+        let mut min_z = f32::INFINITY;
+        let mut max_z = f32::NEG_INFINITY;
+
+        for atom in &self.atoms {
+            let Vec3 { x, y, z } = atom.position;
+
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+            min_y = min_y.min(y);
+            max_y = max_y.max(y);
+            min_z = min_z.min(z);
+            max_z = max_z.max(z);
+        }
+        BoundingBox {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+            min_z,
+            max_z,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec3;
+
+    use super::{Atom, Bond, BondType, Molecule};
+    use crate::element::Element;
+
+    #[test]
+    fn test_groups_atoms_in_residues_and_chains() {
+        let mut mol = Molecule::default();
+        let atom = |id| Atom::new(id, format!("C{}", id), Element::C, Vec3::ZERO);
+
+        mol.add_atom(atom(1), Some((1, "ALA")), Some("A"));
+        mol.add_atom(atom(2), Some((1, "ALA")), Some("A"));
+        mol.add_atom(atom(3), Some((2, "GLY")), Some("A"));
+        mol.add_atom(atom(4), Some((1, "HOH")), Some("B"));
+        mol.add_atom(atom(5), None, None);
+
+        assert_eq!(mol.residues.len(), 3);
+        assert_eq!(mol.residues[0].atoms, vec![0, 1]);
+        assert_eq!(mol.chains.len(), 2);
+        assert_eq!(mol.chains[0].residues, vec![0, 1]);
+        assert_eq!(mol.chain(&mol.atoms[3]).unwrap().name, "B");
+        assert!(mol.residue(&mol.atoms[4]).is_none());
+    }
+
+    #[test]
+    fn test_finds_smallest_ring_of_bond() {
+        // naphthalene-like fused rings (0-5 and 4-9), with a chain atom 10 on atom 0
+        let mut mol = Molecule::default();
+        for i in 0..11 {
+            mol.add_atom(
+                Atom::new(i, "C".to_string(), Element::C, Vec3::ZERO),
+                None,
+                None,
+            );
+        }
+        let pairs = [
+            (0, 1),
+            (1, 2),
+            (2, 3),
+            (3, 4),
+            (4, 5),
+            (5, 0),
+            (4, 6),
+            (6, 7),
+            (7, 8),
+            (8, 9),
+            (9, 5),
+            (0, 10),
+        ];
+        mol.bonds = pairs
+            .iter()
+            .map(|(atom1, atom2)| Bond {
+                atom1: *atom1,
+                atom2: *atom2,
+                type_: BondType::Single,
+            })
+            .collect();
+        let bonds_per_atom = mol.bonds_per_atom();
+
+        assert_eq!(
+            mol.smallest_ring(0, &bonds_per_atom, 8),
+            Some(vec![0, 1, 2, 3, 4, 5])
+        );
+        // the shared bond is in both rings, one of them is returned
+        assert_eq!(mol.smallest_ring(4, &bonds_per_atom, 8).unwrap().len(), 6);
+        assert_eq!(mol.smallest_ring(11, &bonds_per_atom, 8), None);
+        assert_eq!(mol.smallest_ring(0, &bonds_per_atom, 5), None);
+    }
+}
@@ -0,0 +1,707 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use bevy::app::{App, Plugin};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetApp, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::math::Vec3;
+
+use crate::bond_perception::{assign_bond_orders, is_bond_distance};
+use crate::element::Element;
+use crate::mol2_asset_plugin::parse_element;
+use crate::molecule::{Atom, Bond, BondType, Molecule};
+
+/// loads PDB and mmCIF files
+pub struct PdbAssetPlugin;
+
+impl Plugin for PdbAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_asset_loader(PdbAssetLoader)
+            .register_asset_loader(MmcifAssetLoader);
+    }
+}
+
+pub struct PdbAssetLoader;
+
+impl AssetLoader for PdbAssetLoader {
+    type Asset = Molecule;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Molecule, Self::Error> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8(bytes)?;
+
+        let mol = parse_pdb(&text, &file_stem(load_context))?;
+        println!(
+            "finished parsing pdb file: atoms: {}, bonds: {}",
+            mol.atoms.len(),
+            mol.bonds.len()
+        );
+        Ok(mol)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pdb", "ent"]
+    }
+}
+
+pub struct MmcifAssetLoader;
+
+impl AssetLoader for MmcifAssetLoader {
+    type Asset = Molecule;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Molecule, Self::Error> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8(bytes)?;
+
+        let mol = parse_mmcif(&text, &file_stem(load_context))?;
+        println!(
+            "finished parsing mmcif file: atoms: {}, bonds: {}",
+            mol.atoms.len(),
+            mol.bonds.len()
+        );
+        Ok(mol)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cif", "mmcif"]
+    }
+}
+
+/// fallback molecule name, if the file doesn't provide one
+fn file_stem(load_context: &LoadContext) -> String {
+    load_context
+        .path()
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// an ATOM / HETATM record, shared by the pdb and mmcif parsers
+#[derive(Debug, Clone)]
+struct PdbAtom {
+    serial: i32,
+    name: String,
+    alt_loc: Option<char>,
+    res_name: String,
+    chain: String,
+    res_seq: i32,
+    ins_code: Option<char>,
+    pos: Vec3,
+    b_factor: Option<f32>,
+    element: Element,
+}
+
+impl PdbAtom {
+    fn residue_key(&self) -> (&str, i32, Option<char>) {
+        (&self.chain, self.res_seq, self.ins_code)
+    }
+}
+
+pub fn parse_pdb(text: &str, default_name: &str) -> Result<Molecule> {
+    let mut name = None;
+    let mut atoms = vec![];
+    // serial -> bonded serials, repeated entries encode the bond order
+    let mut conect: Vec<(i32, Vec<i32>)> = vec![];
+    // only the first model is used for now, but CONECT records follow the last model
+    let mut first_model_ended = false;
+
+    for (index, line) in text.lines().enumerate() {
+        let record = column(line, 0, 6);
+        match record {
+            "HEADER" => {
+                let id_code = column(line, 62, 66);
+                if !id_code.is_empty() {
+                    name = Some(id_code.to_string());
+                }
+            }
+            "ATOM" | "HETATM" if !first_model_ended => {
+                let atom =
+                    parse_pdb_atom_line(line).map_err(|e| anyhow!("line {}: {}", index + 1, e))?;
+                atoms.push(atom);
+            }
+            "CONECT" => {
+                let serial = parse_column(line, 6, 11)?;
+                let mut bonded = vec![];
+                for start in [11, 16, 21, 26] {
+                    let col = column(line, start, start + 5);
+                    if !col.is_empty() {
+                        bonded.push(col.parse()?);
+                    }
+                }
+                conect.push((serial, bonded));
+            }
+            "ENDMDL" => first_model_ended = true,
+            _ => {}
+        }
+    }
+
+    to_molecule(
+        name.unwrap_or_else(|| default_name.to_string()),
+        atoms,
+        &conect,
+    )
+}
+
+fn parse_pdb_atom_line(line: &str) -> Result<PdbAtom> {
+    let name = column(line, 12, 16).to_string();
+    let element_col = column(line, 76, 78);
+    let element = if element_col.is_empty() {
+        // names of two letter elements start in column 13, one letter elements are right shifted (" CA " is C-alpha, "CA  " calcium).
+        // standard residues only have one letter elements, so this is only applied to hetero atoms
+        let name_col = line.get(12..16).unwrap_or("");
+        let two_letter = column(line, 0, 6) == "HETATM"
+            && name_col.starts_with(|c: char| c.is_ascii_alphabetic());
+        pdb_element_from_atom_name(&name, two_letter)?
+    } else {
+        parse_pdb_element(element_col)?
+    };
+    Ok(PdbAtom {
+        serial: parse_column(line, 6, 11)?,
+        alt_loc: column(line, 16, 17).chars().next(),
+        res_name: column(line, 17, 20).to_string(),
+        chain: column(line, 21, 22).to_string(),
+        res_seq: parse_column(line, 22, 26)?,
+        ins_code: column(line, 26, 27).chars().next(),
+        pos: Vec3::new(
+            parse_column(line, 30, 38)?,
+            parse_column(line, 38, 46)?,
+            parse_column(line, 46, 54)?,
+        ),
+        b_factor: column(line, 60, 66).parse().ok(),
+        name,
+        element,
+    })
+}
+
+/// trimmed fixed-width column, empty if the line is too short
+pub fn column(line: &str, start: usize, end: usize) -> &str {
+    let end = end.min(line.len());
+    if start >= end {
+        return "";
+    }
+    line.get(start..end).unwrap_or("").trim()
+}
+
+pub fn parse_column<T>(line: &str, start: usize, end: usize) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let col = column(line, start, end);
+    col.parse()
+        .map_err(|e| anyhow!("invalid column {}-{} ({:?}): {}", start + 1, end, col, e))
+}
+
+/// pdb / cif element symbols are upper case ("CA", "FE"), our parser expects "Ca", "Fe"
+fn parse_pdb_element(symbol: &str) -> Result<Element> {
+    let mut chars = symbol.chars();
+    let normalized = match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + &chars.as_str().to_lowercase(),
+        None => return Err(anyhow!("Empty element symbol")),
+    };
+    parse_element(&normalized)
+}
+
+/// old files may not have the element column: the element is then the first letter of the name,
+/// ignoring leading digits (e.g. "1HB")
+fn element_from_atom_name(name: &str) -> Result<Element> {
+    let symbol: String = name
+        .chars()
+        .skip_while(|c| c.is_ascii_digit())
+        .take(1)
+        .collect();
+    parse_pdb_element(&symbol)
+}
+
+/// like `element_from_atom_name`, but if `two_letter` the first two letters of the name are tried first
+/// (e.g. "ZN", "FE"), falling back to the first letter if they aren't an element symbol
+fn pdb_element_from_atom_name(name: &str, two_letter: bool) -> Result<Element> {
+    if two_letter {
+        if let Some(element) = name.get(0..2).and_then(|s| parse_pdb_element(s).ok()) {
+            return Ok(element);
+        }
+    }
+    element_from_atom_name(name)
+}
+
+pub fn parse_mmcif(text: &str, default_name: &str) -> Result<Molecule> {
+    let mut name = None;
+    let mut atoms = vec![];
+
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if let Some(data_name) = line.strip_prefix("data_") {
+            name = Some(data_name.to_string());
+        } else if line == "loop_" {
+            let mut columns = vec![];
+            while let Some(next) = lines.peek() {
+                let next = next.trim();
+                if next.starts_with('_') {
+                    columns.push(next.to_string());
+                    lines.next();
+                } else {
+                    break;
+                }
+            }
+
+            if !columns.iter().any(|c| c.starts_with("_atom_site.")) {
+                continue;
+            }
+            let columns: Vec<&str> = columns
+                .iter()
+                .map(|c| c.trim_start_matches("_atom_site."))
+                .collect();
+
+            // rows may in theory span lines, so collect tokens until the loop ends
+            let mut tokens = vec![];
+            while let Some(next) = lines.peek() {
+                let next = next.trim();
+                if next.is_empty() || next.starts_with('#') {
+                    lines.next();
+                    continue;
+                }
+                if next == "loop_" || next.starts_with('_') || next.starts_with("data_") {
+                    break;
+                }
+                tokens.extend(cif_tokens(next));
+                lines.next();
+            }
+
+            for row in tokens.chunks(columns.len()) {
+                if row.len() != columns.len() {
+                    return Err(anyhow!("Incomplete _atom_site row: {:?}", row));
+                }
+                let value = |key: &str| -> Option<&str> {
+                    columns
+                        .iter()
+                        .position(|c| *c == key)
+                        .map(|i| row[i].as_str())
+                        .filter(|v| *v != "?" && *v != ".")
+                };
+
+                // only the first model is used for now
+                if let Some(model) = value("pdbx_PDB_model_num") {
+                    if model != "1" {
+                        continue;
+                    }
+                }
+
+                let required = |key: &str| -> Result<&str> {
+                    value(key).ok_or_else(|| anyhow!("Missing _atom_site.{}", key))
+                };
+
+                let atom_name = required("auth_atom_id")
+                    .or_else(|_| required("label_atom_id"))?
+                    .to_string();
+                let element = match value("type_symbol") {
+                    Some(symbol) => parse_pdb_element(symbol)?,
+                    None => element_from_atom_name(&atom_name)?,
+                };
+
+                atoms.push(PdbAtom {
+                    serial: required("id")?.parse()?,
+                    alt_loc: value("label_alt_id").and_then(|a| a.chars().next()),
+                    res_name: required("auth_comp_id")
+                        .or_else(|_| required("label_comp_id"))?
+                        .to_string(),
+                    chain: required("auth_asym_id")
+                        .or_else(|_| required("label_asym_id"))?
+                        .to_string(),
+                    // hetero groups / waters don't have a label seq id
+                    res_seq: value("auth_seq_id")
+                        .or_else(|| value("label_seq_id"))
+                        .map(|s| s.parse())
+                        .transpose()?
+                        .unwrap_or(0),
+                    ins_code: value("pdbx_PDB_ins_code").and_then(|a| a.chars().next()),
+                    pos: Vec3::new(
+                        required("Cartn_x")?.parse()?,
+                        required("Cartn_y")?.parse()?,
+                        required("Cartn_z")?.parse()?,
+                    ),
+                    b_factor: value("B_iso_or_equiv").and_then(|b| b.parse().ok()),
+                    name: atom_name,
+                    element,
+                });
+            }
+        }
+    }
+
+    // mmcif has no CONECT equivalent for standard residues, bonds are always inferred
+    to_molecule(name.unwrap_or_else(|| default_name.to_string()), atoms, &[])
+}
+
+/// splits a cif data line in whitespace separated tokens, respecting single and double quotes
+fn cif_tokens(line: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut token = String::new();
+            while let Some(next) = chars.next() {
+                // a quote only closes the token if followed by whitespace or end of line
+                if next == c && chars.peek().is_none_or(|n| n.is_whitespace()) {
+                    break;
+                }
+                token.push(next);
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&next) = chars.peek() {
+                if next.is_whitespace() {
+                    break;
+                }
+                token.push(next);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+/// converts the parsed records to the molecule model
+fn to_molecule(name: String, atoms: Vec<PdbAtom>, conect: &[(i32, Vec<i32>)]) -> Result<Molecule> {
+    let atoms = remove_alt_loc_duplicates(atoms);
+
+    let index_for_serial: HashMap<i32, usize> = atoms
+        .iter()
+        .enumerate()
+        .map(|(index, atom)| (atom.serial, index))
+        .collect();
+
+    // (index1, index2) with index1 < index2 -> order
+    let mut bond_orders: HashMap<(usize, usize), usize> = HashMap::new();
+    for (serial, bonded) in conect {
+        // bonds to skipped alt-loc atoms are ignored
+        let Some(&index1) = index_for_serial.get(serial) else {
+            continue;
+        };
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for b in bonded {
+            if let Some(&index2) = index_for_serial.get(b) {
+                *counts.entry(index2).or_default() += 1;
+            }
+        }
+        for (index2, count) in counts {
+            let key = (index1.min(index2), index1.max(index2));
+            let order = bond_orders.entry(key).or_default();
+            *order = (*order).max(count.min(3));
+        }
+    }
+
+    // CONECT only adds bonds or raises their order, e.g. SSBOND disulfides also have CONECT records
+    for (index1, index2) in infer_residue_bonds(&atoms) {
+        bond_orders.entry((index1, index2)).or_insert(1);
+    }
+
+    let mut bond_keys: Vec<(usize, usize)> = bond_orders.keys().copied().collect();
+    bond_keys.sort();
+
+    let mut molecule = Molecule {
+        name,
+        ..Default::default()
+    };
+
+    for atom in atoms {
+        molecule.add_atom(
+            Atom {
+                alt_loc: atom.alt_loc,
+                b_factor: atom.b_factor,
+                ..Atom::new(atom.serial, atom.name, atom.element, atom.pos)
+            },
+            Some((atom.res_seq, &atom.res_name)),
+            Some(&atom.chain)
+                .filter(|c| !c.is_empty())
+                .map(|c| c.as_str()),
+        );
+    }
+
+    molecule.bonds = bond_keys
+        .iter()
+        .map(|key| Bond {
+            atom1: key.0,
+            atom2: key.1,
+            type_: match bond_orders[key] {
+                2 => BondType::Double,
+                3 => BondType::Triple,
+                _ => BondType::Single,
+            },
+        })
+        .collect();
+    assign_bond_orders(&molecule.atoms, &mut molecule.bonds);
+
+    Ok(molecule)
+}
+
+/// keeps only the first alternate location of each atom
+fn remove_alt_loc_duplicates(atoms: Vec<PdbAtom>) -> Vec<PdbAtom> {
+    let mut kept_alt_loc: HashMap<(String, i32, Option<char>, String), char> = HashMap::new();
+    atoms
+        .into_iter()
+        .filter(|atom| match atom.alt_loc {
+            None => true,
+            Some(alt_loc) => {
+                let key = (
+                    atom.chain.clone(),
+                    atom.res_seq,
+                    atom.ins_code,
+                    atom.name.clone(),
+                );
+                *kept_alt_loc.entry(key).or_insert(alt_loc) == alt_loc
+            }
+        })
+        .collect()
+}
+
+/// max distance for peptide / phosphodiester bonds between consecutive residues,
+/// larger distances are considered chain breaks
+const MAX_LINK_DISTANCE: f32 = 2.0;
+
+/// infers bonds by distance within residues, plus the backbone links between consecutive residues of a chain.
+/// returns pairs of atom indices, smaller index first
+fn infer_residue_bonds(atoms: &[PdbAtom]) -> Vec<(usize, usize)> {
+    // residues as ranges of consecutive atoms
+    let mut residues: Vec<std::ops::Range<usize>> = vec![];
+    for (index, atom) in atoms.iter().enumerate() {
+        match residues.last_mut() {
+            Some(range) if atoms[range.start].residue_key() == atom.residue_key() => {
+                range.end = index + 1;
+            }
+            _ => residues.push(index..index + 1),
+        }
+    }
+
+    let mut bonds = vec![];
+    for residue in &residues {
+        for i in residue.clone() {
+            for j in i + 1..residue.end {
+                let (a, b) = (&atoms[i], &atoms[j]);
+                if is_bond_distance(a.element, a.pos, b.element, b.pos) {
+                    bonds.push((i, j));
+                }
+            }
+        }
+    }
+
+    for pair in residues.windows(2) {
+        let (prev, next) = (&pair[0], &pair[1]);
+        if atoms[prev.start].chain != atoms[next.start].chain {
+            continue;
+        }
+        let find = |range: &std::ops::Range<usize>, name: &str| {
+            range.clone().find(|&i| atoms[i].name == name)
+        };
+        for (name1, name2) in [("C", "N"), ("O3'", "P")] {
+            if let (Some(i), Some(j)) = (find(prev, name1), find(next, name2)) {
+                if atoms[i].pos.distance(atoms[j].pos) < MAX_LINK_DISTANCE {
+                    bonds.push((i.min(j), i.max(j)));
+                }
+            }
+        }
+    }
+
+    bonds
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_mmcif, parse_pdb};
+    use crate::{element::Element, molecule::BondType};
+
+    const PDB: &str = "\
+HEADER    TEST                                    01-JAN-00   1ABC
+ATOM      1  N   GLY A   1       0.000   0.000   0.000  1.00 10.00           N
+ATOM      2  CA  GLY A   1       1.450   0.000   0.000  1.00 11.00           C
+ATOM      3  C   GLY A   1       2.000   1.400   0.000  1.00 12.00           C
+ATOM      4  O  AGLY A   1       1.300   2.400   0.000  0.60 13.00           O
+ATOM      5  O  BGLY A   1       1.300   2.400   0.300  0.40 13.00           O
+ATOM      6  N   GLY A   2       3.320   1.500   0.000  1.00 14.00           N
+TER       7      GLY A   2
+HETATM    8 CA    CA B 101      10.000  10.000  10.000  1.00 20.00          CA
+HETATM    9  O   HOH B 102       3.500  -1.000   0.000  1.00 30.00           O
+CONECT    8    9
+END
+";
+
+    #[test]
+    fn test_parses_pdb_records() {
+        let mol = parse_pdb(PDB, "default").unwrap();
+
+        assert_eq!(mol.name, "1ABC");
+        // the second alt-loc of O is dropped
+        assert_eq!(mol.atoms.len(), 7);
+        assert_eq!(mol.atoms[3].alt_loc, Some('A'));
+        assert_eq!(mol.atoms[3].b_factor, Some(13.));
+        assert_eq!(mol.chain(&mol.atoms[5]).unwrap().name, "B");
+        let residue = mol.residue(&mol.atoms[5]).unwrap();
+        assert_eq!(residue.name, "CA");
+        assert_eq!(residue.id, 101);
+        assert_eq!(mol.residues.len(), 4);
+        // ids are the serials of the file
+        assert_eq!(mol.atoms[6].id, 9);
+    }
+
+    #[test]
+    fn test_pdb_bonds_from_conect_and_inferred() {
+        let mol = parse_pdb(PDB, "default").unwrap();
+        let pairs: Vec<(usize, usize)> = mol.bonds.iter().map(|b| (b.atom1, b.atom2)).collect();
+
+        // N-CA, CA-C, C-O inferred within residue 1, C-N peptide bond between residues
+        assert!(pairs.contains(&(0, 1)));
+        assert!(pairs.contains(&(1, 2)));
+        assert!(pairs.contains(&(2, 3)));
+        assert!(pairs.contains(&(2, 4)));
+        // from CONECT
+        assert!(pairs.contains(&(5, 6)));
+        assert_eq!(mol.bonds.len(), 5);
+    }
+
+    #[test]
+    fn test_pdb_assigns_orders_to_inferred_bonds() {
+        let mol = parse_pdb(PDB, "default").unwrap();
+        let type_of = |a: usize, b: usize| {
+            mol.bonds
+                .iter()
+                .find(|bond| (bond.atom1, bond.atom2) == (a, b))
+                .map(|bond| bond.type_)
+        };
+
+        assert_eq!(type_of(2, 3), Some(BondType::Double));
+        assert_eq!(type_of(2, 4), Some(BondType::Amide));
+        assert_eq!(type_of(0, 1), Some(BondType::Single));
+    }
+
+    #[test]
+    fn test_pdb_element_from_atom_name() {
+        // no element column
+        let pdb = "\
+ATOM      1  CA  GLY A   1       0.000   0.000   0.000  1.00  0.00
+HETATM    2 ZN    ZN A 101      10.000   0.000   0.000  1.00  0.00
+HETATM    3 FE   HEM A 102      20.000   0.000   0.000  1.00  0.00
+HETATM    4 CA    CA A 103      30.000   0.000   0.000  1.00  0.00
+HETATM    5  C1  LIG A 104      40.000   0.000   0.000  1.00  0.00
+END
+";
+        let mol = parse_pdb(pdb, "default").unwrap();
+        let elements: Vec<Element> = mol.atoms.iter().map(|a| a.element).collect();
+
+        assert_eq!(
+            elements,
+            vec![
+                Element::C,
+                Element::Zn,
+                Element::Fe,
+                Element::Ca,
+                Element::C
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pdb_disulfide_conect_keeps_residue_bonds() {
+        let pdb = "\
+ATOM      1  N   CYS A   1       0.000   0.000   0.000  1.00  0.00           N
+ATOM      2  CA  CYS A   1       1.460   0.000   0.000  1.00  0.00           C
+ATOM      3  C   CYS A   1       2.000   1.420   0.000  1.00  0.00           C
+ATOM      4  CB  CYS A   1       1.980  -0.760   1.220  1.00  0.00           C
+ATOM      5  SG  CYS A   1       3.790  -0.760   1.220  1.00  0.00           S
+ATOM      6  SG  CYS A   2       3.790  -0.760   3.270  1.00  0.00           S
+CONECT    5    6
+CONECT    6    5
+END
+";
+        let mol = parse_pdb(pdb, "default").unwrap();
+        let pairs: Vec<(usize, usize)> = mol.bonds.iter().map(|b| (b.atom1, b.atom2)).collect();
+
+        // N-CA, CA-C, CA-CB, CB-SG are still inferred
+        assert!(pairs.contains(&(0, 1)));
+        assert!(pairs.contains(&(1, 2)));
+        assert!(pairs.contains(&(1, 3)));
+        assert!(pairs.contains(&(3, 4)));
+        // disulfide from CONECT
+        assert!(pairs.contains(&(4, 5)));
+        assert_eq!(mol.bonds.len(), 5);
+    }
+
+    #[test]
+    fn test_pdb_conect_after_last_model() {
+        let pdb = "\
+MODEL        1
+HETATM    1  C1  ETH A   1       0.000   0.000   0.000  1.00  0.00           C
+HETATM    2  C2  ETH A   1       1.330   0.000   0.000  1.00  0.00           C
+ENDMDL
+MODEL        2
+HETATM    1  C1  ETH A   1       0.100   0.000   0.000  1.00  0.00           C
+HETATM    2  C2  ETH A   1       1.430   0.000   0.000  1.00  0.00           C
+ENDMDL
+CONECT    1    2    2
+CONECT    2    1    1
+END
+";
+        let mol = parse_pdb(pdb, "default").unwrap();
+
+        // atoms of the second model are ignored
+        assert_eq!(mol.atoms.len(), 2);
+        assert_eq!(mol.atoms[0].pos.x, 0.);
+        // the bond order comes from the CONECT records after the models
+        assert_eq!(mol.bonds.len(), 1);
+        assert_eq!(mol.bonds[0].type_, BondType::Double);
+    }
+
+    #[test]
+    fn test_parses_mmcif_atom_site() {
+        let cif = "\
+data_1ABC
+#
+loop_
+_atom_site.group_PDB
+_atom_site.id
+_atom_site.type_symbol
+_atom_site.label_atom_id
+_atom_site.label_alt_id
+_atom_site.label_comp_id
+_atom_site.label_asym_id
+_atom_site.label_seq_id
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+_atom_site.B_iso_or_equiv
+_atom_site.pdbx_PDB_model_num
+ATOM 1 N N . GLY A 1 0.000 0.000 0.000 10.00 1
+ATOM 2 C CA . GLY A 1 1.450 0.000 0.000 11.00 1
+ATOM 3 C \"C'\" . GLY A 1 2.000 1.400 0.000 12.00 1
+ATOM 4 N N . GLY A 1 0.000 0.000 0.000 10.00 2
+#
+";
+        let mol = parse_mmcif(cif, "default").unwrap();
+
+        assert_eq!(mol.name, "1ABC");
+        // second model is ignored
+        assert_eq!(mol.atoms.len(), 3);
+        assert_eq!(mol.atoms[2].name, "C'");
+        assert_eq!(mol.atoms[1].b_factor, Some(11.));
+        assert_eq!(mol.bonds.len(), 2);
+    }
+}
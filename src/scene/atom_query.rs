@@ -0,0 +1,477 @@
+use anyhow::{anyhow, bail, Result};
+use bevy::{
+    math::{IVec3, Vec3},
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    cartoon::is_water,
+    selection::{expand_selection, SelectBy},
+};
+use crate::{
+    element::Element,
+    molecule::{Atom, Molecule},
+    secondary_structure::backbone,
+};
+
+/// angstrom, smallest cell of the grid of `within`
+const MIN_CELL_SIZE: f32 = 0.1;
+
+/// a selection query (similar to VMD and PyMOL), e.g. `chain A and resname HIS and within 5 of resid 42`
+/// or `element C and not backbone`
+#[derive(Debug, Clone, PartialEq)]
+pub enum AtomQuery {
+    All,
+    None,
+    Not(Box<AtomQuery>),
+    And(Box<AtomQuery>, Box<AtomQuery>),
+    Or(Box<AtomQuery>, Box<AtomQuery>),
+    Chain(Vec<String>),
+    ResName(Vec<String>),
+    /// inclusive ranges of residue numbers
+    ResId(Vec<(i64, i64)>),
+    /// atom names
+    Name(Vec<String>),
+    Element(Vec<Element>),
+    /// inclusive ranges of indices in `Molecule::atoms`
+    Index(Vec<(i64, i64)>),
+    /// inclusive ranges of atom ids (serial numbers of the file)
+    Id(Vec<(i64, i64)>),
+    /// N, CA, C and O of the amino acid residues
+    Backbone,
+    /// the atoms of the amino acid residues that aren't in the backbone
+    Sidechain,
+    /// amino acid residues
+    Protein,
+    Water,
+    Hydrogen,
+    /// atoms closer than the distance (angstrom) to the atoms of the query
+    Within(f32, Box<AtomQuery>),
+    /// the residues of the atoms of the query
+    SameResidue(Box<AtomQuery>),
+    /// the chains of the atoms of the query
+    SameChain(Box<AtomQuery>),
+}
+
+/// parses a query: keywords with values (`chain`, `resname`, `resid`, `name`, `element`, `index`, `id`),
+/// `all`, `none`, `backbone`, `sidechain`, `protein`, `water`, `hydrogen`, `within <distance> of`,
+/// `same residue as`, `same chain as`, combined with `not`, `and`, `or` and parentheses.
+/// numbers take ranges, as `1-5`, `1:5` or `1 to 5`
+pub fn parse_atom_query(query: &str) -> Result<AtomQuery> {
+    let tokens = tokenize(query);
+    let mut parser = Parser { tokens, pos: 0 };
+    let query = parser.or()?;
+    match parser.peek() {
+        Some(token) => bail!("Unexpected: {}", token),
+        None => Ok(query),
+    }
+}
+
+impl AtomQuery {
+    /// indices in `Molecule::atoms` of the atoms matching the query
+    pub fn atoms(&self, mol: &Molecule) -> Vec<usize> {
+        self.mask(mol)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, selected)| *selected)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// per atom, whether it matches the query
+    fn mask(&self, mol: &Molecule) -> Vec<bool> {
+        let each = |f: &dyn Fn(usize, &Atom) -> bool| -> Vec<bool> {
+            mol.atoms.iter().enumerate().map(|(i, a)| f(i, a)).collect()
+        };
+        match self {
+            AtomQuery::All => vec![true; mol.atoms.len()],
+            AtomQuery::None => vec![false; mol.atoms.len()],
+            AtomQuery::Not(query) => query.mask(mol).into_iter().map(|s| !s).collect(),
+            AtomQuery::And(a, b) => (a.mask(mol).into_iter().zip(b.mask(mol)))
+                .map(|(a, b)| a && b)
+                .collect(),
+            AtomQuery::Or(a, b) => (a.mask(mol).into_iter().zip(b.mask(mol)))
+                .map(|(a, b)| a || b)
+                .collect(),
+            AtomQuery::Chain(names) => each(&|_, atom| {
+                mol.chain(atom)
+                    .is_some_and(|chain| matches_name(&chain.name, names))
+            }),
+            AtomQuery::ResName(names) => each(&|_, atom| {
+                mol.residue(atom)
+                    .is_some_and(|residue| matches_name(&residue.name, names))
+            }),
+            AtomQuery::ResId(ranges) => each(&|_, atom| {
+                mol.residue(atom)
+                    .is_some_and(|residue| in_ranges(residue.id as i64, ranges))
+            }),
+            AtomQuery::Name(names) => each(&|_, atom| matches_name(&atom.name, names)),
+            AtomQuery::Element(elements) => each(&|_, atom| elements.contains(&atom.element)),
+            AtomQuery::Index(ranges) => each(&|index, _| in_ranges(index as i64, ranges)),
+            AtomQuery::Id(ranges) => each(&|_, atom| in_ranges(atom.id as i64, ranges)),
+            AtomQuery::Backbone => {
+                let mut mask = vec![false; mol.atoms.len()];
+                for residue in backbone(mol) {
+                    for atom in [residue.n, residue.ca, residue.c, residue.o] {
+                        mask[atom] = true;
+                    }
+                }
+                mask
+            }
+            AtomQuery::Sidechain => {
+                let backbone = AtomQuery::Backbone.mask(mol);
+                (AtomQuery::Protein.mask(mol).into_iter().zip(backbone))
+                    .map(|(protein, backbone)| protein && !backbone)
+                    .collect()
+            }
+            AtomQuery::Protein => {
+                let residues: HashSet<usize> = backbone(mol).iter().map(|r| r.residue).collect();
+                each(&|_, atom| atom.residue.is_some_and(|r| residues.contains(&r)))
+            }
+            AtomQuery::Water => each(&|_, atom| {
+                mol.residue(atom)
+                    .is_some_and(|residue| is_water(&residue.name))
+            }),
+            AtomQuery::Hydrogen => each(&|_, atom| atom.element == Element::H),
+            AtomQuery::Within(distance, query) => {
+                let positions: Vec<_> = query
+                    .atoms(mol)
+                    .iter()
+                    .map(|a| mol.atoms[*a].position)
+                    .collect();
+                within_mask(mol, *distance, &positions)
+            }
+            AtomQuery::SameResidue(query) => expanded_mask(mol, query, SelectBy::Residue),
+            AtomQuery::SameChain(query) => expanded_mask(mol, query, SelectBy::Chain),
+        }
+    }
+}
+
+/// per atom, whether it's closer than the distance to one of the positions.
+/// the positions are put in a grid with the distance as cell size, so only the 27 cells around an atom are checked
+fn within_mask(mol: &Molecule, distance: f32, positions: &[Vec3]) -> Vec<bool> {
+    let distance = distance.max(0.0);
+    // a tiny cell would put every position in its own cell
+    let cell_size = distance.max(MIN_CELL_SIZE);
+    let cell = |position: Vec3| (position / cell_size).floor().as_ivec3();
+
+    let mut grid: HashMap<IVec3, Vec<Vec3>> = HashMap::new();
+    for position in positions {
+        grid.entry(cell(*position)).or_default().push(*position);
+    }
+
+    let max = distance * distance;
+    mol.atoms
+        .iter()
+        .map(|atom| {
+            let center = cell(atom.position);
+            (-1..=1).any(|x| {
+                (-1..=1).any(|y| {
+                    (-1..=1).any(|z| {
+                        grid.get(&(center + IVec3::new(x, y, z)))
+                            .is_some_and(|cell_positions| {
+                                cell_positions
+                                    .iter()
+                                    .any(|p| p.distance_squared(atom.position) <= max)
+                            })
+                    })
+                })
+            })
+        })
+        .collect()
+}
+
+fn expanded_mask(mol: &Molecule, query: &AtomQuery, by: SelectBy) -> Vec<bool> {
+    let mut mask = vec![false; mol.atoms.len()];
+    for atom in expand_selection(mol, &query.atoms(mol), by) {
+        mask[atom] = true;
+    }
+    mask
+}
+
+/// names are compared ignoring the case, as in the files they're usually upper case
+fn matches_name(name: &str, names: &[String]) -> bool {
+    names.iter().any(|n| n.eq_ignore_ascii_case(name.trim()))
+}
+
+fn in_ranges(value: i64, ranges: &[(i64, i64)]) -> bool {
+    ranges
+        .iter()
+        .any(|(start, end)| (*start..=*end).contains(&value))
+}
+
+/// words, with parentheses as separate tokens
+fn tokenize(query: &str) -> Vec<String> {
+    query
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(|token| token.to_string())
+        .collect()
+}
+
+/// recursive descent, `or` binds weaker than `and`, which binds weaker than `not`
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    /// the next token if it's the keyword (ignoring the case)
+    fn eat(&mut self, keyword: &str) -> bool {
+        let matches = self
+            .peek()
+            .is_some_and(|token| token.eq_ignore_ascii_case(keyword));
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn next(&mut self) -> Result<String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unexpected end of query"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> Result<AtomQuery> {
+        let mut query = self.and()?;
+        while self.eat("or") {
+            query = AtomQuery::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<AtomQuery> {
+        let mut query = self.not()?;
+        while self.eat("and") {
+            query = AtomQuery::And(Box::new(query), Box::new(self.not()?));
+        }
+        Ok(query)
+    }
+
+    fn not(&mut self) -> Result<AtomQuery> {
+        if self.eat("not") {
+            return Ok(AtomQuery::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<AtomQuery> {
+        let token = self.next()?;
+        let query = match token.to_ascii_lowercase().as_str() {
+            "(" => {
+                let query = self.or()?;
+                if !self.eat(")") {
+                    bail!("Missing )");
+                }
+                query
+            }
+            "all" => AtomQuery::All,
+            "none" => AtomQuery::None,
+            "backbone" => AtomQuery::Backbone,
+            "sidechain" => AtomQuery::Sidechain,
+            "protein" => AtomQuery::Protein,
+            "water" => AtomQuery::Water,
+            "hydrogen" => AtomQuery::Hydrogen,
+            "within" => {
+                let distance = self.next()?;
+                let distance: f32 = distance
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid distance: {}", distance))?;
+                if !self.eat("of") {
+                    bail!("Expected of after within {}", distance);
+                }
+                AtomQuery::Within(distance, Box::new(self.not()?))
+            }
+            "same" => {
+                let by = self.next()?.to_ascii_lowercase();
+                if !self.eat("as") {
+                    bail!("Expected as after same {}", by);
+                }
+                let query = Box::new(self.not()?);
+                match by.as_str() {
+                    "residue" => AtomQuery::SameResidue(query),
+                    "chain" => AtomQuery::SameChain(query),
+                    _ => bail!("Expected residue or chain after same"),
+                }
+            }
+            "chain" => AtomQuery::Chain(self.values(&token)?),
+            "resname" | "resn" => AtomQuery::ResName(self.values(&token)?),
+            "resid" | "resi" => AtomQuery::ResId(self.ranges(&token)?),
+            "name" => AtomQuery::Name(self.values(&token)?),
+            "element" | "elem" => AtomQuery::Element(
+                self.values(&token)?
+                    .iter()
+                    .map(|symbol| parse_element(symbol))
+                    .collect::<Result<_>>()?,
+            ),
+            "index" => AtomQuery::Index(self.ranges(&token)?),
+            "id" | "serial" => AtomQuery::Id(self.ranges(&token)?),
+            _ => bail!("Unknown keyword: {}", token),
+        };
+        Ok(query)
+    }
+
+    /// the values of a keyword, up to the next operator or parenthesis
+    fn values(&mut self, keyword: &str) -> Result<Vec<String>> {
+        let mut values = vec![];
+        while let Some(token) = self.peek() {
+            let operator = ["and", "or", "not", "(", ")"]
+                .iter()
+                .any(|o| token.eq_ignore_ascii_case(o));
+            if operator {
+                break;
+            }
+            values.push(token.to_string());
+            self.pos += 1;
+        }
+        if values.is_empty() {
+            bail!("Expected values after {}", keyword);
+        }
+        Ok(values)
+    }
+
+    /// numbers and ranges (`1-5`, `1:5`, `1 to 5`)
+    fn ranges(&mut self, keyword: &str) -> Result<Vec<(i64, i64)>> {
+        let values = self.values(keyword)?;
+        let number = |value: &str| -> Result<i64> {
+            value
+                .parse()
+                .map_err(|_| anyhow!("Invalid number after {}: {}", keyword, value))
+        };
+        let mut ranges: Vec<(i64, i64)> = vec![];
+        let mut values = values.iter();
+        while let Some(value) = values.next() {
+            if value.eq_ignore_ascii_case("to") {
+                let start = ranges.pop().map(|(start, _)| start);
+                let end = values.next().map(|v| number(v)).transpose()?;
+                let (Some(start), Some(end)) = (start, end) else {
+                    bail!("Invalid range after {}", keyword);
+                };
+                ranges.push((start, end));
+                continue;
+            }
+            // the first character may be the sign of the start
+            let range = value
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| *c == '-' || *c == ':')
+                .map(|(i, _)| (&value[..i], &value[i + 1..]));
+            ranges.push(match range {
+                Some((start, end)) => (number(start)?, number(end)?),
+                None => (number(value)?, number(value)?),
+            });
+        }
+        Ok(ranges)
+    }
+}
+
+/// the symbol in any case, e.g. `CL` or `cl`
+fn parse_element(symbol: &str) -> Result<Element> {
+    let mut chars = symbol.chars();
+    let capitalized: String = chars
+        .next()
+        .map(|c| c.to_ascii_uppercase())
+        .into_iter()
+        .chain(chars.map(|c| c.to_ascii_lowercase()))
+        .collect();
+    Element::from_symbol(symbol)
+        .or_else(|| Element::from_symbol(&capitalized))
+        .ok_or_else(|| anyhow!("Unknown element: {}", symbol))
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec3;
+
+    use super::{parse_atom_query, AtomQuery};
+    use crate::{
+        element::Element,
+        molecule::{Atom, Molecule},
+    };
+
+    #[test]
+    fn test_parses_queries() {
+        let query = parse_atom_query("chain A and not resname HIS or resid 1-3 5 to 7").unwrap();
+        assert_eq!(
+            query,
+            AtomQuery::Or(
+                Box::new(AtomQuery::And(
+                    Box::new(AtomQuery::Chain(vec!["A".to_string()])),
+                    Box::new(AtomQuery::Not(Box::new(AtomQuery::ResName(vec![
+                        "HIS".to_string()
+                    ]))))
+                )),
+                Box::new(AtomQuery::ResId(vec![(1, 3), (5, 7)]))
+            )
+        );
+        assert_eq!(
+            parse_atom_query("within 5 of (element cl)").unwrap(),
+            AtomQuery::Within(5.0, Box::new(AtomQuery::Element(vec![Element::Cl])))
+        );
+        assert!(parse_atom_query("chain").is_err());
+        assert!(parse_atom_query("(protein").is_err());
+        assert!(parse_atom_query("protein water").is_err());
+        assert!(parse_atom_query("resid x").is_err());
+        assert!(parse_atom_query("colour red").is_err());
+    }
+
+    #[test]
+    fn test_evaluates_queries() {
+        let mut mol = Molecule::default();
+        // a residue with a backbone, a histidine without one, a water
+        let atoms = [
+            ("N", Element::N, 1, "ALA", "A", Vec3::new(0.0, 0.0, 0.0)),
+            ("CA", Element::C, 1, "ALA", "A", Vec3::new(1.5, 0.0, 0.0)),
+            ("C", Element::C, 1, "ALA", "A", Vec3::new(2.0, 1.5, 0.0)),
+            ("O", Element::O, 1, "ALA", "A", Vec3::new(1.5, 2.5, 0.0)),
+            ("CB", Element::C, 1, "ALA", "A", Vec3::new(2.0, -1.5, 0.0)),
+            ("NE2", Element::N, 2, "HIS", "A", Vec3::new(10.0, 0.0, 0.0)),
+            ("O", Element::O, 3, "HOH", "B", Vec3::new(5.0, 0.0, 0.0)),
+        ];
+        for (i, (name, element, resid, resname, chain, position)) in atoms.into_iter().enumerate() {
+            let atom = Atom::new(i as i32 + 1, name.to_string(), element, position);
+            mol.add_atom(atom, Some((resid, resname)), Some(chain));
+        }
+        let atoms = |query: &str| parse_atom_query(query).unwrap().atoms(&mol);
+
+        assert_eq!(atoms("backbone"), vec![0, 1, 2, 3]);
+        assert_eq!(atoms("element C and not backbone"), vec![4]);
+        assert_eq!(atoms("sidechain"), vec![4]);
+        assert_eq!(atoms("chain A and resname his"), vec![5]);
+        assert_eq!(atoms("water or id 1"), vec![0, 6]);
+        assert_eq!(atoms("within 6 of resid 2"), vec![5, 6]);
+        assert_eq!(atoms("same residue as name CB"), vec![0, 1, 2, 3, 4]);
+        assert_eq!(atoms("same chain as water"), vec![6]);
+        assert_eq!(atoms("index 0:1 or index 6"), vec![0, 1, 6]);
+        assert_eq!(atoms("not all"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_within_uses_neighbor_cells() {
+        // atoms on a line, 1.5 apart, across many cells of the grid and around the origin
+        let mut mol = Molecule::default();
+        for i in 0..20 {
+            let position = Vec3::new(i as f32 * 1.5 - 15.0, 0.3, -0.2);
+            let atom = Atom::new(i + 1, "C".to_string(), Element::C, position);
+            mol.add_atom(atom, None, None);
+        }
+        let atoms = |query: &str| parse_atom_query(query).unwrap().atoms(&mol);
+
+        assert_eq!(atoms("within 2 of index 10"), vec![9, 10, 11]);
+        assert_eq!(atoms("within 3 of index 10"), vec![8, 9, 10, 11, 12]);
+        assert_eq!(atoms("within 3 of index 0 19"), vec![0, 1, 2, 17, 18, 19]);
+        assert_eq!(atoms("within 0 of index 5"), vec![5]);
+        assert_eq!(atoms("within 100 of index 0").len(), 20);
+        assert_eq!(atoms("within 5 of none"), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,263 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+
+use super::resource::CartoonColor;
+use crate::{
+    molecule::Molecule,
+    secondary_structure::{BackboneResidue, SecondaryStructure},
+};
+
+/// spline points between two residues
+const SEGMENTS_PER_RESIDUE: usize = 8;
+/// points of the (elliptic) cross section of the ribbon
+const CROSS_SECTION_POINTS: usize = 12;
+const COIL_RADIUS: f32 = 0.25;
+const HELIX_WIDTH: f32 = 1.6;
+const HELIX_THICKNESS: f32 = 0.3;
+const STRAND_WIDTH: f32 = 1.4;
+const STRAND_THICKNESS: f32 = 0.35;
+/// width of the base of the arrow at the end of strands
+const ARROW_WIDTH: f32 = 2.2;
+
+const HELIX_COLOR: Srgba = Srgba::rgb(0.9, 0.25, 0.3);
+const STRAND_COLOR: Srgba = Srgba::rgb(0.95, 0.8, 0.2);
+const COIL_COLOR: Srgba = Srgba::rgb(0.8, 0.8, 0.8);
+const CHAIN_COLORS: [Srgba; 6] = [
+    Srgba::rgb(0.3, 0.6, 0.9),
+    Srgba::rgb(0.4, 0.8, 0.4),
+    Srgba::rgb(0.95, 0.6, 0.2),
+    Srgba::rgb(0.7, 0.4, 0.85),
+    Srgba::rgb(0.3, 0.8, 0.8),
+    Srgba::rgb(0.9, 0.45, 0.6),
+];
+
+/// water isn't shown with the cartoon, as it hides the structure
+pub fn is_water(residue_name: &str) -> bool {
+    matches!(residue_name, "HOH" | "WAT" | "H2O" | "SOL")
+}
+
+/// vertex color of each backbone residue
+pub fn cartoon_colors(
+    mol: &Molecule,
+    backbone: &[BackboneResidue],
+    structure: &[SecondaryStructure],
+    color: &CartoonColor,
+) -> Vec<[f32; 4]> {
+    backbone
+        .iter()
+        .zip(structure)
+        .map(|(residue, structure)| {
+            let color = match color {
+                CartoonColor::Chain => {
+                    let chain = mol.residues[residue.residue].chain.unwrap_or(0);
+                    CHAIN_COLORS[chain % CHAIN_COLORS.len()]
+                }
+                CartoonColor::SecondaryStructure => match structure {
+                    SecondaryStructure::Helix => HELIX_COLOR,
+                    SecondaryStructure::Strand => STRAND_COLOR,
+                    SecondaryStructure::Coil => COIL_COLOR,
+                },
+            };
+            LinearRgba::from(color).to_f32_array()
+        })
+        .collect()
+}
+
+/// ribbon through the CA atoms of each connected part of the backbone (a spline), with helices as flat ribbons,
+/// strands as arrows and coils as tubes. the side of the ribbon follows the C=O of the residues
+pub fn cartoon_mesh(
+    backbone: &[BackboneResidue],
+    structure: &[SecondaryStructure],
+    colors: &[[f32; 4]],
+    positions: impl Fn(usize) -> Vec3,
+) -> Mesh {
+    let mut mesh = CartoonMesh::default();
+    let mut start = 0;
+    for end in 1..=backbone.len() {
+        if end == backbone.len() || !backbone[end].connected {
+            add_segment(
+                &mut mesh,
+                start..end,
+                backbone,
+                structure,
+                colors,
+                &positions,
+            );
+            start = end;
+        }
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, mesh.positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, mesh.normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, mesh.colors)
+    .with_inserted_indices(Indices::U32(mesh.indices))
+}
+
+#[derive(Default)]
+struct CartoonMesh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+/// a point of the spline, where a cross section is placed
+struct SplinePoint {
+    position: Vec3,
+    tangent: Vec3,
+    /// direction of the width of the ribbon, perpendicular to the tangent
+    side: Vec3,
+    /// index in the backbone
+    residue: usize,
+    /// position along the spline, in residues
+    t: f32,
+}
+
+fn add_segment(
+    mesh: &mut CartoonMesh,
+    range: std::ops::Range<usize>,
+    backbone: &[BackboneResidue],
+    structure: &[SecondaryStructure],
+    colors: &[[f32; 4]],
+    positions: &impl Fn(usize) -> Vec3,
+) {
+    // a single residue has no direction
+    if range.len() < 2 {
+        return;
+    }
+    let is_strand = |i: usize| range.contains(&i) && structure[i] == SecondaryStructure::Strand;
+
+    // strands zigzag, they're smoothed to be drawn straight
+    let guide: Vec<Vec3> = range
+        .clone()
+        .map(|i| {
+            let ca = positions(backbone[i].ca);
+            if is_strand(i) && i > range.start && i + 1 < range.end {
+                let previous = positions(backbone[i - 1].ca);
+                let next = positions(backbone[i + 1].ca);
+                (previous + 2.0 * ca + next) / 4.0
+            } else {
+                ca
+            }
+        })
+        .collect();
+    // the sides flip between consecutive residues, they're flipped back to avoid twisted ribbons
+    let mut sides: Vec<Vec3> = range
+        .clone()
+        .map(|i| (positions(backbone[i].o) - positions(backbone[i].c)).normalize_or_zero())
+        .collect();
+    for i in 1..sides.len() {
+        if sides[i].dot(sides[i - 1]) < 0.0 {
+            sides[i] = -sides[i];
+        }
+    }
+
+    let last = guide.len() - 1;
+    let point = |k: usize, t: f32| {
+        let p0 = guide[k.saturating_sub(1)];
+        let p1 = guide[k];
+        let p2 = guide[(k + 1).min(last)];
+        let p3 = guide[(k + 2).min(last)];
+        let (position, tangent) = catmull_rom(p0, p1, p2, p3, t);
+        let tangent = tangent.normalize_or_zero();
+        let side = sides[k].lerp(sides[(k + 1).min(last)], t);
+        let side = (side - tangent * side.dot(tangent)).normalize_or_zero();
+        let side = if side == Vec3::ZERO {
+            tangent.any_orthonormal_vector()
+        } else {
+            side
+        };
+        SplinePoint {
+            position,
+            tangent,
+            side,
+            residue: range.start + if t < 0.5 { k } else { k + 1 },
+            t: k as f32 + t,
+        }
+    };
+    let mut points: Vec<SplinePoint> = (0..last)
+        .flat_map(|k| {
+            (0..SEGMENTS_PER_RESIDUE).map(move |s| (k, s as f32 / SEGMENTS_PER_RESIDUE as f32))
+        })
+        .map(|(k, t)| point(k, t))
+        .collect();
+    points.push(point(last, 0.0));
+
+    let first_vertex = mesh.positions.len() as u32;
+    for point in &points {
+        let residue = point.residue;
+        let (width, thickness) = match structure[residue] {
+            SecondaryStructure::Coil => (2.0 * COIL_RADIUS, 2.0 * COIL_RADIUS),
+            SecondaryStructure::Helix => (HELIX_WIDTH, HELIX_THICKNESS),
+            // the last residue of a strand is the arrow head, narrowing from its base to the tip
+            SecondaryStructure::Strand if !is_strand(residue + 1) => {
+                let u = point.t - (residue - range.start) as f32;
+                (ARROW_WIDTH * (0.5 - u), STRAND_THICKNESS)
+            }
+            SecondaryStructure::Strand => (STRAND_WIDTH, STRAND_THICKNESS),
+        };
+        // the tip of the arrow is as narrow as the ribbon is thick
+        let (a, b) = (width.max(thickness) / 2.0, thickness / 2.0);
+        let normal = point.tangent.cross(point.side);
+        for j in 0..CROSS_SECTION_POINTS {
+            let angle = std::f32::consts::TAU * j as f32 / CROSS_SECTION_POINTS as f32;
+            let (sin, cos) = angle.sin_cos();
+            mesh.positions
+                .push(point.position + point.side * a * cos + normal * b * sin);
+            mesh.normals
+                .push((point.side * cos / a + normal * sin / b).normalize_or_zero());
+            mesh.colors.push(colors[residue]);
+        }
+    }
+
+    let ring = |i: usize, j: usize| {
+        first_vertex + (i * CROSS_SECTION_POINTS + j % CROSS_SECTION_POINTS) as u32
+    };
+    for i in 0..points.len() - 1 {
+        for j in 0..CROSS_SECTION_POINTS {
+            let (a, b, c, d) = (
+                ring(i, j),
+                ring(i, j + 1),
+                ring(i + 1, j),
+                ring(i + 1, j + 1),
+            );
+            mesh.indices.extend([a, c, b, b, c, d]);
+        }
+    }
+
+    // close the ends
+    for (i, direction) in [(0, -1.0), (points.len() - 1, 1.0)] {
+        let point = &points[i];
+        let center = mesh.positions.len() as u32;
+        mesh.positions.push(point.position);
+        mesh.normals.push(point.tangent * direction);
+        mesh.colors.push(colors[point.residue]);
+        for j in 0..CROSS_SECTION_POINTS {
+            mesh.indices.extend([center, ring(i, j), ring(i, j + 1)]);
+        }
+    }
+}
+
+/// position and tangent of a catmull-rom spline between `p1` and `p2`, at `t` (0 to 1)
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> (Vec3, Vec3) {
+    let (t2, t3) = (t * t, t * t * t);
+    let position = 0.5
+        * (2.0 * p1
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3);
+    let tangent = 0.5
+        * ((p2 - p0)
+            + 2.0 * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t
+            + 3.0 * (3.0 * p1 - p0 - 3.0 * p2 + p3) * t2);
+    (position, tangent)
+}
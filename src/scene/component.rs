@@ -1,4 +1,15 @@
-use bevy::prelude::Component;
+use bevy::{
+    asset::AssetId,
+    prelude::{Component, Vec3},
+    tasks::Task,
+};
+
+use super::{resource::SurfaceColor, system::UNKNOWN_BOND_DASHES};
+use crate::{
+    molecule::{Bond, BondType, Molecule},
+    secondary_structure::{BackboneResidue, SecondaryStructure},
+    surface::SurfaceGeometry,
+};
 
 #[derive(Component, Default)]
 pub struct MyMolecule;
@@ -9,10 +20,95 @@ pub struct MyMoleculeWrapper;
 #[derive(Component, Default)]
 pub struct MyParent;
 
+/// label of a measurement in the scene, moved to the projection of the measured atoms
+#[derive(Component, Default)]
+pub struct MeasurementLabel {
+    /// `Measurement::id`
+    pub id: usize,
+}
+
+/// an atom sphere
+#[derive(Component, Default)]
+pub struct MyAtom {
+    /// index in `Molecule::atoms`, to update the position when playing a trajectory
+    pub index: usize,
+}
+
 #[derive(Component, Default)]
 pub struct MyBond {
+    /// length of the cylinder (shorter than the bond for dashes)
     pub length: f32,
+    /// index in `Molecule::bonds`, to update the position when playing a trajectory
+    pub index: usize,
+    /// which of the cylinders of the bond (lines of double / triple bonds, dashes)
+    pub line: usize,
+    /// where the additional lines of double / triple / aromatic bonds are drawn
+    pub side: BondSide,
+}
+
+impl MyBond {
+    /// length of the bond, from the cylinder of its first line (a dash for unknown bonds)
+    pub fn bond_length(&self, bond: &Bond) -> f32 {
+        match bond.type_ {
+            BondType::Dummy | BondType::Unknown => {
+                self.length * (2 * UNKNOWN_BOND_DASHES - 1) as f32
+            }
+            _ => self.length,
+        }
+    }
+}
+
+/// atoms that define the side (and plane) of the additional lines of a bond
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum BondSide {
+    #[default]
+    None,
+    /// indices of the atoms of the smallest ring of the bond, the lines are drawn towards its center
+    Ring(Vec<usize>),
+    /// index of a (not collinear) neighbor atom, the lines are drawn in the plane of the bond and the atom
+    Atom(usize),
+}
+
+impl BondSide {
+    /// the point the lines are drawn towards, for the given atom positions
+    pub fn position(&self, positions: impl Fn(usize) -> Vec3) -> Option<Vec3> {
+        match self {
+            BondSide::None => None,
+            BondSide::Ring(atoms) => {
+                Some(atoms.iter().map(|a| positions(*a)).sum::<Vec3>() / atoms.len() as f32)
+            }
+            BondSide::Atom(atom) => Some(positions(*atom)),
+        }
+    }
+
+    pub fn is_ring(&self) -> bool {
+        matches!(self, BondSide::Ring(_))
+    }
 }
 
 #[derive(Component)]
 pub struct Shape;
+
+/// the cartoon mesh of a molecule, with what's needed to rebuild it when playing a trajectory
+#[derive(Component)]
+pub struct MyCartoon {
+    pub backbone: Vec<BackboneResidue>,
+    /// per backbone residue, assigned with the first frame
+    pub structure: Vec<SecondaryStructure>,
+    /// per backbone residue
+    pub colors: Vec<[f32; 4]>,
+}
+
+/// surface of a molecule. it's computed in the background (`SurfaceTask`), the mesh is added when done
+#[derive(Component)]
+pub struct MySurface {
+    pub mol: AssetId<Molecule>,
+    /// for each vertex, the atom it's colored with
+    pub nearest_atoms: Vec<usize>,
+    /// the coloring of the vertices, to recolor them when it changes without computing the surface again
+    pub color: SurfaceColor,
+}
+
+/// the surface being computed for `MySurface`
+#[derive(Component)]
+pub struct SurfaceTask(pub Task<SurfaceGeometry>);
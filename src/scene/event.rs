@@ -4,6 +4,14 @@ use bevy::prelude::Event;
 #[derive(Event, Debug)]
 pub struct UpdateSceneEvent;
 
+/// the style (`MolScene::render`) changed, the entities are updated in place if possible, otherwise the scene is rebuilt
+#[derive(Event, Debug)]
+pub struct UpdateStyleEvent;
+
+/// the atoms of `Selection` changed
+#[derive(Event, Debug)]
+pub struct SelectionChangedEvent;
+
 /// a new bounding box (from a molecule) was added to the scene
 #[derive(Event, Debug)]
 pub struct AddedBoundingBox(pub BoundingBox);
@@ -0,0 +1,567 @@
+use bevy::{
+    core_pipeline::core_3d::Transparent3d,
+    ecs::{
+        query::QueryItem,
+        system::{lifetimeless::*, SystemParamItem},
+    },
+    pbr::{
+        MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup,
+    },
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        mesh::{GpuBufferInfo, GpuMesh, MeshVertexBufferLayoutRef},
+        render_asset::RenderAssets,
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand,
+            RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+        },
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        view::ExtractedView,
+        Render, RenderApp, RenderSet,
+    },
+    utils::{HashMap, HashSet},
+};
+
+use super::component::BondSide;
+use crate::molecule::Molecule;
+
+const SHADER_PATH: &str = "embedded://mol/asset/shaders/impostor.wgsl";
+/// floats per instance: start and radius, end and padding, color
+const INSTANCE_FLOATS: usize = 12;
+/// vertices of `impostor_quad_mesh`
+const QUAD_VERTICES: usize = 4;
+const HOVER_TINT: LinearRgba = LinearRgba::rgb(0.2, 0.4, 1.0);
+const SELECTED_TINT: LinearRgba = LinearRgba::rgb(0.3, 0.3, 1.0);
+
+/// draws spheres and cylinders as ray cast camera facing quads, one instanced draw call per entity,
+/// instead of an entity with a mesh per atom and bond
+pub struct ImpostorPlugin;
+
+impl Plugin for ImpostorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<Impostors>::default());
+        app.sub_app_mut(RenderApp)
+            .add_render_command::<Transparent3d, DrawImpostors>()
+            .init_resource::<SpecializedMeshPipelines<ImpostorPipeline>>()
+            .init_resource::<ImpostorBuffers>()
+            .add_systems(
+                Render,
+                (
+                    queue_impostors.in_set(RenderSet::QueueMeshes),
+                    prepare_instance_buffers.in_set(RenderSet::PrepareResources),
+                    write_mesh_indices.in_set(RenderSet::PrepareResourcesFlush),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<ImpostorPipeline>();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImpostorShape {
+    Sphere,
+    /// with flat caps
+    Cylinder,
+}
+
+/// a sphere (`start` is the center) or cylinder, in the local space of the entity
+#[derive(Debug, Clone, Copy)]
+pub struct ImpostorInstance {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub radius: f32,
+    pub color: LinearRgba,
+}
+
+/// the shapes drawn by the entity. the entity needs a quad mesh (see `impostor_quad_mesh`),
+/// which is drawn once per instance, `NoFrustumCulling`, as the mesh doesn't bound the instances,
+/// and `NoAutomaticBatching`, as each entity is drawn with its own instances
+#[derive(Component, Debug, Clone)]
+pub struct Impostors {
+    pub shape: ImpostorShape,
+    pub instances: Vec<ImpostorInstance>,
+    /// index of the instance under the cursor, drawn tinted
+    pub hovered: Option<usize>,
+    /// indices of the instances of selected atoms and bonds, drawn tinted
+    pub selected: HashSet<usize>,
+}
+
+/// the atoms drawn by a sphere impostor entity, to pick them and update their positions
+#[derive(Component, Debug)]
+pub struct ImpostorAtoms {
+    pub mol: AssetId<Molecule>,
+    /// index in `Molecule::atoms`, per instance
+    pub atoms: Vec<usize>,
+}
+
+/// the bond lines drawn by a cylinder impostor entity, to update their positions
+#[derive(Component, Debug)]
+pub struct ImpostorBonds {
+    pub mol: AssetId<Molecule>,
+    /// per instance
+    pub lines: Vec<ImpostorBondLine>,
+}
+
+/// see `MyBond`
+#[derive(Debug)]
+pub struct ImpostorBondLine {
+    /// index in `Molecule::bonds`
+    pub index: usize,
+    /// which of the cylinders of the bond
+    pub line: usize,
+    pub side: BondSide,
+}
+
+/// the quad the impostors are drawn on, from -1 to 1
+pub fn impostor_quad_mesh() -> Mesh {
+    Rectangle::new(2.0, 2.0).into()
+}
+
+/// index of the nearest sphere (center, radius) hit by the ray, with the ray parameter of the hit.
+/// the direction doesn't have to be normalized
+pub fn ray_spheres_intersection(
+    origin: Vec3,
+    direction: Vec3,
+    spheres: impl Iterator<Item = (Vec3, f32)>,
+) -> Option<(usize, f32)> {
+    let a = direction.length_squared();
+    if a == 0.0 {
+        return None;
+    }
+    spheres
+        .enumerate()
+        .filter_map(|(index, (center, radius))| {
+            let oc = origin - center;
+            let b = oc.dot(direction);
+            let c = oc.length_squared() - radius * radius;
+            let h = b * b - a * c;
+            if h < 0.0 {
+                return None;
+            }
+            let t = (-b - h.sqrt()) / a;
+            // the camera may be in the sphere
+            let t = if t < 0.0 { (-b + h.sqrt()) / a } else { t };
+            (t >= 0.0).then_some((index, t))
+        })
+        .min_by(|(_, t1), (_, t2)| t1.total_cmp(t2))
+}
+
+/// index of the nearest capped cylinder (start, end, radius) hit by the ray, with the ray parameter of the hit,
+/// as the cylinders are drawn (see `cylinder_fragment` of the shader). the direction doesn't have to be normalized
+pub fn ray_cylinders_intersection(
+    origin: Vec3,
+    direction: Vec3,
+    cylinders: impl Iterator<Item = (Vec3, Vec3, f32)>,
+) -> Option<(usize, f32)> {
+    let dd = direction.length_squared();
+    if dd == 0.0 {
+        return None;
+    }
+    cylinders
+        .enumerate()
+        .filter_map(|(index, (start, end, radius))| {
+            let ba = end - start;
+            let oc = origin - start;
+            let baba = ba.length_squared();
+            let bard = ba.dot(direction);
+            let baoc = ba.dot(oc);
+            let k2 = baba * dd - bard * bard;
+            let k1 = baba * oc.dot(direction) - baoc * bard;
+            let k0 = baba * oc.length_squared() - baoc * baoc - radius * radius * baba;
+            let h = k1 * k1 - k2 * k0;
+            if h < 0.0 || baba == 0.0 {
+                return None;
+            }
+            let h = h.sqrt();
+
+            // body
+            let t = (-k1 - h) / k2;
+            let y = baoc + t * bard;
+            if y > 0.0 && y < baba {
+                return (t >= 0.0).then_some((index, t));
+            }
+            // caps
+            let t = (if y < 0.0 { 0.0 } else { baba } - baoc) / bard;
+            ((k1 + k2 * t).abs() < h && t >= 0.0).then_some((index, t))
+        })
+        .min_by(|(_, t1), (_, t2)| t1.total_cmp(t2))
+}
+
+/// instance data in the local space of the entity, as the instance buffer layout.
+/// the model matrix is applied in the shader, from the mesh uniform of the entity
+#[derive(Component)]
+pub struct ExtractedImpostors {
+    shape: ImpostorShape,
+    /// `None` if the impostors didn't change since the last extraction, the uploaded buffer is kept then
+    data: Option<Vec<f32>>,
+}
+
+impl ExtractComponent for Impostors {
+    type QueryData = Ref<'static, Impostors>;
+    type QueryFilter = ();
+    type Out = ExtractedImpostors;
+
+    fn extract_component(impostors: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        if impostors.instances.is_empty() {
+            return None;
+        }
+        if !impostors.is_changed() {
+            return Some(ExtractedImpostors {
+                shape: impostors.shape,
+                data: None,
+            });
+        }
+        let data = impostors
+            .instances
+            .iter()
+            .enumerate()
+            .flat_map(|(index, instance)| {
+                let color = if impostors.hovered == Some(index) {
+                    instance.color.mix(&HOVER_TINT, 0.5)
+                } else if impostors.selected.contains(&index) {
+                    instance.color.mix(&SELECTED_TINT, 0.5)
+                } else {
+                    instance.color
+                };
+                let floats: [f32; INSTANCE_FLOATS] = [
+                    instance.start.x,
+                    instance.start.y,
+                    instance.start.z,
+                    instance.radius,
+                    instance.end.x,
+                    instance.end.y,
+                    instance.end.z,
+                    0.0,
+                    color.red,
+                    color.green,
+                    color.blue,
+                    color.alpha,
+                ];
+                floats
+            })
+            .collect();
+        Some(ExtractedImpostors {
+            shape: impostors.shape,
+            data: Some(data),
+        })
+    }
+}
+
+/// the instance buffers of the impostor entities. render world entities are despawned every frame,
+/// so they're kept here, by entity, to upload the instances only when they change
+#[derive(Resource, Default)]
+struct ImpostorBuffers(HashMap<Entity, InstanceBuffer>);
+
+struct InstanceBuffer {
+    buffer: Buffer,
+    length: usize,
+    /// the index of the entity in the mesh uniforms, per quad vertex (see `write_mesh_indices`)
+    mesh_index: Buffer,
+}
+
+fn prepare_instance_buffers(
+    query: Query<(Entity, &ExtractedImpostors)>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffers: ResMut<ImpostorBuffers>,
+) {
+    // despawned entities, or without instances
+    buffers.0.retain(|entity, _| query.contains(*entity));
+
+    for (entity, impostors) in &query {
+        let Some(data) = &impostors.data else {
+            continue;
+        };
+        let contents: Vec<u8> = data.iter().flat_map(|f| f.to_ne_bytes()).collect();
+        let length = data.len() / INSTANCE_FLOATS;
+        match buffers.0.get_mut(&entity) {
+            // e.g. moved by a trajectory frame
+            Some(buffer) if buffer.length == length => {
+                render_queue.write_buffer(&buffer.buffer, 0, &contents);
+            }
+            _ => {
+                let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("impostor instance buffer"),
+                    contents: &contents,
+                    usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                });
+                let mesh_index = render_device.create_buffer(&BufferDescriptor {
+                    label: Some("impostor mesh index buffer"),
+                    size: (QUAD_VERTICES * std::mem::size_of::<u32>()) as u64,
+                    usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                buffers.0.insert(
+                    entity,
+                    InstanceBuffer {
+                        buffer,
+                        length,
+                        mesh_index,
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// writes the index of each impostor entity in the mesh uniforms, which is known once the phase items are batched.
+/// it's passed as a vertex attribute of the quad, as the instance index is used for the instances
+fn write_mesh_indices(
+    transparent_render_phases: Res<ViewSortedRenderPhases<Transparent3d>>,
+    transparent_3d_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    buffers: Res<ImpostorBuffers>,
+    render_queue: Res<RenderQueue>,
+) {
+    let draw_impostors = transparent_3d_draw_functions.read().id::<DrawImpostors>();
+    for phase in transparent_render_phases.values() {
+        for item in phase
+            .items
+            .iter()
+            .filter(|item| item.draw_function == draw_impostors)
+        {
+            let Some(buffer) = buffers.0.get(&item.entity) else {
+                continue;
+            };
+            let indices: Vec<u8> = [item.batch_range.start; QUAD_VERTICES]
+                .iter()
+                .flat_map(|i| i.to_ne_bytes())
+                .collect();
+            render_queue.write_buffer(&buffer.mesh_index, 0, &indices);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_impostors(
+    transparent_3d_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    impostor_pipeline: Res<ImpostorPipeline>,
+    msaa: Res<Msaa>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<ImpostorPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<GpuMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    impostors: Query<(Entity, &ExtractedImpostors)>,
+    mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+    views: Query<(Entity, &ExtractedView)>,
+) {
+    let draw_impostors = transparent_3d_draw_functions.read().id::<DrawImpostors>();
+    let msaa_key = MeshPipelineKey::from_msaa_samples(msaa.samples());
+
+    for (view_entity, view) in &views {
+        let Some(transparent_phase) = transparent_render_phases.get_mut(&view_entity) else {
+            continue;
+        };
+        let view_key = msaa_key | MeshPipelineKey::from_hdr(view.hdr);
+        let rangefinder = view.rangefinder3d();
+        for (entity, extracted) in &impostors {
+            let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(entity) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+            let key = ImpostorPipelineKey {
+                mesh: view_key
+                    | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology()),
+                shape: extracted.shape,
+            };
+            let pipeline = match pipelines.specialize(
+                &pipeline_cache,
+                &impostor_pipeline,
+                key,
+                &mesh.layout,
+            ) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    eprintln!("Couldn't specialize impostor pipeline: {}", e);
+                    continue;
+                }
+            };
+            transparent_phase.add(Transparent3d {
+                entity,
+                pipeline,
+                draw_function: draw_impostors,
+                distance: rangefinder.distance_translation(&mesh_instance.translation),
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ImpostorPipeline {
+    shader: Handle<Shader>,
+    mesh_pipeline: MeshPipeline,
+}
+
+impl FromWorld for ImpostorPipeline {
+    fn from_world(world: &mut World) -> Self {
+        ImpostorPipeline {
+            shader: world.resource::<AssetServer>().load(SHADER_PATH),
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ImpostorPipelineKey {
+    mesh: MeshPipelineKey,
+    shape: ImpostorShape,
+}
+
+impl SpecializedMeshPipeline for ImpostorPipeline {
+    type Key = ImpostorPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key.mesh, layout)?;
+        let (vertex_entry, fragment_entry) = match key.shape {
+            ImpostorShape::Sphere => ("sphere_vertex", "sphere_fragment"),
+            ImpostorShape::Cylinder => ("cylinder_vertex", "cylinder_fragment"),
+        };
+        descriptor.vertex.shader = self.shader.clone();
+        descriptor.vertex.entry_point = vertex_entry.into();
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: (INSTANCE_FLOATS * std::mem::size_of::<f32>()) as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: (0..3)
+                .map(|i| VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: i * 16,
+                    shader_location: 3 + i as u32,
+                })
+                .collect(),
+        });
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: std::mem::size_of::<u32>() as u64,
+            step_mode: VertexStepMode::Vertex,
+            attributes: vec![VertexAttribute {
+                format: VertexFormat::Uint32,
+                offset: 0,
+                shader_location: 6,
+            }],
+        });
+        let fragment = descriptor.fragment.as_mut().unwrap();
+        fragment.shader = self.shader.clone();
+        fragment.entry_point = fragment_entry.into();
+        // the spheres and cylinders have their own shape, not the quad's
+        descriptor.primitive.cull_mode = None;
+        Ok(descriptor)
+    }
+}
+
+type DrawImpostors = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    DrawMeshInstanced,
+);
+
+struct DrawMeshInstanced;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
+    type Param = (
+        SRes<RenderAssets<GpuMesh>>,
+        SRes<RenderMeshInstances>,
+        SRes<ImpostorBuffers>,
+    );
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        _entity: Option<()>,
+        (meshes, render_mesh_instances, buffers): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(item.entity())
+        else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(instance_buffer) = buffers.into_inner().0.get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+        pass.set_vertex_buffer(2, instance_buffer.mesh_index.slice(..));
+        let instances = 0..instance_buffer.length as u32;
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, instances);
+            }
+            GpuBufferInfo::NonIndexed => {
+                pass.draw(0..gpu_mesh.vertex_count, instances);
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec3;
+
+    use super::{ray_cylinders_intersection, ray_spheres_intersection};
+
+    #[test]
+    fn test_finds_nearest_sphere_on_ray() {
+        let spheres = [
+            (Vec3::new(0.0, 0.0, -10.0), 1.0),
+            (Vec3::new(0.0, 0.0, -5.0), 1.0),
+            (Vec3::new(3.0, 0.0, -2.0), 1.0),
+            // behind the origin
+            (Vec3::new(0.0, 0.0, 5.0), 1.0),
+        ];
+        let hit = ray_spheres_intersection(Vec3::ZERO, Vec3::NEG_Z * 2.0, spheres.into_iter());
+        assert_eq!(hit, Some((1, 2.0)));
+
+        let miss = ray_spheres_intersection(Vec3::ZERO, Vec3::X + Vec3::Y, spheres.into_iter());
+        assert_eq!(miss, None);
+    }
+
+    #[test]
+    fn test_finds_nearest_cylinder_on_ray() {
+        let cylinders = [
+            // across the ray
+            (Vec3::new(-1.0, 0.0, -10.0), Vec3::new(1.0, 0.0, -10.0), 0.5),
+            (Vec3::new(-1.0, 0.0, -5.0), Vec3::new(1.0, 0.0, -5.0), 0.5),
+            // beside the ray
+            (Vec3::new(2.0, -1.0, -2.0), Vec3::new(2.0, 1.0, -2.0), 0.5),
+        ];
+        let hit = ray_cylinders_intersection(Vec3::ZERO, Vec3::NEG_Z * 2.0, cylinders.into_iter());
+        assert_eq!(hit, Some((1, 2.25)));
+
+        // almost along the axis, the cap is hit
+        let along = [(Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, -6.0), 0.5)];
+        let direction = Vec3::new(0.05, 0.0, -1.0);
+        let hit = ray_cylinders_intersection(Vec3::ZERO, direction, along.into_iter());
+        assert_eq!(hit, Some((0, 3.0)));
+
+        // past the end of the cylinders
+        let miss = ray_cylinders_intersection(
+            Vec3::new(1.2, 0.0, 0.0),
+            Vec3::NEG_Z,
+            cylinders.into_iter(),
+        );
+        assert_eq!(miss, None);
+    }
+}
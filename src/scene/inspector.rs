@@ -0,0 +1,182 @@
+use bevy::{asset::AssetId, math::Vec3, prelude::Resource};
+
+use super::selection::AtomRef;
+use crate::{
+    bond_perception::{hybridization, Hybridization},
+    element::Element,
+    molecule::{Atom, BondType, Molecule},
+};
+
+/// a bond of a shown molecule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BondRef {
+    pub mol: AssetId<Molecule>,
+    /// index in `Molecule::bonds`
+    pub index: usize,
+}
+
+/// what the inspector shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inspected {
+    Atom(AtomRef),
+    Bond(BondRef),
+}
+
+/// the atom or bond under the cursor, shown in the inspector instead of the selected atom
+#[derive(Resource, Debug, Default)]
+pub struct Hovered(pub Option<Inspected>);
+
+impl Hovered {
+    /// clears it if it's still the one the cursor left
+    pub fn leave(&mut self, inspected: Inspected) {
+        if self.0 == Some(inspected) {
+            self.0 = None;
+        }
+    }
+}
+
+/// all the fields of the atom, its hybridization and bonded neighbors with bond orders and distances
+pub fn atom_record(mol: &Molecule, index: usize, position: impl Fn(usize) -> Vec3) -> String {
+    let atom = &mol.atoms[index];
+    let pos = position(index);
+    let mut record = format!(
+        "{}\nelement: {}\ntype: {}\npos: {:.2}, {:.2}, {:.2}",
+        atom_name(atom),
+        atom.element.symbol(),
+        atom.type_,
+        pos.x,
+        pos.y,
+        pos.z
+    );
+    if let Some(residue) = mol.residue(atom) {
+        record += &format!("\nresidue: {} {}", residue.name, residue.id);
+    }
+    if let Some(chain) = mol.chain(atom) {
+        record += &format!("\nchain: {}", chain.name);
+    }
+    if let Some(charge) = atom.partial_charge {
+        record += &format!("\ncharge: {:.3}", charge);
+    }
+    if let Some(charge) = atom.formal_charge.filter(|c| *c != 0) {
+        record += &format!("\nformal charge: {:+}", charge);
+    }
+    if let Some(b_factor) = atom.b_factor {
+        record += &format!("\nb-factor: {:.2}", b_factor);
+    }
+    if let Some(isotope) = atom.isotope {
+        record += &format!("\nisotope: {}", isotope);
+    }
+    if let Some(alt_loc) = atom.alt_loc {
+        record += &format!("\nalt loc: {}", alt_loc);
+    }
+
+    // bonded atoms with the index of the bond
+    let neighbors: Vec<(usize, usize)> = mol
+        .bonds
+        .iter()
+        .enumerate()
+        .filter_map(|(bond_index, bond)| {
+            if bond.atom1 == index {
+                Some((bond.atom2, bond_index))
+            } else if bond.atom2 == index {
+                Some((bond.atom1, bond_index))
+            } else {
+                None
+            }
+        })
+        .collect();
+    // the geometry of the molecule's atoms, as used for the bond orders
+    if atom.element != Element::H && !neighbors.is_empty() {
+        let hybridization = hybridization(&mol.atoms, atom, &neighbors, &mol.bonds);
+        record += &format!("\nhybridization: {}", hybridization_text(hybridization));
+    }
+    if !neighbors.is_empty() {
+        record += "\nneighbors:";
+    }
+    for (neighbor, bond) in neighbors {
+        record += &format!(
+            "\n  {} {} {:.2} Å",
+            atom_name(&mol.atoms[neighbor]),
+            bond_order_text(mol.bonds[bond].type_),
+            pos.distance(position(neighbor))
+        );
+    }
+    record
+}
+
+/// the atoms, order and length (Å) of the bond
+pub fn bond_record(mol: &Molecule, index: usize, length: f32) -> String {
+    let bond = &mol.bonds[index];
+    format!(
+        "bond {} - {}\norder: {}\nlength: {:.2} Å",
+        atom_name(&mol.atoms[bond.atom1]),
+        atom_name(&mol.atoms[bond.atom2]),
+        bond_order_text(bond.type_),
+        length
+    )
+}
+
+/// name and id in the source file
+fn atom_name(atom: &Atom) -> String {
+    format!("{} ({})", atom.name, atom.id)
+}
+
+fn bond_order_text(type_: BondType) -> &'static str {
+    match type_ {
+        BondType::Single => "single",
+        BondType::Double => "double",
+        BondType::Triple => "triple",
+        BondType::Aromatic => "aromatic",
+        BondType::Amide => "amide",
+        BondType::Dummy => "dummy",
+        BondType::Unknown => "unknown",
+        BondType::NotConnected => "not connected",
+    }
+}
+
+fn hybridization_text(hybridization: Hybridization) -> &'static str {
+    match hybridization {
+        Hybridization::Sp => "sp",
+        Hybridization::Sp2 => "sp2",
+        Hybridization::Sp3 => "sp3",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec3;
+
+    use super::atom_record;
+    use crate::{
+        element::Element,
+        molecule::{Atom, Bond, BondType, Molecule},
+    };
+
+    #[test]
+    fn test_atom_record_lists_neighbors() {
+        let mut mol = Molecule::default();
+        let atoms = [
+            ("C1", Element::C, Vec3::ZERO),
+            ("O1", Element::O, Vec3::new(1.2, 0.0, 0.0)),
+            // trigonal, as the carbon of a carbonyl
+            ("H1", Element::H, Vec3::new(-0.5, 0.866, 0.0)),
+        ];
+        for (i, (name, element, position)) in atoms.into_iter().enumerate() {
+            let atom = Atom::new(i as i32 + 1, name.to_string(), element, position);
+            mol.add_atom(atom, Some((1, "LIG")), Some("A"));
+        }
+        for (atom2, type_) in [(1, BondType::Double), (2, BondType::Single)] {
+            mol.bonds.push(Bond {
+                atom1: 0,
+                atom2,
+                type_,
+            });
+        }
+
+        let record = atom_record(&mol, 0, |atom| mol.atoms[atom].position);
+        assert!(record.starts_with("C1 (1)\nelement: C"));
+        assert!(record.contains("\nresidue: LIG 1\nchain: A"));
+        assert!(record.contains("\nhybridization: sp2"));
+        assert!(record.ends_with("\nneighbors:\n  O1 (2) double 1.20 Å\n  H1 (3) single 1.00 Å"));
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+
+/// levels of detail of the atom and bond meshes, 0 is the most detailed
+pub const LOD_LEVELS: usize = 3;
+/// minimum radius on screen (pixels) of each level of detail but the last
+const MIN_PIXEL_RADIUS: [f32; LOD_LEVELS - 1] = [16.0, 5.0];
+
+/// the meshes with levels of detail, see `PreloadedAssets::lod_mesh`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LodMesh {
+    Atom,
+    BondCylinder,
+    BondCapsule,
+    /// lines of double / triple / aromatic bonds, dashes
+    SmallBondCylinder,
+    SmallBondCapsule,
+}
+
+/// an atom or bond whose mesh is switched by its size on screen, see `update_level_of_detail`
+#[derive(Component, Debug)]
+pub struct Lod {
+    pub mesh: LodMesh,
+    /// radius of the mesh before scaling, the size on screen is calculated with it
+    pub radius: f32,
+    /// the most detailed level used, larger for large molecules
+    pub min_level: usize,
+    pub level: usize,
+}
+
+impl Lod {
+    pub fn new(mesh: LodMesh, radius: f32, min_level: usize) -> Self {
+        Self {
+            mesh,
+            radius,
+            min_level,
+            level: min_level,
+        }
+    }
+}
+
+/// level of detail for a mesh with the radius on screen (pixels)
+pub fn lod_level(pixel_radius: f32, min_level: usize) -> usize {
+    let level = MIN_PIXEL_RADIUS
+        .iter()
+        .position(|min| pixel_radius >= *min)
+        .unwrap_or(LOD_LEVELS - 1);
+    level.max(min_level).min(LOD_LEVELS - 1)
+}
+
+/// radius on screen (pixels) of a sphere, with the camera's `clip_from_world` and viewport height
+pub fn pixel_radius(
+    clip_from_world: &Mat4,
+    viewport_height: f32,
+    center: Vec3,
+    radius: f32,
+) -> f32 {
+    // w is the depth in perspective and 1 in orthographic projections
+    let w = (*clip_from_world * center.extend(1.0)).w;
+    if w <= 0.0 {
+        // behind the camera
+        return 0.0;
+    }
+    // the vertical scale of the projection
+    let scale = clip_from_world.row(1).truncate().length();
+    radius * scale / w * viewport_height / 2.0
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::{Mat4, Vec3};
+
+    use super::{lod_level, pixel_radius};
+
+    #[test]
+    fn test_chooses_level_by_size_on_screen() {
+        assert_eq!(lod_level(40.0, 0), 0);
+        assert_eq!(lod_level(10.0, 0), 1);
+        assert_eq!(lod_level(1.0, 0), 2);
+        // large molecules don't use the most detailed level
+        assert_eq!(lod_level(40.0, 1), 1);
+
+        let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0);
+        let near = pixel_radius(&projection, 600.0, Vec3::new(0.0, 0.0, -5.0), 1.0);
+        let far = pixel_radius(&projection, 600.0, Vec3::new(0.0, 0.0, -50.0), 1.0);
+        assert!((near - 60.0).abs() < 1e-3);
+        assert!((far - 6.0).abs() < 1e-3);
+        assert_eq!(
+            pixel_radius(&projection, 600.0, Vec3::new(0.0, 0.0, 5.0), 1.0),
+            0.0
+        );
+    }
+}
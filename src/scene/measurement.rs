@@ -0,0 +1,180 @@
+use bevy::{math::Vec3, prelude::Resource};
+
+use super::selection::AtomRef;
+
+/// what clicking atoms does, measuring instead of selecting them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MeasureMode {
+    #[default]
+    Off,
+    Distance,
+    Angle,
+    Dihedral,
+}
+
+impl MeasureMode {
+    /// the number of atoms picked for a measurement
+    pub fn atom_count(&self) -> usize {
+        match self {
+            MeasureMode::Off => 0,
+            MeasureMode::Distance => 2,
+            MeasureMode::Angle => 3,
+            MeasureMode::Dihedral => 4,
+        }
+    }
+}
+
+/// a distance, angle or dihedral between atoms, shown until deleted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    /// to identify it in the panel, stays the same when others are deleted
+    pub id: usize,
+    /// in the order they were picked, 2 to 4
+    pub atoms: Vec<AtomRef>,
+    /// angstrom or degrees, none while an atom isn't shown
+    pub value: Option<f32>,
+}
+
+impl Measurement {
+    pub fn mode(&self) -> MeasureMode {
+        match self.atoms.len() {
+            2 => MeasureMode::Distance,
+            3 => MeasureMode::Angle,
+            _ => MeasureMode::Dihedral,
+        }
+    }
+
+    pub fn value_text(&self) -> String {
+        match (self.value, self.mode()) {
+            (None, _) => "hidden".to_string(),
+            (Some(value), MeasureMode::Distance) => format!("{:.2} Å", value),
+            (Some(value), _) => format!("{:.1}°", value),
+        }
+    }
+
+    /// short text for the panel and the label in the scene
+    pub fn text(&self) -> String {
+        format!("#{} {}", self.id + 1, self.value_text())
+    }
+}
+
+/// the measurements and the atoms picked for the next one (`mode`)
+#[derive(Resource, Debug, Default)]
+pub struct Measurements {
+    pub mode: MeasureMode,
+    /// in the order they were picked
+    pub picked: Vec<AtomRef>,
+    pub list: Vec<Measurement>,
+    next_id: usize,
+}
+
+impl Measurements {
+    /// the picked atoms are dropped when the mode changes
+    pub fn set_mode(&mut self, mode: MeasureMode) {
+        self.mode = mode;
+        self.picked.clear();
+    }
+
+    /// adds a measurement when enough atoms are picked. picking the last atom again unpicks it
+    pub fn pick(&mut self, atom: AtomRef) {
+        if self.picked.last() == Some(&atom) {
+            self.picked.pop();
+            return;
+        }
+        if self.mode == MeasureMode::Off || self.picked.contains(&atom) {
+            return;
+        }
+        self.picked.push(atom);
+        if self.picked.len() == self.mode.atom_count() {
+            self.list.push(Measurement {
+                id: self.next_id,
+                atoms: std::mem::take(&mut self.picked),
+                value: None,
+            });
+            self.next_id += 1;
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Measurement> {
+        self.list.iter().find(|m| m.id == id)
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        self.list.retain(|m| m.id != id);
+    }
+}
+
+/// distance in angstrom, angle and dihedral in degrees, for the positions of the measured atoms
+pub fn measure(positions: &[Vec3]) -> Option<f32> {
+    match *positions {
+        [a, b] => Some(a.distance(b)),
+        [a, b, c] => Some(angle(a, b, c)),
+        [a, b, c, d] => Some(dihedral(a, b, c, d)),
+        _ => None,
+    }
+}
+
+/// angle at b, in degrees
+pub fn angle(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    (a - b).angle_between(c - b).to_degrees()
+}
+
+/// torsion around the b-c bond in degrees (-180 to 180), positive if a turns clockwise to d looking from b to c
+pub fn dihedral(a: Vec3, b: Vec3, c: Vec3, d: Vec3) -> f32 {
+    let (b1, b2, b3) = (b - a, c - b, d - c);
+    let n2 = b2.cross(b3);
+    let y = b2.length() * b1.dot(n2);
+    let x = b1.cross(b2).dot(n2);
+    y.atan2(x).to_degrees()
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{asset::AssetId, math::Vec3};
+
+    use super::{measure, MeasureMode, Measurements};
+    use crate::scene::selection::AtomRef;
+
+    #[test]
+    fn test_measures_distance_angle_and_dihedral() {
+        let a = Vec3::X;
+        let b = Vec3::ZERO;
+        let c = Vec3::Z;
+        let d = Vec3::new(0.0, 1.0, 1.0);
+        let close = |value: Option<f32>, expected: f32| (value.unwrap() - expected).abs() < 1e-4;
+
+        assert!(close(measure(&[a, c]), 2f32.sqrt()));
+        assert!(close(measure(&[a, b, c]), 90.0));
+        assert!(close(measure(&[a, b, c, d]), 90.0));
+        assert!(close(measure(&[a, b, c, d - 2.0 * Vec3::Y]), -90.0));
+        assert!(close(measure(&[a, b, c, a + c]), 0.0));
+        assert_eq!(measure(&[a]), None);
+    }
+
+    #[test]
+    fn test_adds_measurement_when_enough_atoms_are_picked() {
+        let atom = |index: usize| AtomRef {
+            mol: AssetId::default(),
+            index,
+        };
+        let mut measurements = Measurements::default();
+        measurements.pick(atom(0));
+        assert!(measurements.picked.is_empty());
+
+        measurements.set_mode(MeasureMode::Angle);
+        measurements.pick(atom(0));
+        measurements.pick(atom(1));
+        // other picked atoms are ignored, picking the last atom again unpicks it
+        measurements.pick(atom(0));
+        measurements.pick(atom(1));
+        assert_eq!(measurements.picked, vec![atom(0)]);
+        measurements.pick(atom(2));
+        measurements.pick(atom(3));
+        assert!(measurements.picked.is_empty());
+        assert_eq!(measurements.list[0].atoms, vec![atom(0), atom(2), atom(3)]);
+        assert_eq!(measurements.list[0].mode(), MeasureMode::Angle);
+
+        measurements.remove(0);
+        assert!(measurements.list.is_empty());
+    }
+}
@@ -1,46 +1,107 @@
+pub mod atom_query;
+mod cartoon;
 mod comp;
 pub mod component;
 pub mod event;
 mod helper;
+mod impostor;
+pub mod inspector;
+mod lod;
+pub mod measurement;
 pub mod resource;
+pub mod selection;
+mod surface;
 mod system;
 
 use self::{
-    resource::{MolRender, MolScene, MolSceneContent, MolStyle},
+    resource::{
+        AtomOverrides, CartoonColor, LodStyle, MolRender, MolScene, MolSceneContent, MolStyle,
+        Playback, SurfaceStyle, TrajectoryStream,
+    },
     system::{
-        check_file_loaded, handle_added_bounding_box, handle_update_scene_event, setup_molecule,
-        trigger_init_scene_event,
+        check_file_loaded, handle_added_bounding_box, handle_update_scene_event,
+        handle_update_style_event, setup_molecule, trigger_init_scene_event,
     },
 };
 use bevy::app::{App, PostStartup, Startup, Update};
-use bevy_mod_picking::DefaultPickingPlugins;
-use event::UpdateSceneEvent;
-use resource::PreloadedAssets;
-use system::{preload_item_assets, update_bond_length};
+use bevy::prelude::{IntoSystemConfigs, PluginGroup};
+use bevy_mod_picking::{selection::SelectionPlugin, DefaultPickingPlugins};
+use event::{SelectionChangedEvent, UpdateSceneEvent, UpdateStyleEvent};
+use impostor::ImpostorPlugin;
+use inspector::Hovered;
+use measurement::Measurements;
+use resource::{MolEntities, PreloadedAssets};
+use selection::Selection;
+use system::{
+    advance_playback, apply_playback_frame, draw_measurements, drop_hidden_measurements,
+    drop_hidden_selection, finish_surfaces, handle_selection_input, pick_impostors,
+    preload_item_assets, stream_trajectory_frames, update_bond_length, update_frame_count,
+    update_level_of_detail, update_perceived_bonds, update_selection_highlight,
+    update_surface_colors, update_surface_opacity,
+};
 
 #[allow(dead_code)]
 pub fn add_mol_scene(app: &mut App) {
-    app.add_plugins(DefaultPickingPlugins)
+    // the selection is kept in `Selection`, the picking selection only tints the selected entities
+    let picking = DefaultPickingPlugins.build().disable::<SelectionPlugin>();
+    app.add_plugins((picking, ImpostorPlugin))
         .insert_resource(MolScene {
             content: MolSceneContent::Empty,
+            trajectory: None,
+            open_error: None,
+            force_bond_perception: false,
             style: MolStyle {
                 atom_scale_ball_stick: 0.3,
                 atom_scale_ball: 1.8,
             },
             render: MolRender::BallStick,
+            cartoon_color: CartoonColor::Chain,
+            surface: SurfaceStyle::default(),
+            impostors: false,
+            lod: LodStyle::default(),
+            overrides: AtomOverrides::default(),
         })
         .insert_resource(PreloadedAssets::default())
+        .insert_resource(MolEntities::default())
+        .insert_resource(Selection::default())
+        .insert_resource(Measurements::default())
+        .insert_resource(Hovered::default())
+        .insert_resource(Playback::default())
+        .insert_resource(TrajectoryStream::default())
         .add_event::<UpdateSceneEvent>()
+        .add_event::<UpdateStyleEvent>()
+        .add_event::<SelectionChangedEvent>()
         .add_systems(Startup, preload_item_assets)
         .add_systems(Startup, setup_molecule)
         .add_systems(PostStartup, (trigger_init_scene_event,)) // TODO maybe it works in startup? test
         .add_systems(
             Update,
             (
-                handle_update_scene_event,
-                check_file_loaded,
+                (
+                    update_perceived_bonds,
+                    handle_update_style_event,
+                    handle_update_scene_event,
+                    check_file_loaded,
+                    drop_hidden_selection,
+                    drop_hidden_measurements,
+                    handle_selection_input,
+                    update_selection_highlight,
+                    draw_measurements,
+                )
+                    .chain(),
                 handle_added_bounding_box,
-                update_bond_length,
+                update_surface_opacity,
+                (finish_surfaces, update_surface_colors).chain(),
+                pick_impostors,
+                update_level_of_detail,
+                (
+                    update_frame_count,
+                    advance_playback,
+                    stream_trajectory_frames,
+                    apply_playback_frame,
+                    update_bond_length,
+                )
+                    .chain(),
             ),
         );
 }
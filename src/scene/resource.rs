@@ -1,33 +1,248 @@
-use crate::mol2_asset_plugin::Mol2Molecule;
+use super::{
+    lod::{LodMesh, LOD_LEVELS},
+    selection::AtomRef,
+    system::atom_material,
+};
+use crate::{
+    element::Element,
+    mol_collection::MolCollection,
+    molecule::Molecule,
+    surface::SurfaceKind,
+    trajectory_asset_plugin::{Trajectory, TrajectoryFrame},
+};
 use bevy::{
-    asset::Handle,
+    asset::{AssetId, Assets, Handle, UntypedAssetId},
+    color::Srgba,
     pbr::StandardMaterial,
-    prelude::{Mesh, Resource},
+    prelude::{Entity, Mesh, Resource},
+    tasks::Task,
+    utils::{HashMap, HashSet},
 };
 
 #[derive(Debug, Resource)]
 pub struct MolScene {
     pub content: MolSceneContent,
+    /// binary trajectory (dcd, xtc) played over the shown molecule, which is its topology
+    pub trajectory: Option<Handle<Trajectory>>,
+    /// why the last file opened by the user couldn't be loaded, for errors before its loader runs (e.g. unsupported extension)
+    pub open_error: Option<String>,
+    /// perceive the bonds from the distances also for molecules with bonds (always done for molecules without)
+    pub force_bond_perception: bool,
     pub style: MolStyle,
     pub render: MolRender,
+    /// coloring of the cartoon (`MolRender::Cartoon`)
+    pub cartoon_color: CartoonColor,
+    /// `MolRender::Surface`
+    pub surface: SurfaceStyle,
+    /// draw the atoms and bonds as impostors (always done for large molecules)
+    pub impostors: bool,
+    pub lod: LodStyle,
+    pub overrides: AtomOverrides,
 }
 
 #[derive(Debug)]
 pub enum MolSceneContent {
     Empty,
-    Mol2 {
-        handle: Handle<Mol2Molecule>,
+    Molecule {
+        handle: Handle<Molecule>,
+        waiting_for_async_handle: bool,
+    },
+    /// a file with multiple molecules, shows the selected one or all of them overlaid
+    Collection {
+        handle: Handle<MolCollection>,
+        selected: usize,
+        overlay: bool,
         waiting_for_async_handle: bool,
     },
 }
 
+impl MolSceneContent {
+    /// the molecules to be shown (empty if not loaded yet)
+    pub fn molecules<'a>(
+        &self,
+        mols: &'a Assets<Molecule>,
+        collections: &Assets<MolCollection>,
+    ) -> Vec<&'a Molecule> {
+        self.molecule_ids(collections)
+            .into_iter()
+            .filter_map(|id| mols.get(id))
+            .collect()
+    }
+
+    /// ids of the molecules to be shown (empty if the collection isn't loaded yet)
+    pub fn molecule_ids(&self, collections: &Assets<MolCollection>) -> Vec<AssetId<Molecule>> {
+        match self {
+            MolSceneContent::Empty => vec![],
+            MolSceneContent::Molecule { handle, .. } => vec![handle.id()],
+            MolSceneContent::Collection {
+                handle,
+                selected,
+                overlay,
+                ..
+            } => collections
+                .get(handle)
+                .map(|c| {
+                    let handles = if *overlay {
+                        &c.molecules[..]
+                    } else {
+                        c.molecules
+                            .get(*selected..*selected + 1)
+                            .unwrap_or_default()
+                    };
+                    handles.iter().map(|h| h.id()).collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// the selected molecule, if loaded
+    pub fn molecule<'a>(
+        &self,
+        mols: &'a Assets<Molecule>,
+        collections: &Assets<MolCollection>,
+    ) -> Option<&'a Molecule> {
+        match self {
+            MolSceneContent::Empty => None,
+            MolSceneContent::Molecule { handle, .. } => mols.get(handle),
+            MolSceneContent::Collection {
+                handle, selected, ..
+            } => collections
+                .get(handle)
+                .and_then(|c| c.molecules.get(*selected))
+                .and_then(|h| mols.get(h)),
+        }
+    }
+
+    /// number of frames of the shown trajectory, 1 for a single structure (or overlaid molecules)
+    pub fn frame_count(
+        &self,
+        mols: &Assets<Molecule>,
+        collections: &Assets<MolCollection>,
+    ) -> usize {
+        match self.molecules(mols, collections)[..] {
+            [mol] => mol.frame_count(),
+            _ => 1,
+        }
+    }
+
+    /// id of the loaded asset (molecule or collection)
+    pub fn asset_id(&self) -> Option<UntypedAssetId> {
+        match self {
+            MolSceneContent::Empty => None,
+            MolSceneContent::Molecule { handle, .. } => Some(handle.id().untyped()),
+            MolSceneContent::Collection { handle, .. } => Some(handle.id().untyped()),
+        }
+    }
+
+    pub fn waiting_for_async_handle(&self) -> bool {
+        match self {
+            MolSceneContent::Empty => false,
+            MolSceneContent::Molecule {
+                waiting_for_async_handle,
+                ..
+            }
+            | MolSceneContent::Collection {
+                waiting_for_async_handle,
+                ..
+            } => *waiting_for_async_handle,
+        }
+    }
+
+    pub fn set_waiting_for_async_handle(&mut self, waiting: bool) {
+        match self {
+            MolSceneContent::Empty => {}
+            MolSceneContent::Molecule {
+                waiting_for_async_handle,
+                ..
+            }
+            | MolSceneContent::Collection {
+                waiting_for_async_handle,
+                ..
+            } => *waiting_for_async_handle = waiting,
+        }
+    }
+}
+
+/// trajectory playback state, for molecules with frames
+#[derive(Resource, Debug)]
+pub struct Playback {
+    /// index of the shown frame
+    pub frame: usize,
+    /// frames of the shown trajectory (molecule frames or attached trajectory), 1 for a single structure
+    pub frame_count: usize,
+    pub playing: bool,
+    pub fps: u32,
+    /// start again after the last frame
+    pub looping: bool,
+    /// time since the last frame change, in seconds
+    pub elapsed: f32,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            frame: 0,
+            frame_count: 1,
+            playing: false,
+            fps: 10,
+            looping: true,
+            elapsed: 0.0,
+        }
+    }
+}
+
+/// frames of the attached trajectory are read from the file on demand, one at a time
+#[derive(Resource, Default)]
+pub struct TrajectoryStream {
+    /// the trajectory the frames belong to
+    pub trajectory: Option<AssetId<Trajectory>>,
+    /// index of the last frame requested from the file
+    pub requested: Option<usize>,
+    /// the last read frame
+    pub frame: Option<TrajectoryFrame>,
+    /// the frame being read
+    pub task: Option<Task<anyhow::Result<TrajectoryFrame>>>,
+}
+
 #[derive(Resource, Debug)]
 pub struct MolStyle {
     pub atom_scale_ball_stick: f32,
     pub atom_scale_ball: f32,
 }
 
-#[derive(Resource, PartialEq, Eq, Debug)]
+/// atoms hidden or colored by the user (see `Selection`), on top of the style
+#[derive(Debug, Clone, Default)]
+pub struct AtomOverrides {
+    /// not drawn, nor their bonds
+    pub hidden: HashSet<AtomRef>,
+    pub colors: HashMap<AtomRef, Srgba>,
+}
+
+impl AtomOverrides {
+    pub fn color(&self, mol: AssetId<Molecule>, index: usize) -> Option<Srgba> {
+        self.colors.get(&AtomRef { mol, index }).copied()
+    }
+}
+
+/// levels of detail of the atom and bond meshes
+#[derive(Debug, Clone)]
+pub struct LodStyle {
+    /// choose the meshes by their size on screen, otherwise the most detailed ones are used
+    pub enabled: bool,
+    /// molecules with more atoms don't use the most detailed meshes
+    pub coarse_atom_count: usize,
+}
+
+impl Default for LodStyle {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            coarse_atom_count: 5_000,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum MolRender {
     BallStick,
     #[allow(unused)]
@@ -35,22 +250,112 @@ pub enum MolRender {
     #[allow(unused)]
     // just a quick experiment - larger sphere scale
     Ball,
+    /// ribbons for the protein backbone, other atoms (e.g. ligands) as ball and stick
+    Cartoon,
+    /// molecular surface over ball and stick
+    Surface,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartoonColor {
+    Chain,
+    SecondaryStructure,
+}
+
+#[derive(Debug, Clone)]
+pub struct SurfaceStyle {
+    pub kind: SurfaceKind,
+    /// angstrom, for the solvent accessible / excluded surfaces
+    pub probe_radius: f32,
+    /// spacing of the grid the surface is computed with, in angstrom (smaller is finer)
+    pub resolution: f32,
+    /// 0 (transparent) to 1
+    pub opacity: f32,
+    pub color: SurfaceColor,
+}
+
+impl Default for SurfaceStyle {
+    fn default() -> Self {
+        Self {
+            kind: SurfaceKind::SolventExcluded,
+            probe_radius: 1.4,
+            resolution: 0.5,
+            opacity: 0.7,
+            color: SurfaceColor::Element,
+        }
+    }
+}
+
+/// the surface is colored with the nearest atom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceColor {
+    Element,
+    /// partial (or formal) charge, red negative and blue positive
+    Charge,
+    /// hydropathy of the residue, blue hydrophilic and orange hydrophobic
+    Hydrophobicity,
+}
+
+/// the atom and bond entities of the shown molecules, to update them when the style changes instead of rebuilding the scene
+#[derive(Resource, Debug, Default)]
+pub struct MolEntities {
+    /// the style the entities were drawn with, none if nothing is drawn
+    pub render: Option<MolRender>,
+    /// whether a molecule is drawn with impostors, which are drawn again
+    pub impostors: bool,
+    pub mols: HashMap<AssetId<Molecule>, MolEntityMap>,
+}
+
+#[derive(Debug, Default)]
+pub struct MolEntityMap {
+    /// by index in `Molecule::atoms`
+    pub atoms: HashMap<usize, Entity>,
+    /// by index in `Molecule::bonds`, an entity per line of the bond
+    pub bonds: HashMap<usize, Vec<Entity>>,
 }
 
 #[derive(Resource, Debug, Default)]
 pub struct PreloadedAssets {
-    pub h_mat: Handle<StandardMaterial>,
-    pub c_mat: Handle<StandardMaterial>,
-    pub o_mat: Handle<StandardMaterial>,
-    pub n_mat: Handle<StandardMaterial>,
-    pub f_mat: Handle<StandardMaterial>,
-    pub p_mat: Handle<StandardMaterial>,
-    pub s_mat: Handle<StandardMaterial>,
-    pub ca_mat: Handle<StandardMaterial>,
-    pub atom_mesh: Handle<Mesh>,
+    /// per element, created on first use
+    pub atom_mats: HashMap<Element, Handle<StandardMaterial>>,
+    /// the meshes are per level of detail, see `lod_mesh`
+    pub atom_meshes: [Handle<Mesh>; LOD_LEVELS],
     pub bond_mat: Handle<StandardMaterial>,
-    pub bond_cyl_mesh: Handle<Mesh>,
-    pub bond_caps_mesh: Handle<Mesh>,
-    pub bond_small_cyl_mesh: Handle<Mesh>,
-    pub bond_small_caps_mesh: Handle<Mesh>,
+    pub amide_bond_mat: Handle<StandardMaterial>,
+    pub bond_cyl_meshes: [Handle<Mesh>; LOD_LEVELS],
+    pub bond_caps_meshes: [Handle<Mesh>; LOD_LEVELS],
+    pub bond_small_cyl_meshes: [Handle<Mesh>; LOD_LEVELS],
+    pub bond_small_caps_meshes: [Handle<Mesh>; LOD_LEVELS],
+    /// white, the cartoon colors are in the vertices
+    pub cartoon_mat: Handle<StandardMaterial>,
+    /// white, with the opacity of the surface style
+    pub surface_mat: Handle<StandardMaterial>,
+    /// the quad the impostors are drawn on
+    pub impostor_quad: Handle<Mesh>,
+}
+
+impl PreloadedAssets {
+    /// material for element, created if it doesn't exist yet
+    pub fn atom_material(
+        &mut self,
+        materials: &mut Assets<StandardMaterial>,
+        element: Element,
+    ) -> Handle<StandardMaterial> {
+        self.atom_mats
+            .entry(element)
+            .or_insert_with(|| atom_material(materials, element))
+            .clone()
+    }
+
+    /// the mesh with the level of detail (0 is the most detailed)
+    pub fn lod_mesh(&self, mesh: LodMesh, level: usize) -> Handle<Mesh> {
+        let meshes = match mesh {
+            LodMesh::Atom => &self.atom_meshes,
+            LodMesh::BondCylinder => &self.bond_cyl_meshes,
+            LodMesh::BondCapsule => &self.bond_caps_meshes,
+            LodMesh::SmallBondCylinder => &self.bond_small_cyl_meshes,
+            LodMesh::SmallBondCapsule => &self.bond_small_caps_meshes,
+        };
+        meshes[level.min(LOD_LEVELS - 1)].clone()
+    }
 }
@@ -0,0 +1,255 @@
+use bevy::{
+    asset::AssetId,
+    math::Vec2,
+    prelude::Resource,
+    utils::{HashMap, HashSet},
+};
+use std::hash::Hash;
+
+use crate::molecule::{Atom, Bond, Molecule};
+
+/// mouse movement (pixels) up to which a press and release is a click, not a drag
+pub const CLICK_MAX_DISTANCE: f32 = 4.0;
+
+/// an atom of a shown molecule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AtomRef {
+    pub mol: AssetId<Molecule>,
+    /// index in `Molecule::atoms`
+    pub index: usize,
+}
+
+/// the selected atoms, a `SelectionChangedEvent` is sent when they change.
+/// bonds are selected when both of their atoms are
+#[derive(Resource, Debug, Default)]
+pub struct Selection {
+    pub atoms: HashSet<AtomRef>,
+    /// what clicking or dragging over atoms selects
+    pub by: SelectBy,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SelectBy {
+    #[default]
+    Atom,
+    Residue,
+    Chain,
+    Element,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectMode {
+    /// the atoms become the selection
+    Replace,
+    Add,
+    /// the atoms are removed if all of them are selected, otherwise added
+    Toggle,
+}
+
+impl Selection {
+    pub fn contains(&self, atom: &AtomRef) -> bool {
+        self.atoms.contains(atom)
+    }
+
+    pub fn contains_bond(&self, mol: AssetId<Molecule>, bond: &Bond) -> bool {
+        [bond.atom1, bond.atom2]
+            .iter()
+            .all(|index| self.contains(&AtomRef { mol, index: *index }))
+    }
+
+    /// returns whether the selection changed
+    pub fn apply(&mut self, atoms: Vec<AtomRef>, mode: SelectMode) -> bool {
+        match mode {
+            SelectMode::Replace => {
+                let atoms: HashSet<AtomRef> = atoms.into_iter().collect();
+                if atoms == self.atoms {
+                    return false;
+                }
+                self.atoms = atoms;
+                true
+            }
+            SelectMode::Add => {
+                let len = self.atoms.len();
+                self.atoms.extend(atoms);
+                self.atoms.len() != len
+            }
+            SelectMode::Toggle => {
+                if atoms.is_empty() {
+                    return false;
+                }
+                if atoms.iter().all(|atom| self.contains(atom)) {
+                    for atom in &atoms {
+                        self.atoms.remove(atom);
+                    }
+                } else {
+                    self.atoms.extend(atoms);
+                }
+                true
+            }
+        }
+    }
+}
+
+/// the picked atoms expanded to their residues, chains or elements.
+/// atoms without a residue (or chain) are only selected themselves
+pub fn expand_selection(mol: &Molecule, atoms: &[usize], by: SelectBy) -> Vec<usize> {
+    match by {
+        SelectBy::Atom => atoms.to_vec(),
+        SelectBy::Residue => atoms_with_same(mol, atoms, |atom| atom.residue),
+        SelectBy::Chain => atoms_with_same(mol, atoms, |atom| {
+            mol.residue(atom).and_then(|residue| residue.chain)
+        }),
+        SelectBy::Element => atoms_with_same(mol, atoms, |atom| Some(atom.element)),
+    }
+}
+
+/// the atoms and all the atoms with the same key as one of them
+fn atoms_with_same<K: Eq + Hash>(
+    mol: &Molecule,
+    atoms: &[usize],
+    key: impl Fn(&Atom) -> Option<K>,
+) -> Vec<usize> {
+    let picked: HashSet<usize> = atoms.iter().copied().collect();
+    let keys: HashSet<K> = atoms.iter().filter_map(|a| key(&mol.atoms[*a])).collect();
+    (0..mol.atoms.len())
+        .filter(|a| picked.contains(a) || key(&mol.atoms[*a]).is_some_and(|k| keys.contains(&k)))
+        .collect()
+}
+
+/// groups the atoms by molecule, for `expand_selection`
+pub fn atoms_by_mol(atoms: &[AtomRef]) -> HashMap<AssetId<Molecule>, Vec<usize>> {
+    let mut by_mol: HashMap<AssetId<Molecule>, Vec<usize>> = HashMap::new();
+    for atom in atoms {
+        by_mol.entry(atom.mol).or_default().push(atom.index);
+    }
+    by_mol
+}
+
+/// a box or lasso being dragged over the viewport, in pixels
+#[derive(Debug)]
+pub struct SelectionDrag {
+    pub lasso: bool,
+    /// the cursor positions, from where the drag started
+    pub points: Vec<Vec2>,
+}
+
+impl SelectionDrag {
+    /// the outline of the box or lasso
+    pub fn polygon(&self) -> Vec<Vec2> {
+        if self.lasso {
+            return self.points.clone();
+        }
+        match (self.points.first(), self.points.last()) {
+            (Some(start), Some(end)) => vec![
+                *start,
+                Vec2::new(end.x, start.y),
+                *end,
+                Vec2::new(start.x, end.y),
+            ],
+            _ => vec![],
+        }
+    }
+}
+
+/// even-odd rule, the polygon is closed implicitly
+pub fn point_in_polygon(point: Vec2, polygon: &[Vec2]) -> bool {
+    let mut inside = false;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{asset::AssetId, math::Vec2, math::Vec3};
+
+    use super::{
+        expand_selection, point_in_polygon, AtomRef, SelectBy, SelectMode, Selection, SelectionDrag,
+    };
+    use crate::{
+        element::Element,
+        molecule::{Atom, Molecule},
+    };
+
+    #[test]
+    fn test_selects_atoms_with_modes() {
+        let mol = AssetId::default();
+        let atoms = |indices: &[usize]| -> Vec<AtomRef> {
+            indices
+                .iter()
+                .map(|index| AtomRef { mol, index: *index })
+                .collect()
+        };
+        let indices = |selection: &Selection| -> Vec<usize> {
+            let mut indices: Vec<usize> = selection.atoms.iter().map(|a| a.index).collect();
+            indices.sort();
+            indices
+        };
+        let mut selection = Selection::default();
+        assert!(selection.apply(atoms(&[0, 1]), SelectMode::Replace));
+        assert!(!selection.apply(atoms(&[1, 0]), SelectMode::Replace));
+        assert!(selection.apply(atoms(&[2]), SelectMode::Add));
+        assert!(!selection.apply(atoms(&[2]), SelectMode::Add));
+        assert_eq!(indices(&selection), vec![0, 1, 2]);
+
+        // partly selected groups are added, fully selected removed
+        assert!(selection.apply(atoms(&[2, 3]), SelectMode::Toggle));
+        assert_eq!(indices(&selection), vec![0, 1, 2, 3]);
+        assert!(selection.apply(atoms(&[2, 3]), SelectMode::Toggle));
+        assert_eq!(indices(&selection), vec![0, 1]);
+
+        assert!(selection.apply(vec![], SelectMode::Replace));
+        assert!(selection.atoms.is_empty());
+    }
+
+    #[test]
+    fn test_expands_selection_to_residues_chains_and_elements() {
+        let mut mol = Molecule::default();
+        let atoms = [
+            (Element::N, Some((1, "ALA")), Some("A")),
+            (Element::C, Some((1, "ALA")), Some("A")),
+            (Element::C, Some((2, "GLY")), Some("A")),
+            (Element::O, Some((3, "GLY")), Some("B")),
+            (Element::C, None, None),
+        ];
+        for (i, (element, residue, chain)) in atoms.into_iter().enumerate() {
+            let atom = Atom::new(i as i32 + 1, String::new(), element, Vec3::ZERO);
+            mol.add_atom(atom, residue, chain);
+        }
+
+        assert_eq!(expand_selection(&mol, &[1], SelectBy::Atom), vec![1]);
+        assert_eq!(expand_selection(&mol, &[1], SelectBy::Residue), vec![0, 1]);
+        assert_eq!(expand_selection(&mol, &[0], SelectBy::Chain), vec![0, 1, 2]);
+        assert_eq!(expand_selection(&mol, &[4], SelectBy::Chain), vec![4]);
+        assert_eq!(
+            expand_selection(&mol, &[2], SelectBy::Element),
+            vec![1, 2, 4]
+        );
+    }
+
+    #[test]
+    fn test_selects_points_in_box_and_lasso() {
+        let drag = SelectionDrag {
+            lasso: false,
+            points: vec![Vec2::new(10.0, 10.0), Vec2::new(0.0, 0.0)],
+        };
+        let polygon = drag.polygon();
+        assert!(point_in_polygon(Vec2::new(5.0, 5.0), &polygon));
+        assert!(!point_in_polygon(Vec2::new(15.0, 5.0), &polygon));
+
+        // a triangle
+        let drag = SelectionDrag {
+            lasso: true,
+            points: vec![Vec2::ZERO, Vec2::new(10.0, 0.0), Vec2::new(0.0, 10.0)],
+        };
+        let polygon = drag.polygon();
+        assert!(point_in_polygon(Vec2::new(2.0, 2.0), &polygon));
+        assert!(!point_in_polygon(Vec2::new(8.0, 8.0), &polygon));
+    }
+}
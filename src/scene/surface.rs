@@ -0,0 +1,113 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+
+use super::resource::SurfaceColor;
+use crate::{
+    molecule::{Atom, Molecule},
+    surface::SurfaceGeometry,
+};
+
+/// partial charge with the most intense color
+const MAX_CHARGE: f32 = 0.5;
+const NEGATIVE_COLOR: Srgba = Srgba::rgb(0.85, 0.2, 0.2);
+const NEUTRAL_COLOR: Srgba = Srgba::rgb(0.95, 0.95, 0.95);
+const POSITIVE_COLOR: Srgba = Srgba::rgb(0.2, 0.35, 0.9);
+const HYDROPHILIC_COLOR: Srgba = Srgba::rgb(0.3, 0.55, 0.9);
+const HYDROPHOBIC_COLOR: Srgba = Srgba::rgb(0.9, 0.55, 0.25);
+
+/// mesh of a computed surface, with the vertex colors of `surface_colors`
+pub fn surface_mesh(geometry: SurfaceGeometry, colors: Vec<[f32; 4]>) -> Mesh {
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, geometry.positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, geometry.normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(geometry.indices))
+}
+
+/// vertex colors of a surface, from the atom nearest to each vertex
+pub fn surface_colors(
+    mol: &Molecule,
+    nearest_atoms: &[usize],
+    color: &SurfaceColor,
+) -> Vec<[f32; 4]> {
+    nearest_atoms
+        .iter()
+        .map(|atom| {
+            let color = surface_color(mol, &mol.atoms[*atom], color);
+            LinearRgba::from(color).to_f32_array()
+        })
+        .collect()
+}
+
+/// white, the colors are in the vertices
+pub fn surface_material(opacity: f32) -> StandardMaterial {
+    StandardMaterial {
+        base_color: Color::srgba(1.0, 1.0, 1.0, opacity),
+        alpha_mode: if opacity < 1.0 {
+            AlphaMode::Blend
+        } else {
+            AlphaMode::Opaque
+        },
+        perceptual_roughness: 0.7,
+        ..default()
+    }
+}
+
+fn surface_color(mol: &Molecule, atom: &Atom, color: &SurfaceColor) -> Srgba {
+    match color {
+        SurfaceColor::Element => {
+            let [r, g, b] = atom.element.color();
+            Srgba::rgb_u8(r, g, b)
+        }
+        SurfaceColor::Charge => {
+            let charge = atom
+                .partial_charge
+                .or(atom.formal_charge.map(|c| c as f32))
+                .unwrap_or(0.0);
+            let t = (charge / MAX_CHARGE).clamp(-1.0, 1.0);
+            if t < 0.0 {
+                NEUTRAL_COLOR.mix(&NEGATIVE_COLOR, -t)
+            } else {
+                NEUTRAL_COLOR.mix(&POSITIVE_COLOR, t)
+            }
+        }
+        SurfaceColor::Hydrophobicity => match mol.residue(atom).and_then(|r| hydropathy(&r.name)) {
+            // the scale goes from -4.5 to 4.5
+            Some(value) => HYDROPHILIC_COLOR.mix(&HYDROPHOBIC_COLOR, (value + 4.5) / 9.0),
+            None => NEUTRAL_COLOR,
+        },
+    }
+}
+
+/// kyte-doolittle hydropathy of an amino acid, positive is hydrophobic
+fn hydropathy(residue_name: &str) -> Option<f32> {
+    let value = match residue_name.to_ascii_uppercase().as_str() {
+        "ILE" => 4.5,
+        "VAL" => 4.2,
+        "LEU" => 3.8,
+        "PHE" => 2.8,
+        "CYS" => 2.5,
+        "MET" => 1.9,
+        "ALA" => 1.8,
+        "GLY" => -0.4,
+        "THR" => -0.7,
+        "SER" => -0.8,
+        "TRP" => -0.9,
+        "TYR" => -1.3,
+        "PRO" => -1.6,
+        "HIS" => -3.2,
+        "GLU" | "GLN" | "ASP" | "ASN" => -3.5,
+        "LYS" => -3.9,
+        "ARG" => -4.5,
+        _ => return None,
+    };
+    Some(value)
+}
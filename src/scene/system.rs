@@ -1,29 +1,72 @@
 use super::{
+    cartoon::{cartoon_colors, cartoon_mesh, is_water},
     comp::sphere_pbr_bundle,
-    component::{MyBond, MyMolecule, MyMoleculeWrapper, Shape},
-    event::{AddedBoundingBox, UpdateSceneEvent},
+    component::{
+        BondSide, MeasurementLabel, MyAtom, MyBond, MyCartoon, MyMolecule, MyMoleculeWrapper,
+        MySurface, Shape, SurfaceTask,
+    },
+    event::{AddedBoundingBox, SelectionChangedEvent, UpdateSceneEvent, UpdateStyleEvent},
     helper::{add_mol, add_mol_wrapper},
-    resource::{MolRender, MolScene, MolSceneContent, MolStyle, PreloadedAssets},
+    impostor::{
+        impostor_quad_mesh, ray_cylinders_intersection, ray_spheres_intersection, ImpostorAtoms,
+        ImpostorBondLine, ImpostorBonds, ImpostorInstance, ImpostorShape, Impostors,
+    },
+    inspector::{BondRef, Hovered, Inspected},
+    lod::{lod_level, pixel_radius, Lod, LodMesh, LOD_LEVELS},
+    measurement::{measure, MeasureMode, Measurements},
+    resource::{
+        CartoonColor, MolEntities, MolEntityMap, MolRender, MolScene, MolSceneContent, MolStyle,
+        Playback, PreloadedAssets, SurfaceStyle, TrajectoryStream,
+    },
+    selection::{
+        atoms_by_mol, expand_selection, point_in_polygon, AtomRef, SelectMode, Selection,
+        SelectionDrag, CLICK_MAX_DISTANCE,
+    },
+    surface::{surface_colors, surface_material, surface_mesh},
 };
-use crate::mol2_asset_plugin::{Mol2Atom, Mol2Bond};
 use crate::{
     bounding_box::BoundingBox,
     element::Element,
-    mol2_asset_plugin::{bounding_box_for_mol, Mol2Molecule},
-    ui::{component::TooltipMarker, helper::add_tooltip, system::despawn_all_entities},
+    mol_collection::MolCollection,
+    molecule::{Bond, BondType, Molecule},
+    secondary_structure::{assign_secondary_structure, backbone},
+    surface::compute_surface,
+    trajectory_asset_plugin::{load_frame, Trajectory},
+    ui::{helper::add_floating_label, system::despawn_all_entities},
 };
 use bevy::{
-    color::palettes::css::{BLACK, GREEN, LIGHT_CYAN, MAGENTA, ORANGE, RED, WHITE, YELLOW},
     prelude::*,
+    render::{batching::NoAutomaticBatching, view::NoFrustumCulling},
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, IoTaskPool},
+    ui::RelativeCursorPosition,
+    utils::{HashMap, HashSet},
+    window::PrimaryWindow,
 };
 use bevy_mod_picking::{
     events::{Out, Over, Pointer},
-    prelude::{Highlight, HighlightKind, On},
+    prelude::{Highlight, HighlightKind, On, PickSelection},
     PickableBundle,
 };
 
-const SPHERE_LAT: usize = 32;
-const SPHERE_LON: usize = 18;
+/// sectors and stacks of the atom spheres, per level of detail
+const SPHERE_RESOLUTIONS: [(usize, usize); LOD_LEVELS] = [(32, 18), (16, 10), (8, 6)];
+/// per level of detail
+const CYLINDER_RESOLUTIONS: [u32; LOD_LEVELS] = [32, 12, 6];
+/// longitudes and latitudes of the stick capsules, per level of detail
+const CAPSULE_RESOLUTIONS: [(usize, usize); LOD_LEVELS] = [(32, 16), (12, 8), (6, 4)];
+const BOND_RADIUS: f32 = 0.07;
+/// lines of double / triple / aromatic bonds, dashes
+const SMALL_BOND_RADIUS: f32 = 0.04;
+const BOND_COLOR: Srgba = Srgba::new(0.4, 0.4, 0.4, 1.0);
+/// amide bonds (partial double bond character) are tinted blue, to distinguish them from single bonds
+const AMIDE_BOND_COLOR: Srgba = Srgba::new(0.35, 0.45, 0.65, 1.0);
+/// molecules with more atoms are always drawn with impostors, as an entity per atom is too slow
+const IMPOSTOR_MIN_ATOMS: usize = 10_000;
+const MEASUREMENT_COLOR: Srgba = Srgba::new(1.0, 0.85, 0.2, 1.0);
+/// length of the dashes (and gaps) of the measurement lines
+const MEASUREMENT_DASH: f32 = 0.12;
+/// radius of the arcs of angles and dihedrals
+const MEASUREMENT_ARC_RADIUS: f32 = 0.5;
 // const CAPSULE_LAT: usize = 32;
 // const CAPSULE_LON: usize = 16;
 
@@ -32,61 +75,46 @@ pub fn preload_item_assets(
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut preloaded_assets: ResMut<PreloadedAssets>,
 ) {
-    // This is slightly wasteful, as we don't need all the materials/elements for all the molecules
-    // but cost is negligible. This is also executed only once in the lifetime of the app.
-    // And performance should be "ideal" anyway for complex molecules / when using all the materials.
+    // atom materials are created lazily (see `PreloadedAssets::atom_material`), as there's one per element
     let materials = &mut materials;
-    let c_mat = atom_material(materials, Element::C);
-    let h_mat = atom_material(materials, Element::H);
-    let n_mat = atom_material(materials, Element::N);
-    let o_mat = atom_material(materials, Element::O);
-    let f_mat = atom_material(materials, Element::F);
-    let p_mat = atom_material(materials, Element::P);
-    let s_mat = atom_material(materials, Element::S);
-    let ca_mat = atom_material(materials, Element::Ca);
-    let atom_mesh: Handle<Mesh> = atom_mesh(&mut meshes);
+    let atom_meshes = atom_meshes(&mut meshes);
     let bond_mat: Handle<StandardMaterial> = bond_material(materials);
-    let bond_cyl_mesh: Handle<Mesh> = bond_cylinder_mesh(&mut meshes, 0.07);
-    let bond_caps_mesh: Handle<Mesh> = bond_capsule_mesh(&mut meshes, 0.07);
-    let bond_small_cyl_mesh: Handle<Mesh> = bond_cylinder_mesh(&mut meshes, 0.04);
-    let bond_small_caps_mesh: Handle<Mesh> = bond_capsule_mesh(&mut meshes, 0.04);
+    let amide_bond_mat: Handle<StandardMaterial> = amide_bond_material(materials);
+    let bond_cyl_meshes = bond_cylinder_meshes(&mut meshes, BOND_RADIUS);
+    let bond_caps_meshes = bond_capsule_meshes(&mut meshes, BOND_RADIUS);
+    let bond_small_cyl_meshes = bond_cylinder_meshes(&mut meshes, SMALL_BOND_RADIUS);
+    let bond_small_caps_meshes = bond_capsule_meshes(&mut meshes, SMALL_BOND_RADIUS);
+    let cartoon_mat: Handle<StandardMaterial> = cartoon_material(materials);
+    let surface_mat = materials.add(surface_material(SurfaceStyle::default().opacity));
+    let impostor_quad = meshes.add(impostor_quad_mesh());
 
     *preloaded_assets = PreloadedAssets {
-        h_mat,
-        c_mat,
-        o_mat,
-        n_mat,
-        f_mat,
-        p_mat,
-        s_mat,
-        ca_mat,
-        atom_mesh,
+        atom_mats: HashMap::new(),
+        atom_meshes,
         bond_mat,
-        bond_cyl_mesh,
-        bond_caps_mesh,
-        bond_small_cyl_mesh,
-        bond_small_caps_mesh,
+        amide_bond_mat,
+        bond_cyl_meshes,
+        bond_caps_meshes,
+        bond_small_cyl_meshes,
+        bond_small_caps_meshes,
+        cartoon_mat,
+        surface_mat,
+        impostor_quad,
     };
 }
 
-fn tooltip_descr(atom: &Mol2Atom) -> String {
-    format!(
-        "Id: {},\nname: {},\npos: {},\ntype: {},\nmol name: {}",
-        atom.id,
-        atom.name,
-        atom.loc_vec3(),
-        atom.type_,
-        atom.mol_name
-    )
-}
-
+#[allow(clippy::too_many_arguments)]
 pub fn handle_update_scene_event(
     mut event: EventReader<UpdateSceneEvent>,
     mut commands: Commands,
     molecule: Query<Entity, With<MyMolecule>>,
     mut scene: ResMut<MolScene>,
-    assets: Res<Assets<Mol2Molecule>>,
-    preloaded_assets: Res<PreloadedAssets>,
+    assets: Res<Assets<Molecule>>,
+    collections: Res<Assets<MolCollection>>,
+    mut preloaded_assets: ResMut<PreloadedAssets>,
+    mut entities: ResMut<MolEntities>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut wrapper_query: Query<(Entity, &mut Transform), (With<MyMoleculeWrapper>, Without<MyBond>)>,
 ) {
     for _ in event.read() {
@@ -97,7 +125,11 @@ pub fn handle_update_scene_event(
             &molecule,
             &mut scene,
             &assets,
-            &preloaded_assets,
+            &collections,
+            &mut preloaded_assets,
+            &mut entities,
+            &mut materials,
+            &mut meshes,
             &mut wrapper_query,
         );
     }
@@ -127,44 +159,145 @@ static HIGHLIGHT_TINT: Highlight<StandardMaterial> = Highlight {
     })),
 };
 
+/// applies a change between the ball, stick and ball and stick styles to the drawn entities: shows or hides the atoms
+/// and bonds, rescales the atoms and swaps the bond meshes. other changes rebuild the scene
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn handle_update_style_event(
+    mut events: EventReader<UpdateStyleEvent>,
+    scene: Res<MolScene>,
+    mut entities: ResMut<MolEntities>,
+    mols: Res<Assets<Molecule>>,
+    assets: Res<PreloadedAssets>,
+    mut atoms: Query<(&mut Transform, &mut Visibility), With<MyAtom>>,
+    mut bonds: Query<(&mut Lod, &mut Handle<Mesh>, &mut Visibility), Without<MyAtom>>,
+    mut scene_event_writer: EventWriter<UpdateSceneEvent>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    let render = scene.render;
+    let in_place = |render: MolRender| {
+        matches!(
+            render,
+            MolRender::Ball | MolRender::Stick | MolRender::BallStick
+        )
+    };
+    let Some(drawn) = entities.render else {
+        // nothing drawn yet
+        return;
+    };
+    if entities.impostors || !in_place(drawn) || !in_place(render) {
+        scene_event_writer.send(UpdateSceneEvent);
+        return;
+    }
+    if drawn == render {
+        return;
+    }
+    println!("updating style in place");
+
+    for (id, map) in &entities.mols {
+        let Some(mol) = mols.get(*id) else {
+            continue;
+        };
+        for (index, entity) in &map.atoms {
+            if let Ok((mut transform, mut visibility)) = atoms.get_mut(*entity) {
+                let scale = sphere_scale(&render, &scene.style, &mol.atoms[*index].element);
+                transform.scale = Vec3::splat(scale);
+                *visibility = atom_visibility(&render);
+            }
+        }
+        for (index, lines) in &map.bonds {
+            let lod_mesh = bond_lod_mesh(&render, &bond_diameter(&mol.bonds[*index]));
+            for entity in lines {
+                if let Ok((mut lod, mut mesh, mut visibility)) = bonds.get_mut(*entity) {
+                    lod.mesh = lod_mesh;
+                    *mesh = assets.lod_mesh(lod_mesh, lod.level);
+                    *visibility = bond_visibility(&render);
+                }
+            }
+        }
+    }
+    entities.render = Some(render);
+}
+
+/// perceives the bonds of the shown molecules if they have none or if forced, see `Molecule::update_perceived_bonds`.
+/// runs when a molecule is loaded (before it's drawn) and when the scene changes (e.g. the toggle)
+pub fn update_perceived_bonds(
+    scene: Res<MolScene>,
+    mut assets: ResMut<Assets<Molecule>>,
+    collections: Res<Assets<MolCollection>>,
+    mut event_writer: EventWriter<UpdateSceneEvent>,
+) {
+    let waiting = scene.content.waiting_for_async_handle();
+    if !waiting && !scene.is_changed() {
+        return;
+    }
+    let mut changed = false;
+    for id in scene.content.molecule_ids(&collections) {
+        if let Some(mol) = assets.get_mut(id) {
+            changed |= mol.update_perceived_bonds(scene.force_bond_perception);
+        }
+    }
+    // a loaded molecule is drawn anyway
+    if changed && !waiting {
+        event_writer.send(UpdateSceneEvent);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn check_file_loaded(
     mut commands: Commands,
     mol_query: Query<Entity, With<MyMolecule>>,
-    assets: Res<Assets<Mol2Molecule>>,
+    assets: Res<Assets<Molecule>>,
+    collections: Res<Assets<MolCollection>>,
     mut scene: ResMut<MolScene>,
     mut event_writer: EventWriter<AddedBoundingBox>,
-    preloaded_assets: Res<PreloadedAssets>,
+    mut preloaded_assets: ResMut<PreloadedAssets>,
+    mut entities: ResMut<MolEntities>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut wrapper_query: Query<(Entity, &mut Transform), (With<MyMoleculeWrapper>, Without<MyBond>)>,
+    mut playback: ResMut<Playback>,
 ) {
-    if let MolSceneContent::Mol2 {
-        handle,
-        waiting_for_async_handle,
-    } = &scene.content
-    {
-        if *waiting_for_async_handle {
-            if let Some(mol) = assets.get(handle) {
-                if let Ok((_, mut wrapper_transform)) = wrapper_query.get_single_mut() {
-                    // got the molecule - set flag to false so this is not called again
-                    scene.content = MolSceneContent::Mol2 {
-                        handle: handle.clone(),
-                        waiting_for_async_handle: false,
-                    };
+    if scene.content.waiting_for_async_handle() {
+        let mols = scene.content.molecules(&assets, &collections);
+        if let Some(bounding_box) = bounding_box_for_mols(&mols) {
+            if let Ok((_, mut wrapper_transform)) = wrapper_query.get_single_mut() {
+                // got the molecule - set flag to false so this is not called again
+                scene.content.set_waiting_for_async_handle(false);
 
-                    // reset transforms (note: this just resets rotation, translation is managed with the camera)
-                    *wrapper_transform = Transform::IDENTITY;
+                // reset transforms (note: this just resets rotation, translation is managed with the camera)
+                *wrapper_transform = Transform::IDENTITY;
 
-                    let bounding_box = bounding_box_for_mol(mol);
-                    event_writer.send(AddedBoundingBox(bounding_box));
+                event_writer.send(AddedBoundingBox(bounding_box));
+                *playback = Playback {
+                    fps: playback.fps,
+                    looping: playback.looping,
+                    ..default()
+                };
 
-                    println!("received loaded mol event, will rebuild");
-                    clear(&mut commands, &mol_query);
+                println!("received loaded mol event, will rebuild");
+                clear(&mut commands, &mol_query);
+                *entities = MolEntities {
+                    render: Some(scene.render),
+                    ..default()
+                };
 
-                    draw_mol2_mol(
+                for id in scene.content.molecule_ids(&collections) {
+                    let Some(mol) = assets.get(id) else {
+                        continue;
+                    };
+                    draw_mol(
                         &mut commands,
+                        id,
                         mol,
-                        &scene.style,
-                        &scene.render,
-                        &preloaded_assets,
+                        &scene,
+                        &mut preloaded_assets,
+                        &mut entities,
+                        &mut materials,
+                        &mut meshes,
                         &mut wrapper_query,
                     );
                 }
@@ -173,6 +306,13 @@ pub fn check_file_loaded(
     }
 }
 
+/// bounding box containing all the molecules, none if there are no molecules
+fn bounding_box_for_mols(mols: &[&Molecule]) -> Option<BoundingBox> {
+    mols.iter()
+        .map(|mol| mol.bounding_box())
+        .reduce(|acc, bounding_box| acc.union(&bounding_box))
+}
+
 pub fn handle_added_bounding_box(
     mut mol_query: Query<&mut Transform, With<MyMoleculeWrapper>>,
     mut events: EventReader<AddedBoundingBox>,
@@ -185,43 +325,63 @@ pub fn handle_added_bounding_box(
 }
 
 fn update_for_bounding_box(transform: &mut Transform, bounding_box: &BoundingBox) {
-    transform.translation.x = -bounding_box.mid_x();
-    transform.translation.y = -bounding_box.mid_y();
-    transform.translation.z = -bounding_box.mid_z();
+    // the center of the box is moved to the origin, also if the molecule was rotated (zooming to atoms)
+    let mid = Vec3::new(
+        bounding_box.mid_x(),
+        bounding_box.mid_y(),
+        bounding_box.mid_z(),
+    );
+    transform.translation = -(transform.rotation * (transform.scale * mid));
     // println!(
     //     "new bounding box: {:?}, updated translation to: {:?}",
     //     bounding_box, transform.translation
     // );
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_scene(
     commands: &mut Commands,
     mol_query: &Query<Entity, With<MyMolecule>>,
     scene: &mut ResMut<MolScene>,
-    assets: &Res<Assets<Mol2Molecule>>,
-    preloaded_assets: &Res<PreloadedAssets>,
+    assets: &Res<Assets<Molecule>>,
+    collections: &Res<Assets<MolCollection>>,
+    preloaded_assets: &mut PreloadedAssets,
+    entities: &mut MolEntities,
+    materials: &mut Assets<StandardMaterial>,
+    meshes: &mut Assets<Mesh>,
     wrapper_query: &mut Query<(Entity, &mut Transform), (With<MyMoleculeWrapper>, Without<MyBond>)>,
 ) {
     match &scene.content {
-        MolSceneContent::Mol2 { handle, .. } => {
-            if let Some(mol) = assets.get(handle) {
+        MolSceneContent::Molecule { .. } | MolSceneContent::Collection { .. } => {
+            let mols = scene.content.molecules(assets, collections);
+            if !mols.is_empty() {
                 clear(commands, mol_query);
+                *entities = MolEntities {
+                    render: Some(scene.render),
+                    ..default()
+                };
 
                 // build scene
-                draw_mol2_mol(
-                    commands,
-                    mol,
-                    &scene.style,
-                    &scene.render,
-                    preloaded_assets,
-                    wrapper_query,
-                );
+                for id in scene.content.molecule_ids(collections) {
+                    let Some(mol) = assets.get(id) else {
+                        continue;
+                    };
+                    draw_mol(
+                        commands,
+                        id,
+                        mol,
+                        scene,
+                        preloaded_assets,
+                        entities,
+                        materials,
+                        meshes,
+                        wrapper_query,
+                    );
+                }
             } else {
                 // when the user loads a file, there's *no* scene update event, so we shouldn't be here
                 // this is for things like changing the rendering type: normally the file is already loaded
-                println!(
-                    "Warn: got update scene event of type Mol2 but file is not loaded (yet?)."
-                );
+                println!("Warn: got update scene event but file is not loaded (yet?).");
             }
         }
         MolSceneContent::Empty => {}
@@ -248,72 +408,911 @@ fn update_scene(
 //     );
 // }
 
-fn draw_mol2_mol(
+#[allow(clippy::too_many_arguments)]
+fn draw_mol(
     commands: &mut Commands,
-    mol: &Mol2Molecule,
-    mol_style: &MolStyle,
-    mol_render: &MolRender,
-    assets: &Res<PreloadedAssets>,
+    mol_id: AssetId<Molecule>,
+    mol: &Molecule,
+    scene: &MolScene,
+    assets: &mut PreloadedAssets,
+    entities: &mut MolEntities,
+    materials: &mut Assets<StandardMaterial>,
+    meshes: &mut Assets<Mesh>,
     wrapper_query: &mut Query<(Entity, &mut Transform), (With<MyMoleculeWrapper>, Without<MyBond>)>,
 ) {
     if let Ok((wrapper_entity, _)) = wrapper_query.get_single_mut() {
         let mol_entity = add_mol(commands, wrapper_entity);
+        let mol_style = &scene.style;
+        let mol_render = &scene.render;
+        let min_lod_level = usize::from(mol.atoms.len() > scene.lod.coarse_atom_count);
 
-        if *mol_render != MolRender::Stick {
-            for atom in &mol.atoms {
-                let material = match atom.element {
-                    Element::H => assets.h_mat.clone(),
-                    Element::C => assets.c_mat.clone(),
-                    Element::N => assets.n_mat.clone(),
-                    Element::O => assets.o_mat.clone(),
-                    Element::F => assets.f_mat.clone(),
-                    Element::P => assets.p_mat.clone(),
-                    Element::S => assets.s_mat.clone(),
-                    Element::Ca => assets.ca_mat.clone(),
-                };
+        // atoms shown as spheres and sticks: with the cartoon, only the ones not in the backbone residues
+        let mut shown = vec![true; mol.atoms.len()];
+        if *mol_render == MolRender::Cartoon {
+            let residues = add_cartoon(
+                commands,
+                mol,
+                &scene.cartoon_color,
+                mol_entity,
+                assets,
+                meshes,
+            );
+            for (atom, shown) in mol.atoms.iter().zip(&mut shown) {
+                *shown = !atom.residue.is_some_and(|residue| {
+                    residues.contains(&residue) || is_water(&mol.residues[residue].name)
+                });
+            }
+        }
+        if *mol_render == MolRender::Surface {
+            add_surface(commands, mol_id, mol, &scene.surface, mol_entity);
+        }
+        for atom in scene.overrides.hidden.iter().filter(|a| a.mol == mol_id) {
+            if let Some(shown) = shown.get_mut(atom.index) {
+                *shown = false;
+            }
+        }
 
-                add_atom(
-                    commands,
-                    mol_style,
-                    mol_render,
-                    mol_entity,
-                    atom.loc_vec3(),
-                    &atom.element,
-                    &tooltip_descr(atom),
-                    &material,
-                    &assets.atom_mesh,
-                );
+        if scene.impostors || mol.atoms.len() >= IMPOSTOR_MIN_ATOMS {
+            add_impostors(commands, mol_id, mol, &shown, scene, mol_entity, assets);
+            entities.impostors = true;
+            return;
+        }
+
+        // all the atoms and bonds are added, the ones not used by the style hidden, so changing it doesn't rebuild them
+        let mut map = MolEntityMap::default();
+        // a material per color the user colored atoms with
+        let mut color_materials: HashMap<[u8; 4], Handle<StandardMaterial>> = HashMap::new();
+        for (index, atom) in mol.atoms.iter().enumerate() {
+            if !shown[index] {
+                continue;
             }
+            let material = match scene.overrides.color(mol_id, index) {
+                Some(color) => color_materials
+                    .entry(color.to_u8_array())
+                    .or_insert_with(|| materials.add(StandardMaterial::from_color(color)))
+                    .clone(),
+                None => assets.atom_material(materials, atom.element),
+            };
+
+            let entity = add_atom(
+                commands,
+                mol_style,
+                mol_render,
+                mol_entity,
+                index,
+                atom.position,
+                &atom.element,
+                mol_id,
+                &material,
+                assets,
+                min_lod_level,
+            );
+            map.atoms.insert(index, entity);
         }
 
-        if *mol_render != MolRender::Ball {
-            for bond in &mol.bonds {
-                add_bond(
-                    commands,
-                    &assets.bond_mat,
-                    mol_render,
-                    mol_entity,
-                    // ASSUMPTION: atoms ordered by id, 1-indexed, no gaps
-                    // this seems to be always the case in mol2 files
-                    mol.atoms[bond.atom1 - 1].loc_vec3(),
-                    mol.atoms[bond.atom2 - 1].loc_vec3(),
-                    assets,
-                    bond,
-                );
+        let bonds_per_atom = mol.bonds_per_atom();
+        for (index, bond) in mol.bonds.iter().enumerate() {
+            if !shown[bond.atom1] || !shown[bond.atom2] {
+                continue;
             }
+            let material = match bond.type_ {
+                BondType::Amide => &assets.amide_bond_mat,
+                _ => &assets.bond_mat,
+            };
+            let side = bond_side(mol, &bonds_per_atom, index);
+            let lines = add_bond(
+                commands,
+                material,
+                mol_render,
+                mol_entity,
+                BondRef { mol: mol_id, index },
+                mol.atoms[bond.atom1].position,
+                mol.atoms[bond.atom2].position,
+                side,
+                assets,
+                min_lod_level,
+                bond,
+                |atom| mol.atoms[atom].position,
+            );
+            map.bonds.insert(index, lines);
         }
+        entities.mols.insert(mol_id, map);
     } else {
         eprintln!("No mol wrapper found, can't add mol.");
     }
 }
 
+/// adds the shown atoms and bonds as impostors: an entity with all the spheres and one with all the cylinders
+#[allow(clippy::too_many_arguments)]
+fn add_impostors(
+    commands: &mut Commands,
+    mol_id: AssetId<Molecule>,
+    mol: &Molecule,
+    shown: &[bool],
+    scene: &MolScene,
+    parent: Entity,
+    assets: &PreloadedAssets,
+) {
+    let mol_style = &scene.style;
+    let mol_render = &scene.render;
+    let atoms: Vec<usize> = (0..mol.atoms.len()).filter(|a| shown[*a]).collect();
+    let spheres = atoms
+        .iter()
+        .map(|index| {
+            let atom = &mol.atoms[*index];
+            let radius = match mol_render {
+                // the joints of the sticks
+                MolRender::Stick => BOND_RADIUS,
+                // the sphere mesh has a radius of 0.5
+                _ => sphere_scale(mol_render, mol_style, &atom.element) / 2.0,
+            };
+            ImpostorInstance {
+                start: atom.position,
+                end: atom.position,
+                radius,
+                color: scene
+                    .overrides
+                    .color(mol_id, *index)
+                    .unwrap_or_else(|| color_for_element(&atom.element))
+                    .into(),
+            }
+        })
+        .collect();
+    add_impostor_entity(
+        commands,
+        parent,
+        assets,
+        Impostors {
+            shape: ImpostorShape::Sphere,
+            instances: spheres,
+            hovered: None,
+            selected: HashSet::new(),
+        },
+        ImpostorAtoms { mol: mol_id, atoms },
+    );
+
+    if *mol_render == MolRender::Ball {
+        return;
+    }
+    let bonds_per_atom = mol.bonds_per_atom();
+    let mut cylinders = vec![];
+    let mut lines = vec![];
+    for (index, bond) in mol.bonds.iter().enumerate() {
+        if !shown[bond.atom1] || !shown[bond.atom2] {
+            continue;
+        }
+        let side = bond_side(mol, &bonds_per_atom, index);
+        let color = match bond.type_ {
+            BondType::Amide => AMIDE_BOND_COLOR,
+            _ => BOND_COLOR,
+        };
+        let coords = bond_coords(
+            bond,
+            mol.atoms[bond.atom1].position,
+            mol.atoms[bond.atom2].position,
+            side.position(|atom| mol.atoms[atom].position),
+            side.is_ring(),
+        );
+        for (line, coords) in coords.into_iter().enumerate() {
+            cylinders.push(ImpostorInstance {
+                start: coords.start,
+                end: coords.end,
+                radius: bond_diameter(bond).radius(),
+                color: color.into(),
+            });
+            lines.push(ImpostorBondLine {
+                index,
+                line,
+                side: side.clone(),
+            });
+        }
+    }
+    add_impostor_entity(
+        commands,
+        parent,
+        assets,
+        Impostors {
+            shape: ImpostorShape::Cylinder,
+            instances: cylinders,
+            hovered: None,
+            selected: HashSet::new(),
+        },
+        ImpostorBonds { mol: mol_id, lines },
+    );
+}
+
+fn add_impostor_entity(
+    commands: &mut Commands,
+    parent: Entity,
+    assets: &PreloadedAssets,
+    impostors: Impostors,
+    data: impl Bundle,
+) {
+    let entity = commands
+        .spawn((
+            assets.impostor_quad.clone(),
+            SpatialBundle::default(),
+            // the quad mesh doesn't bound the instances
+            NoFrustumCulling,
+            // the instances are drawn per entity
+            NoAutomaticBatching,
+            impostors,
+            data,
+        ))
+        .id();
+    commands.entity(parent).add_child(entity);
+}
+
+/// hover of the atoms and bonds drawn as impostors: the nearest sphere or cylinder hit by the ray through the cursor,
+/// shown in the inspector
+#[allow(clippy::type_complexity)]
+pub fn pick_impostors(
+    mut cursor_moved: EventReader<CursorMoved>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut impostors_query: Query<(
+        Entity,
+        &mut Impostors,
+        &GlobalTransform,
+        Option<&ImpostorAtoms>,
+        Option<&ImpostorBonds>,
+    )>,
+    mut hover: ResMut<Hovered>,
+) {
+    let Some(cursor) = cursor_moved.read().last().map(|e| e.position) else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Some(ray) = camera.viewport_to_world(camera_transform, cursor) else {
+        return;
+    };
+
+    // the shapes are intersected in the space of the molecule, the ray parameters are comparable as the ray isn't normalized
+    let nearest = impostors_query
+        .iter()
+        .filter_map(|(entity, impostors, transform, _, _)| {
+            let inverse = transform.affine().inverse();
+            let origin = inverse.transform_point3(ray.origin);
+            let direction = inverse.transform_vector3(*ray.direction);
+            let instances = impostors.instances.iter();
+            let hit = match impostors.shape {
+                ImpostorShape::Sphere => ray_spheres_intersection(
+                    origin,
+                    direction,
+                    instances.map(|i| (i.start, i.radius)),
+                ),
+                ImpostorShape::Cylinder => ray_cylinders_intersection(
+                    origin,
+                    direction,
+                    instances.map(|i| (i.start, i.end, i.radius)),
+                ),
+            };
+            hit.map(|(instance, t)| (entity, instance, t))
+        })
+        .min_by(|(_, _, t1), (_, _, t2)| t1.total_cmp(t2));
+
+    for (entity, mut impostors, _, atoms, bonds) in &mut impostors_query {
+        let hovered = nearest
+            .filter(|(e, _, _)| *e == entity)
+            .map(|(_, instance, _)| instance);
+        if impostors.hovered == hovered {
+            continue;
+        }
+        let inspected = |instance: usize| match (atoms, bonds) {
+            (Some(atoms), _) => Some(Inspected::Atom(AtomRef {
+                mol: atoms.mol,
+                index: atoms.atoms[instance],
+            })),
+            (None, Some(bonds)) => Some(Inspected::Bond(BondRef {
+                mol: bonds.mol,
+                index: bonds.lines[instance].index,
+            })),
+            (None, None) => None,
+        };
+        if let Some(inspected) = impostors.hovered.and_then(inspected) {
+            hover.leave(inspected);
+        }
+        impostors.hovered = hovered;
+        if let Some(inspected) = hovered.and_then(inspected) {
+            hover.0 = Some(inspected);
+        }
+    }
+}
+
+/// selects the clicked atom (shift toggles it), or the atoms in a box dragged with the right mouse button
+/// (a lasso with alt, shift adds them), expanded by `Selection::by`. clicking nothing clears the selection.
+/// in a measure mode the clicked atom is picked for the measurement instead
+#[allow(clippy::too_many_arguments)]
+pub fn handle_selection_input(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    ui: Query<&Interaction>,
+    panels: Query<&RelativeCursorPosition>,
+    entities: Res<MolEntities>,
+    atoms: Query<(&Transform, &GlobalTransform, &Visibility), With<MyAtom>>,
+    impostors: Query<(&ImpostorAtoms, &Impostors, &GlobalTransform)>,
+    mols: Res<Assets<Molecule>>,
+    mut selection: ResMut<Selection>,
+    mut selection_events: EventWriter<SelectionChangedEvent>,
+    mut measurements: ResMut<Measurements>,
+    mut gizmos: Gizmos,
+    mut click_start: Local<Option<Vec2>>,
+    mut drag: Local<Option<SelectionDrag>>,
+) {
+    let Some(cursor) = windows.get_single().ok().and_then(|w| w.cursor_position()) else {
+        // released outside of the window, the drag is cancelled
+        if mouse.just_released(MouseButton::Right) {
+            *drag = None;
+        }
+        return;
+    };
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    // the panels (side panel, inspector, popups) aren't interactive, only their buttons
+    let over_ui = ui
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+        || panels.iter().any(|panel| panel.mouse_over());
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if mouse.just_pressed(MouseButton::Left) && !over_ui {
+        *click_start = Some(cursor);
+    }
+    if mouse.just_pressed(MouseButton::Right) && !over_ui {
+        *drag = Some(SelectionDrag {
+            lasso: keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]),
+            points: vec![cursor],
+        });
+    }
+    if let Some(drag) = drag.as_mut() {
+        if drag.points.last() != Some(&cursor) {
+            drag.points.push(cursor);
+        }
+        draw_selection_drag(&mut gizmos, camera, camera_transform, drag);
+    }
+
+    let (picked, mode) = if mouse.just_released(MouseButton::Left) {
+        // dragging with the left mouse button rotates the molecule
+        let Some(start) = click_start
+            .take()
+            .filter(|s| s.distance(cursor) <= CLICK_MAX_DISTANCE)
+        else {
+            return;
+        };
+        let Some(ray) = camera.viewport_to_world(camera_transform, start) else {
+            return;
+        };
+        let atoms = world_atoms(&entities, &atoms, &impostors);
+        let spheres = atoms.iter().map(|(_, center, radius)| (*center, *radius));
+        let hit = ray_spheres_intersection(ray.origin, *ray.direction, spheres);
+        let picked: Vec<AtomRef> = hit.map(|(index, _)| atoms[index].0).into_iter().collect();
+        if measurements.mode != MeasureMode::Off {
+            if let Some(atom) = picked.first() {
+                measurements.pick(*atom);
+            }
+            return;
+        }
+        let mode = if shift {
+            SelectMode::Toggle
+        } else {
+            SelectMode::Replace
+        };
+        (picked, mode)
+    } else if mouse.just_released(MouseButton::Right) {
+        let Some(drag) = drag.take().filter(|drag| drag.points.len() > 1) else {
+            return;
+        };
+        let polygon = drag.polygon();
+        let picked = world_atoms(&entities, &atoms, &impostors)
+            .into_iter()
+            .filter(|(_, center, _)| {
+                camera
+                    .world_to_viewport(camera_transform, *center)
+                    .is_some_and(|point| point_in_polygon(point, &polygon))
+            })
+            .map(|(atom, _, _)| atom)
+            .collect();
+        let mode = if shift {
+            SelectMode::Add
+        } else {
+            SelectMode::Replace
+        };
+        (picked, mode)
+    } else {
+        return;
+    };
+
+    let mut expanded = vec![];
+    for (mol_id, indices) in atoms_by_mol(&picked) {
+        if let Some(mol) = mols.get(mol_id) {
+            let indices = expand_selection(mol, &indices, selection.by);
+            expanded.extend(
+                indices
+                    .into_iter()
+                    .map(|index| AtomRef { mol: mol_id, index }),
+            );
+        }
+    }
+    // only marked as changed if the atoms changed
+    if selection.bypass_change_detection().apply(expanded, mode) {
+        selection.set_changed();
+        selection_events.send(SelectionChangedEvent);
+    }
+}
+
+/// the outline of the selection box or lasso, just in front of the camera
+fn draw_selection_drag(
+    gizmos: &mut Gizmos,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    drag: &SelectionDrag,
+) {
+    let points: Vec<Vec3> = drag
+        .polygon()
+        .into_iter()
+        .filter_map(|point| camera.viewport_to_world(camera_transform, point))
+        .map(|ray| ray.get_point(0.01))
+        .collect();
+    if let Some(first) = points.first() {
+        gizmos.linestrip(points.iter().copied().chain([*first]), Color::WHITE);
+    }
+}
+
+/// the shown atoms, with their center and radius in world space, to pick and select them
+fn world_atoms(
+    entities: &MolEntities,
+    atoms: &Query<(&Transform, &GlobalTransform, &Visibility), With<MyAtom>>,
+    impostors: &Query<(&ImpostorAtoms, &Impostors, &GlobalTransform)>,
+) -> Vec<(AtomRef, Vec3, f32)> {
+    let mut world_atoms = vec![];
+    for (mol, map) in &entities.mols {
+        for (index, entity) in &map.atoms {
+            let Ok((transform, global_transform, visibility)) = atoms.get(*entity) else {
+                continue;
+            };
+            let scale = global_transform.affine().matrix3.x_axis.length();
+            // the sphere mesh has a radius of 0.5, hidden atoms (stick style) are the joints of the sticks
+            let radius = if visibility == Visibility::Hidden {
+                BOND_RADIUS * scale / transform.scale.x
+            } else {
+                0.5 * scale
+            };
+            let atom = AtomRef {
+                mol: *mol,
+                index: *index,
+            };
+            world_atoms.push((atom, global_transform.translation(), radius));
+        }
+    }
+    for (impostor_atoms, impostors, transform) in impostors {
+        let scale = transform.affine().matrix3.x_axis.length();
+        for (instance, index) in impostors.instances.iter().zip(&impostor_atoms.atoms) {
+            let atom = AtomRef {
+                mol: impostor_atoms.mol,
+                index: *index,
+            };
+            world_atoms.push((
+                atom,
+                transform.transform_point(instance.start),
+                instance.radius * scale,
+            ));
+        }
+    }
+    world_atoms
+}
+
+/// drops the selected atoms of molecules that aren't shown anymore
+pub fn drop_hidden_selection(
+    scene: Res<MolScene>,
+    collections: Res<Assets<MolCollection>>,
+    mut selection: ResMut<Selection>,
+    mut selection_events: EventWriter<SelectionChangedEvent>,
+) {
+    if !scene.is_changed() {
+        return;
+    }
+    let shown = scene.content.molecule_ids(&collections);
+    if selection
+        .atoms
+        .iter()
+        .any(|atom| !shown.contains(&atom.mol))
+    {
+        selection.atoms.retain(|atom| shown.contains(&atom.mol));
+        selection_events.send(SelectionChangedEvent);
+    }
+}
+
+/// drops the measurements (and picked atoms) of molecules that aren't shown anymore
+pub fn drop_hidden_measurements(
+    scene: Res<MolScene>,
+    collections: Res<Assets<MolCollection>>,
+    mut measurements: ResMut<Measurements>,
+) {
+    if !scene.is_changed() {
+        return;
+    }
+    let shown = scene.content.molecule_ids(&collections);
+    let is_shown = |atom: &AtomRef| shown.contains(&atom.mol);
+    let measured = measurements.list.iter().flat_map(|m| &m.atoms);
+    if measurements.picked.iter().chain(measured).all(is_shown) {
+        return;
+    }
+    measurements.picked.retain(is_shown);
+    measurements
+        .list
+        .retain(|measurement| measurement.atoms.iter().all(is_shown));
+}
+
+/// draws the measurements with dashed lines (and arcs) and a label following the atoms, updating their values.
+/// the atoms picked for the next measurement are circled
+#[allow(clippy::too_many_arguments)]
+pub fn draw_measurements(
+    mut commands: Commands,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    entities: Res<MolEntities>,
+    atoms: Query<(&Transform, &GlobalTransform, &Visibility), With<MyAtom>>,
+    impostors: Query<(&ImpostorAtoms, &Impostors, &GlobalTransform)>,
+    mut measurements: ResMut<Measurements>,
+    mut labels: Query<(Entity, &MeasurementLabel, &mut Style, &mut Text)>,
+    mut gizmos: Gizmos,
+) {
+    if measurements.list.is_empty() && measurements.picked.is_empty() && labels.is_empty() {
+        return;
+    }
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let world: HashMap<AtomRef, (Vec3, f32)> = world_atoms(&entities, &atoms, &impostors)
+        .into_iter()
+        .map(|(atom, center, radius)| (atom, (center, radius)))
+        .collect();
+
+    for atom in &measurements.picked {
+        if let Some((center, radius)) = world.get(atom) {
+            gizmos.sphere(*center, Quat::IDENTITY, radius * 1.3, MEASUREMENT_COLOR);
+        }
+    }
+
+    // by measurement id, the text and where it's shown
+    let mut shown_labels: HashMap<usize, (String, Vec2)> = HashMap::new();
+    let mut changed = false;
+    for measurement in &mut measurements.bypass_change_detection().list {
+        let positions: Option<Vec<Vec3>> = measurement
+            .atoms
+            .iter()
+            .map(|atom| world.get(atom).map(|(center, _)| *center))
+            .collect();
+        let value = positions.as_deref().and_then(measure);
+        if measurement.value != value {
+            measurement.value = value;
+            changed = true;
+        }
+        let Some(positions) = positions else {
+            continue;
+        };
+        draw_measurement(&mut gizmos, &positions);
+        let center = positions.iter().sum::<Vec3>() / positions.len() as f32;
+        if let Some(point) = camera.world_to_viewport(camera_transform, center) {
+            shown_labels.insert(measurement.id, (measurement.text(), point));
+        }
+    }
+    // only marked as changed if a value changed, as it's updated every frame
+    if changed {
+        measurements.set_changed();
+    }
+
+    for (entity, label, mut style, mut text) in &mut labels {
+        let Some((value, point)) = shown_labels.remove(&label.id) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        if style.left != Val::Px(point.x) || style.top != Val::Px(point.y) {
+            style.left = Val::Px(point.x);
+            style.top = Val::Px(point.y);
+        }
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+    for (id, (value, point)) in shown_labels {
+        add_floating_label(&mut commands, point, value, MeasurementLabel { id });
+    }
+}
+
+/// dashed lines between the atoms, with an arc at the angle, or around the axis of a dihedral
+fn draw_measurement(gizmos: &mut Gizmos, positions: &[Vec3]) {
+    for line in positions.windows(2) {
+        draw_dashed_line(gizmos, line[0], line[1]);
+    }
+    let radius = MEASUREMENT_ARC_RADIUS;
+    match *positions {
+        [a, b, c] => {
+            let from = b + (a - b).normalize_or_zero() * radius;
+            let to = b + (c - b).normalize_or_zero() * radius;
+            gizmos.short_arc_3d_between(b, from, to, MEASUREMENT_COLOR);
+        }
+        [a, b, c, d] => {
+            // the outer atoms projected on the plane perpendicular to the axis, at its middle
+            let axis = (c - b).normalize_or_zero();
+            let perpendicular = |v: Vec3| (v - axis * v.dot(axis)).normalize_or_zero();
+            let center = (b + c) / 2.;
+            let from = center + perpendicular(a - b) * radius;
+            let to = center + perpendicular(d - c) * radius;
+            gizmos.short_arc_3d_between(center, from, to, MEASUREMENT_COLOR);
+        }
+        _ => {}
+    }
+}
+
+fn draw_dashed_line(gizmos: &mut Gizmos, start: Vec3, end: Vec3) {
+    let dashes = (start.distance(end) / (2. * MEASUREMENT_DASH))
+        .ceil()
+        .max(1.);
+    let step = (end - start) / (2. * dashes);
+    for i in 0..dashes as usize {
+        let dash_start = start + step * (2 * i) as f32;
+        gizmos.line(dash_start, dash_start + step, MEASUREMENT_COLOR);
+    }
+}
+
+/// tints the selected atoms and the bonds between them, when the selection or the drawn entities change
+#[allow(clippy::type_complexity)]
+pub fn update_selection_highlight(
+    selection: Res<Selection>,
+    entities: Res<MolEntities>,
+    mols: Res<Assets<Molecule>>,
+    mut picks: Query<&mut PickSelection>,
+    mut impostor_atoms: Query<(&ImpostorAtoms, &mut Impostors)>,
+    mut impostor_bonds: Query<(&ImpostorBonds, &mut Impostors), Without<ImpostorAtoms>>,
+) {
+    if !selection.is_changed() && !entities.is_changed() {
+        return;
+    }
+    let mut set_selected = |entity: Entity, selected: bool| {
+        if let Ok(mut pick) = picks.get_mut(entity) {
+            if pick.is_selected != selected {
+                pick.is_selected = selected;
+            }
+        }
+    };
+    for (mol_id, map) in &entities.mols {
+        for (index, entity) in &map.atoms {
+            let atom = AtomRef {
+                mol: *mol_id,
+                index: *index,
+            };
+            set_selected(*entity, selection.contains(&atom));
+        }
+        let Some(mol) = mols.get(*mol_id) else {
+            continue;
+        };
+        for (index, lines) in &map.bonds {
+            let selected = mol
+                .bonds
+                .get(*index)
+                .is_some_and(|bond| selection.contains_bond(*mol_id, bond));
+            for entity in lines {
+                set_selected(*entity, selected);
+            }
+        }
+    }
+
+    for (atoms, mut impostors) in &mut impostor_atoms {
+        let selected: HashSet<usize> = (0..atoms.atoms.len())
+            .filter(|instance| {
+                selection.contains(&AtomRef {
+                    mol: atoms.mol,
+                    index: atoms.atoms[*instance],
+                })
+            })
+            .collect();
+        if impostors.selected != selected {
+            impostors.selected = selected;
+        }
+    }
+    for (bonds, mut impostors) in &mut impostor_bonds {
+        let Some(mol) = mols.get(bonds.mol) else {
+            continue;
+        };
+        let selected: HashSet<usize> = (0..bonds.lines.len())
+            .filter(|instance| {
+                mol.bonds
+                    .get(bonds.lines[*instance].index)
+                    .is_some_and(|bond| selection.contains_bond(bonds.mol, bond))
+            })
+            .collect();
+        if impostors.selected != selected {
+            impostors.selected = selected;
+        }
+    }
+}
+
+/// switches the meshes of the atoms and bonds by their size on screen, when the camera or the molecule moves
+#[allow(clippy::type_complexity)]
+pub fn update_level_of_detail(
+    scene: Res<MolScene>,
+    assets: Res<PreloadedAssets>,
+    cameras: Query<(&Camera, Ref<GlobalTransform>)>,
+    moved: Query<(), (Changed<GlobalTransform>, With<MyMolecule>)>,
+    added: Query<(), Or<(Added<MyAtom>, Added<MyBond>)>>,
+    mut items: Query<(&mut Lod, &mut Handle<Mesh>, &GlobalTransform)>,
+) {
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    if !camera_transform.is_changed() && moved.is_empty() && added.is_empty() && !scene.is_changed()
+    {
+        return;
+    }
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return;
+    };
+    let clip_from_world = camera.clip_from_view() * camera_transform.compute_matrix().inverse();
+
+    for (mut lod, mut mesh, transform) in &mut items {
+        let level = if scene.lod.enabled {
+            let radius = lod.radius * transform.affine().matrix3.x_axis.length();
+            let pixels = pixel_radius(
+                &clip_from_world,
+                viewport_size.y,
+                transform.translation(),
+                radius,
+            );
+            lod_level(pixels, lod.min_level)
+        } else {
+            0
+        };
+        if lod.level != level {
+            lod.level = level;
+            *mesh = assets.lod_mesh(lod.mesh, level);
+        }
+    }
+}
+
+/// adds the cartoon of the protein backbone of the molecule, returns the residues in it (indices in `Molecule::residues`)
+fn add_cartoon(
+    commands: &mut Commands,
+    mol: &Molecule,
+    cartoon_color: &CartoonColor,
+    parent: Entity,
+    assets: &PreloadedAssets,
+    meshes: &mut Assets<Mesh>,
+) -> HashSet<usize> {
+    let backbone = backbone(mol);
+    if backbone.is_empty() {
+        return HashSet::new();
+    }
+    let position = |atom: usize| mol.atoms[atom].position;
+    let structure = assign_secondary_structure(&backbone, position);
+    let colors = cartoon_colors(mol, &backbone, &structure, cartoon_color);
+    let mesh = cartoon_mesh(&backbone, &structure, &colors, position);
+    let residues = backbone.iter().map(|r| r.residue).collect();
+
+    let entity = commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(mesh),
+                material: assets.cartoon_mat.clone(),
+                ..default()
+            },
+            MyCartoon {
+                backbone,
+                structure,
+                colors,
+            },
+        ))
+        .id();
+    commands.entity(parent).add_child(entity);
+    residues
+}
+
+/// sets the opacity of the surface style on the surface material
+pub fn update_surface_opacity(
+    scene: Res<MolScene>,
+    assets: Res<PreloadedAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !scene.is_changed() {
+        return;
+    }
+    let opacity = scene.surface.opacity;
+    let current = materials
+        .get(&assets.surface_mat)
+        .map(|m| m.base_color.alpha());
+    if current.is_some_and(|alpha| alpha != opacity) {
+        materials.insert(&assets.surface_mat, surface_material(opacity));
+    }
+}
+
+/// adds the surface of the molecule, computed in the background as it's expensive (see `finish_surfaces`).
+/// it's not updated when playing a trajectory
+fn add_surface(
+    commands: &mut Commands,
+    mol_id: AssetId<Molecule>,
+    mol: &Molecule,
+    surface: &SurfaceStyle,
+    parent: Entity,
+) {
+    let atoms = mol.atoms.clone();
+    let (kind, probe_radius, resolution) = (surface.kind, surface.probe_radius, surface.resolution);
+    let task = AsyncComputeTaskPool::get()
+        .spawn(async move { compute_surface(&atoms, kind, probe_radius, resolution) });
+    let entity = commands
+        .spawn((
+            SpatialBundle::default(),
+            MySurface {
+                mol: mol_id,
+                nearest_atoms: vec![],
+                color: surface.color,
+            },
+            SurfaceTask(task),
+        ))
+        .id();
+    commands.entity(parent).add_child(entity);
+}
+
+/// adds the meshes of the computed surfaces.
+/// a surface whose molecule is rebuilt meanwhile is despawned, which cancels its task
+pub fn finish_surfaces(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut SurfaceTask, &mut MySurface)>,
+    scene: Res<MolScene>,
+    mols: Res<Assets<Molecule>>,
+    assets: Res<PreloadedAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (entity, mut task, mut surface) in &mut tasks {
+        let Some(geometry) = block_on(poll_once(&mut task.0)) else {
+            continue;
+        };
+        commands.entity(entity).remove::<SurfaceTask>();
+        let Some(mol) = mols.get(surface.mol) else {
+            continue;
+        };
+        let colors = surface_colors(mol, &geometry.nearest_atoms, &scene.surface.color);
+        surface.color = scene.surface.color;
+        surface.nearest_atoms = geometry.nearest_atoms.clone();
+        commands.entity(entity).insert((
+            meshes.add(surface_mesh(geometry, colors)),
+            assets.surface_mat.clone(),
+        ));
+    }
+}
+
+/// sets the vertex colors of the surfaces when the surface coloring changes, without computing them again
+pub fn update_surface_colors(
+    scene: Res<MolScene>,
+    mut surfaces: Query<(&mut MySurface, &Handle<Mesh>)>,
+    mols: Res<Assets<Molecule>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if !scene.is_changed() {
+        return;
+    }
+    for (mut surface, mesh) in &mut surfaces {
+        if surface.color == scene.surface.color {
+            continue;
+        }
+        let (Some(mol), Some(mesh)) = (mols.get(surface.mol), meshes.get_mut(mesh)) else {
+            continue;
+        };
+        let colors = surface_colors(mol, &surface.nearest_atoms, &scene.surface.color);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        surface.color = scene.surface.color;
+    }
+}
+
 pub fn clear(commands: &mut Commands, mol_query: &Query<Entity, With<MyMolecule>>) {
     despawn_all_entities(commands, mol_query);
 }
 
 /// each element has a unique color / material
 pub fn atom_material(
-    materials: &mut ResMut<Assets<StandardMaterial>>,
+    materials: &mut Assets<StandardMaterial>,
     element: Element,
 ) -> Handle<StandardMaterial> {
     let color = color_for_element(&element);
@@ -324,35 +1323,73 @@ pub fn atom_material(
     materials.add(material)
 }
 
-pub fn atom_mesh(meshes: &mut ResMut<Assets<Mesh>>) -> Handle<Mesh> {
-    meshes.add(Sphere { ..default() }.mesh().uv(SPHERE_LAT, SPHERE_LON))
+/// per level of detail
+pub fn atom_meshes(meshes: &mut ResMut<Assets<Mesh>>) -> [Handle<Mesh>; LOD_LEVELS] {
+    SPHERE_RESOLUTIONS
+        .map(|(sectors, stacks)| meshes.add(Sphere { ..default() }.mesh().uv(sectors, stacks)))
 }
 
 pub fn bond_material(materials: &mut ResMut<Assets<StandardMaterial>>) -> Handle<StandardMaterial> {
     materials.add(StandardMaterial {
-        base_color: Srgba::new(0.4, 0.4, 0.4, 1.0).into(),
+        base_color: BOND_COLOR.into(),
         ..default()
     })
 }
 
-pub fn bond_cylinder_mesh(meshes: &mut ResMut<Assets<Mesh>>, radius: f32) -> Handle<Mesh> {
-    meshes.add(
-        Cylinder {
-            radius,
-            half_height: 0.5,
-        }
-        .mesh(),
-    )
+pub fn amide_bond_material(
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) -> Handle<StandardMaterial> {
+    materials.add(StandardMaterial {
+        base_color: AMIDE_BOND_COLOR.into(),
+        ..default()
+    })
 }
 
-pub fn bond_capsule_mesh(meshes: &mut ResMut<Assets<Mesh>>, radius: f32) -> Handle<Mesh> {
-    meshes.add(
-        Capsule3d {
-            radius,
-            half_length: 0.5,
-        }
-        .mesh(),
-    )
+pub fn cartoon_material(
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) -> Handle<StandardMaterial> {
+    materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        perceptual_roughness: 0.6,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    })
+}
+
+/// per level of detail
+pub fn bond_cylinder_meshes(
+    meshes: &mut ResMut<Assets<Mesh>>,
+    radius: f32,
+) -> [Handle<Mesh>; LOD_LEVELS] {
+    CYLINDER_RESOLUTIONS.map(|resolution| {
+        meshes.add(
+            Cylinder {
+                radius,
+                half_height: 0.5,
+            }
+            .mesh()
+            .resolution(resolution),
+        )
+    })
+}
+
+/// per level of detail
+pub fn bond_capsule_meshes(
+    meshes: &mut ResMut<Assets<Mesh>>,
+    radius: f32,
+) -> [Handle<Mesh>; LOD_LEVELS] {
+    CAPSULE_RESOLUTIONS.map(|(longitudes, latitudes)| {
+        meshes.add(
+            Capsule3d {
+                radius,
+                half_length: 0.5,
+            }
+            .mesh()
+            .longitudes(longitudes)
+            .latitudes(latitudes),
+        )
+    })
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -361,76 +1398,253 @@ pub fn add_bond(
     material: &Handle<StandardMaterial>,
     mol_render: &MolRender,
     parent: Entity,
+    bond_ref: BondRef,
     atom1_loc: Vec3,
     atom2_loc: Vec3,
-    preloaded_assets: &Res<PreloadedAssets>,
-    bond: &Mol2Bond,
-) {
-    let length = atom1_loc.distance(atom2_loc);
+    side: BondSide,
+    preloaded_assets: &PreloadedAssets,
+    min_lod_level: usize,
+    bond: &Bond,
+    atom_position: impl Fn(usize) -> Vec3,
+) -> Vec<Entity> {
+    let bond_coords = bond_coords(
+        bond,
+        atom1_loc,
+        atom2_loc,
+        side.position(atom_position),
+        side.is_ring(),
+    );
+
+    let bond_diam = bond_diameter(bond);
+    let lod_mesh = bond_lod_mesh(mol_render, &bond_diam);
+    let mesh = preloaded_assets.lod_mesh(lod_mesh, min_lod_level);
+
+    let mut entities = vec![];
+    for (line, bond_coord) in bond_coords.into_iter().enumerate() {
+        let mut bond = create_bond(material, bond_coord.start, bond_coord.end, &mesh);
+        bond.visibility = bond_visibility(mol_render);
+        let entity = commands
+            .spawn((
+                bond,
+                MyBond {
+                    length: bond_coord.start.distance(bond_coord.end),
+                    index: bond_ref.index,
+                    line,
+                    side: side.clone(),
+                },
+                Lod::new(lod_mesh, bond_diam.radius(), min_lod_level),
+                // tinted when selected
+                PickableBundle::default(),
+                HIGHLIGHT_TINT.clone(),
+                // shown in the inspector
+                On::<Pointer<Over>>::run(move |mut hovered: ResMut<Hovered>| {
+                    hovered.0 = Some(Inspected::Bond(bond_ref));
+                }),
+                On::<Pointer<Out>>::run(move |mut hovered: ResMut<Hovered>| {
+                    hovered.leave(Inspected::Bond(bond_ref));
+                }),
+            ))
+            .id();
+        commands.entity(parent).add_child(entity);
+        entities.push(entity);
+    }
+    entities
+}
+
+/// the bond mesh for the style, capsules for sticks so the corners look smooth
+fn bond_lod_mesh(mol_render: &MolRender, bond_diam: &BondDiameter) -> LodMesh {
+    match (mol_render, bond_diam) {
+        (MolRender::BallStick, BondDiameter::Regular) => LodMesh::BondCylinder,
+        (MolRender::BallStick, BondDiameter::Small) => LodMesh::SmallBondCylinder,
+        (MolRender::Stick, BondDiameter::Regular) => LodMesh::BondCapsule,
+        (MolRender::Stick, BondDiameter::Small) => LodMesh::SmallBondCapsule,
+        (MolRender::Cartoon | MolRender::Surface, BondDiameter::Regular) => LodMesh::BondCylinder,
+        (MolRender::Cartoon | MolRender::Surface, BondDiameter::Small) => {
+            LodMesh::SmallBondCylinder
+        }
+        // hidden, can be anything
+        (MolRender::Ball, _) => LodMesh::BondCylinder,
+    }
+}
+
+/// the atoms are hidden in the stick style
+fn atom_visibility(mol_render: &MolRender) -> Visibility {
+    match mol_render {
+        MolRender::Stick => Visibility::Hidden,
+        _ => Visibility::Inherited,
+    }
+}
+
+/// the bonds are hidden in the ball style
+fn bond_visibility(mol_render: &MolRender) -> Visibility {
+    match mol_render {
+        MolRender::Ball => Visibility::Hidden,
+        _ => Visibility::Inherited,
+    }
+}
+
+/// dashes of the inner line of aromatic bonds
+const AROMATIC_DASHES: usize = 3;
+/// dashes of dummy / unknown bonds
+pub const UNKNOWN_BOND_DASHES: usize = 5;
 
+/// largest ring whose bonds are drawn towards its center
+const MAX_DRAWN_RING_SIZE: usize = 8;
+/// minimum distance of an atom to the bond axis to define the plane of the bond lines
+const MIN_SIDE_ATOM_DISTANCE: f32 = 0.1;
+
+/// where the additional lines of double / triple / aromatic bonds are drawn: towards the center of the smallest ring,
+/// or else in the plane of the bond and the closest (in the bond graph) atom that's not collinear with it
+fn bond_side(mol: &Molecule, bonds_per_atom: &[Vec<usize>], index: usize) -> BondSide {
+    let bond = &mol.bonds[index];
+    if !matches!(
+        bond.type_,
+        BondType::Double | BondType::Triple | BondType::Aromatic
+    ) {
+        return BondSide::None;
+    }
+    if let Some(ring) = mol.smallest_ring(index, bonds_per_atom, MAX_DRAWN_RING_SIZE) {
+        return BondSide::Ring(ring);
+    }
+
+    let line = BondCoords {
+        start: mol.atoms[bond.atom1].position,
+        end: mol.atoms[bond.atom2].position,
+    };
+    let direction = (line.end - line.start).normalize_or_zero();
+    let off_axis = |atom: usize| {
+        let v = mol.atoms[atom].position - line.start;
+        (v - direction * v.dot(direction)).length() > MIN_SIDE_ATOM_DISTANCE
+    };
+    // neighbors first, then neighbors of neighbors (e.g. for chains of triple bonds, which are linear)
+    let mut visited = vec![bond.atom1, bond.atom2];
+    let mut current = visited.clone();
+    for _ in 0..2 {
+        let mut next = vec![];
+        for atom in current {
+            for b in &bonds_per_atom[atom] {
+                let b = &mol.bonds[*b];
+                let neighbor = if b.atom1 == atom { b.atom2 } else { b.atom1 };
+                if visited.contains(&neighbor) {
+                    continue;
+                }
+                if off_axis(neighbor) {
+                    return BondSide::Atom(neighbor);
+                }
+                visited.push(neighbor);
+                next.push(neighbor);
+            }
+        }
+        current = next;
+    }
+    BondSide::None
+}
+
+/// the cylinders of a bond: one per bond order, in the plane of `side`. in rings the second line of double bonds
+/// is a shorter inner line (towards `side`, the ring center), aromatic bonds have a dashed inner line,
+/// dummy / unknown bonds are dashed and not connected atoms have none
+fn bond_coords(
+    bond: &Bond,
+    atom1_loc: Vec3,
+    atom2_loc: Vec3,
+    side: Option<Vec3>,
+    ring: bool,
+) -> Vec<BondCoords> {
     // // uncomment this and comment next block to see all bonds as triple bonds (or double, adjusted)
     // let c = calculate_triple_bond_coords(
     //     &BondCoords {
     //         start: atom1_loc,
     //         end: atom2_loc,
     //     },
-    //     0.1,
+    //     Vec3::X * 0.1,
     // );
     // let bond_coords = vec![c.bond1, c.bond2, c.bond3];
 
-    let bond_coords = match bond.type_.as_ref() {
-        "2" => {
-            let c = calculate_double_bond_coords(
-                &BondCoords {
-                    start: atom1_loc,
-                    end: atom2_loc,
-                },
-                0.08,
-            );
+    let line = BondCoords {
+        start: atom1_loc,
+        end: atom2_loc,
+    };
+    match bond.type_ {
+        BondType::Double if ring => {
+            let inner = inner_line(&line, perpendicular(&line, side) * 0.16);
+            vec![line, inner]
+        }
+        BondType::Double => {
+            let c = calculate_double_bond_coords(&line, perpendicular(&line, side) * 0.08);
             vec![c.bond1, c.bond2]
         }
-        "3" => {
-            let c = calculate_triple_bond_coords(
-                &BondCoords {
-                    start: atom1_loc,
-                    end: atom2_loc,
-                },
-                0.1,
-            );
+        BondType::Triple => {
+            let c = calculate_triple_bond_coords(&line, perpendicular(&line, side) * 0.1);
             vec![c.bond1, c.bond2, c.bond3]
         }
-        "1" | _ => vec![BondCoords {
-            start: atom1_loc,
-            end: atom2_loc,
-        }],
-    };
-
-    let bond_diam = match bond_coords.len() {
-        2 | 3 => BondDiameter::Small,
-        _ => BondDiameter::Regular,
-    };
+        BondType::Aromatic => {
+            let inner = inner_line(&line, perpendicular(&line, side) * 0.16);
+            let mut coords = vec![line];
+            coords.extend(dashed_coords(&inner, AROMATIC_DASHES));
+            coords
+        }
+        BondType::Dummy | BondType::Unknown => dashed_coords(&line, UNKNOWN_BOND_DASHES),
+        BondType::NotConnected => vec![],
+        BondType::Single | BondType::Amide => vec![line],
+    }
+}
 
-    let mesh = match (mol_render, bond_diam) {
-        (MolRender::BallStick, BondDiameter::Regular) => preloaded_assets.bond_cyl_mesh.clone(),
-        (MolRender::BallStick, BondDiameter::Small) => preloaded_assets.bond_small_cyl_mesh.clone(),
-        (MolRender::Stick, BondDiameter::Regular) => preloaded_assets.bond_caps_mesh.clone(),
-        (MolRender::Stick, BondDiameter::Small) => preloaded_assets.bond_small_caps_mesh.clone(),
-        // not used, can be anything
-        (MolRender::Ball, _) => preloaded_assets.bond_cyl_mesh.clone(),
-    };
+/// unit vector perpendicular to the line, towards `side` if it's not on the line
+fn perpendicular(line: &BondCoords, side: Option<Vec3>) -> Vec3 {
+    let direction = (line.end - line.start).normalize_or_zero();
+    side.map(|side| {
+        let to_side = side - line.start;
+        (to_side - direction * to_side.dot(direction)).normalize_or_zero()
+    })
+    .filter(|p| *p != Vec3::ZERO)
+    .unwrap_or_else(|| direction.any_orthonormal_vector())
+}
 
-    for bond_coord in bond_coords {
-        let bond = create_bond(material, bond_coord.start, bond_coord.end, &mesh);
-        let entity = commands.spawn((bond, MyBond { length })).id();
-        commands.entity(parent).add_child(entity);
+/// the line moved by `offset` and shortened at both ends, as inner lines are usually drawn
+fn inner_line(line: &BondCoords, offset: Vec3) -> BondCoords {
+    let v = line.end - line.start;
+    BondCoords {
+        start: line.start + v * 0.2 + offset,
+        end: line.end - v * 0.2 + offset,
     }
 }
 
+/// the line split in `count` dashes, with gaps as long as the dashes
+fn dashed_coords(line: &BondCoords, count: usize) -> Vec<BondCoords> {
+    let step = (line.end - line.start) / (2 * count - 1) as f32;
+    (0..count)
+        .map(|i| {
+            let start = line.start + step * (2 * i) as f32;
+            BondCoords {
+                start,
+                end: start + step,
+            }
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 enum BondDiameter {
     Regular,
     Small,
 }
+
+impl BondDiameter {
+    fn radius(&self) -> f32 {
+        match self {
+            BondDiameter::Regular => BOND_RADIUS,
+            BondDiameter::Small => SMALL_BOND_RADIUS,
+        }
+    }
+}
+
+/// the lines of double / triple / aromatic bonds and dashes are thinner
+fn bond_diameter(bond: &Bond) -> BondDiameter {
+    match bond.type_ {
+        BondType::Single | BondType::Amide => BondDiameter::Regular,
+        _ => BondDiameter::Small,
+    }
+}
 /// Represents location of a bond, start and end are atom (center) positions
 #[derive(Debug)]
 struct BondCoords {
@@ -451,41 +1665,23 @@ struct TripleBondCoords {
     bond3: BondCoords,
 }
 
-fn calculate_double_bond_coords(line: &BondCoords, distance: f32) -> DoubleBondCoords {
-    let v = line.end - line.start;
-
-    // choose arbitrary vector that is not parallel to v - axis vectors here for brevity
-    let not_parallel_unit_vector = if v.dot(Vec3::X).abs() < 0.99 {
-        Vec3::X
-    } else if v.dot(Vec3::Y).abs() < 0.99 {
-        Vec3::Y
-    } else {
-        Vec3::Z
-    };
-
-    // cross product of v and arbitrary vector above is a vector perpendicular to v
-    // this is the direction across which we want to position the bond's start
-    let u = v.cross(not_parallel_unit_vector).normalize();
-
-    // multiply direction unit vector by distance to get actual start
-    let point1 = u * distance;
-    // point 2, that is the start of the other bond same thing in the opposite direction
-    let point2 = -u * distance;
-
+/// the line moved by `offset` in both directions
+fn calculate_double_bond_coords(line: &BondCoords, offset: Vec3) -> DoubleBondCoords {
     DoubleBondCoords {
         bond1: BondCoords {
-            start: line.start + point1,
-            end: line.end + point1,
+            start: line.start + offset,
+            end: line.end + offset,
         },
         bond2: BondCoords {
-            start: line.start + point2,
-            end: line.end + point2,
+            start: line.start - offset,
+            end: line.end - offset,
         },
     }
 }
 
-fn calculate_triple_bond_coords(line: &BondCoords, distance: f32) -> TripleBondCoords {
-    let double_bond_coords = calculate_double_bond_coords(line, distance);
+/// the line and the line moved by `offset` in both directions
+fn calculate_triple_bond_coords(line: &BondCoords, offset: Vec3) -> TripleBondCoords {
+    let double_bond_coords = calculate_double_bond_coords(line, offset);
     TripleBondCoords {
         bond1: double_bond_coords.bond1,
         bond2: double_bond_coords.bond2,
@@ -503,8 +1699,8 @@ pub fn update_bond_length(
     scene: ResMut<MolScene>,
     mut bond_query: Query<(&mut Transform, &MyBond), With<MyBond>>,
 ) {
-    // Only BallStick uses cylinders (instead of Capsule3d or nothing)
-    if scene.render == MolRender::BallStick || scene.render == MolRender::Stick {
+    // Ball has no bonds, the others use cylinders or capsules
+    if scene.render != MolRender::Ball {
         for (mut transform, bond) in bond_query.iter_mut() {
             let length = if scene.render == MolRender::Stick {
                 // shorten a bit for corners to look smooth
@@ -523,34 +1719,31 @@ fn create_bond(
     p2: Vec3,
     mesh: &Handle<Mesh>,
 ) -> PbrBundle {
+    PbrBundle {
+        mesh: mesh.clone(),
+        material: material.clone(),
+        transform: bond_transform(p1, p2),
+        ..default()
+    }
+}
+
+/// places the (y-axis) bond cylinder between the points. the length is set by `update_bond_length`
+fn bond_transform(p1: Vec3, p2: Vec3) -> Transform {
     let midpoint = (p1 + p2) / 2.0;
 
     let direction = (p2 - p1).normalize();
     let rotation = Quat::from_rotation_arc(Vec3::Y, direction);
 
-    PbrBundle {
-        mesh: mesh.clone(),
-        material: material.clone(),
-        transform: Transform {
-            translation: midpoint,
-            rotation,
-            ..default()
-        },
+    Transform {
+        translation: midpoint,
+        rotation,
         ..default()
     }
 }
 
 fn color_for_element(element: &Element) -> Srgba {
-    match element {
-        Element::H => WHITE,
-        Element::C => BLACK,
-        Element::N => GREEN,
-        Element::O => RED,
-        Element::F => MAGENTA,
-        Element::P => ORANGE,
-        Element::S => YELLOW,
-        Element::Ca => LIGHT_CYAN,
-    }
+    let [r, g, b] = element.color();
+    Srgba::rgb_u8(r, g, b)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -559,56 +1752,54 @@ pub fn add_atom(
     mol_style: &MolStyle,
     mol_render: &MolRender,
     parent: Entity,
+    index: usize,
     position: Vec3,
     element: &Element,
-    description: &str,
+    mol_id: AssetId<Molecule>,
     material: &Handle<StandardMaterial>,
-    mesh: &Handle<Mesh>,
-) {
-    let pbr_bundle = sphere_pbr_bundle(
+    assets: &PreloadedAssets,
+    min_lod_level: usize,
+) -> Entity {
+    let mut pbr_bundle = sphere_pbr_bundle(
         position,
         sphere_scale(mol_render, mol_style, element),
         material,
-        mesh,
+        &assets.lod_mesh(LodMesh::Atom, min_lod_level),
     );
-    let descr = description.to_string();
+    pbr_bundle.visibility = atom_visibility(mol_render);
+    let inspected = Inspected::Atom(AtomRef { mol: mol_id, index });
 
     let sphere = (
         pbr_bundle,
         PickableBundle::default(),
-        On::<Pointer<Over>>::commands_mut(move |over, commands| {
-            add_tooltip(commands, over.pointer_location.position, descr.clone());
+        // shown in the inspector
+        On::<Pointer<Over>>::run(move |mut hovered: ResMut<Hovered>| {
+            hovered.0 = Some(inspected);
+        }),
+        On::<Pointer<Out>>::run(move |mut hovered: ResMut<Hovered>| {
+            hovered.leave(inspected);
         }),
-        On::<Pointer<Out>>::run(
-            |mut commands: Commands, tooltips_query: Query<Entity, With<TooltipMarker>>| {
-                despawn_all_entities(&mut commands, &tooltips_query);
-            },
-        ),
         HIGHLIGHT_TINT.clone(),
         Shape,
+        MyAtom { index },
+        // the sphere mesh has a radius of 0.5
+        Lod::new(LodMesh::Atom, 0.5, min_lod_level),
     );
 
     let entity = commands.spawn(sphere).id();
     commands.entity(parent).add_child(entity);
+    entity
 }
 
 fn sphere_scale(mol_render: &MolRender, mol_style: &MolStyle, element: &Element) -> f32 {
     let basic_scale = match mol_render {
         MolRender::BallStick => mol_style.atom_scale_ball_stick,
         MolRender::Ball => mol_style.atom_scale_ball,
-        MolRender::Stick => mol_style.atom_scale_ball_stick, // sphere not added to scene - arbitrary
+        MolRender::Stick => mol_style.atom_scale_ball_stick, // sphere hidden - arbitrary
+        MolRender::Cartoon | MolRender::Surface => mol_style.atom_scale_ball_stick,
     };
 
-    let van_der_waals_radius = match element {
-        Element::H => 1.2,
-        Element::C => 1.7,
-        Element::N => 1.55,
-        Element::O => 1.52,
-        Element::F => 1.47,
-        Element::P => 1.8,
-        Element::S => 1.8,
-        Element::Ca => 2.31,
-    };
+    let van_der_waals_radius = element.van_der_waals_radius();
     let van_der_waals_scaling_factor = 1.;
 
     basic_scale * van_der_waals_radius * van_der_waals_scaling_factor
@@ -622,3 +1813,240 @@ pub fn setup_molecule(mut commands: Commands) {
 pub fn trigger_init_scene_event(mut event: EventWriter<UpdateSceneEvent>) {
     event.send(UpdateSceneEvent);
 }
+
+/// frame count of the shown trajectory: the frames of the attached trajectory if it matches the molecule,
+/// otherwise the frames of the molecule. a trajectory that can't be played over the shown molecule is removed,
+/// with the reason shown as load error
+pub fn update_frame_count(
+    mut playback: ResMut<Playback>,
+    mut scene: ResMut<MolScene>,
+    assets: Res<Assets<Molecule>>,
+    collections: Res<Assets<MolCollection>>,
+    trajectories: Res<Assets<Trajectory>>,
+) {
+    let trajectory = scene.trajectory.as_ref().and_then(|h| trajectories.get(h));
+    let mols = scene.content.molecules(&assets, &collections);
+    let frame_count = match (&mols[..], trajectory) {
+        ([mol], Some(trajectory)) if trajectory.atom_count == mol.atoms.len() => {
+            trajectory.frame_count()
+        }
+        ([mol], Some(trajectory)) => {
+            let message = format!(
+                "The trajectory has {} atoms, the molecule {}",
+                trajectory.atom_count,
+                mol.atoms.len()
+            );
+            eprintln!("{}", message);
+            scene.open_error = Some(message);
+            scene.trajectory = None;
+            mol.frame_count()
+        }
+        // overlaid records of a collection
+        ([_, _, ..], Some(_)) => {
+            let message = "A trajectory can't be played over overlaid records".to_string();
+            eprintln!("{}", message);
+            scene.open_error = Some(message);
+            scene.trajectory = None;
+            1
+        }
+        _ => scene.content.frame_count(&assets, &collections),
+    };
+    if playback.frame_count != frame_count {
+        playback.frame_count = frame_count;
+        playback.frame = playback.frame.min(frame_count - 1);
+    }
+}
+
+/// advances the frame of the shown trajectory with the playback fps
+pub fn advance_playback(time: Res<Time>, mut playback: ResMut<Playback>) {
+    if !playback.playing {
+        return;
+    }
+    let frame_count = playback.frame_count;
+    if frame_count < 2 {
+        playback.playing = false;
+        return;
+    }
+
+    let frame_duration = 1.0 / playback.fps as f32;
+    let mut elapsed = playback.elapsed + time.delta_seconds();
+    let mut frame = playback.frame;
+    let mut playing = true;
+    while elapsed >= frame_duration {
+        elapsed -= frame_duration;
+        if frame + 1 < frame_count {
+            frame += 1;
+        } else if playback.looping {
+            frame = 0;
+        } else {
+            playing = false;
+            elapsed = 0.0;
+            break;
+        }
+    }
+
+    if frame != playback.frame || !playing {
+        playback.frame = frame;
+        playback.playing = playing;
+        playback.elapsed = elapsed;
+    } else {
+        // only the time changed, nothing to react to
+        playback.bypass_change_detection().elapsed = elapsed;
+    }
+}
+
+/// reads the current frame of the attached trajectory from its file, in the background.
+/// the playback doesn't wait for it: if it advanced meanwhile, the then current frame is read next
+pub fn stream_trajectory_frames(
+    scene: Res<MolScene>,
+    trajectories: Res<Assets<Trajectory>>,
+    asset_server: Res<AssetServer>,
+    mut playback: ResMut<Playback>,
+    mut stream: ResMut<TrajectoryStream>,
+) {
+    let trajectory_id = scene.trajectory.as_ref().map(|h| h.id());
+    if stream.trajectory != trajectory_id {
+        *stream = TrajectoryStream {
+            trajectory: trajectory_id,
+            ..default()
+        };
+        playback.frame = 0;
+    }
+    let Some(trajectory) = scene.trajectory.as_ref().and_then(|h| trajectories.get(h)) else {
+        return;
+    };
+
+    // polling isn't a change, only a read frame is
+    if let Some(task) = &mut stream.bypass_change_detection().task {
+        if let Some(result) = block_on(poll_once(task)) {
+            stream.bypass_change_detection().task = None;
+            match result {
+                Ok(frame) => stream.frame = Some(frame),
+                Err(e) => {
+                    // not requested again until the frame changes
+                    eprintln!("Couldn't read trajectory frame: {}", e);
+                    playback.playing = false;
+                }
+            }
+        }
+    }
+
+    if stream.task.is_none() && stream.requested != Some(playback.frame) {
+        if let Some(range) = trajectory.frames.get(playback.frame) {
+            let future = load_frame(
+                asset_server.clone(),
+                trajectory.path.clone(),
+                trajectory.format,
+                trajectory.atom_count,
+                range.clone(),
+            );
+            let stream = stream.bypass_change_detection();
+            stream.requested = Some(playback.frame);
+            stream.task = Some(IoTaskPool::get().spawn(future));
+        }
+    }
+}
+
+/// moves the atoms, bonds, impostors and cartoon of the shown trajectory to the positions of the current frame.
+/// the entities are updated in place, instead of rebuilding the scene
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn apply_playback_frame(
+    playback: Res<Playback>,
+    scene: Res<MolScene>,
+    stream: Res<TrajectoryStream>,
+    assets: Res<Assets<Molecule>>,
+    collections: Res<Assets<MolCollection>>,
+    added: Query<
+        (),
+        Or<(
+            Added<Lod>,
+            Added<MyCartoon>,
+            Added<ImpostorAtoms>,
+            Added<ImpostorBonds>,
+        )>,
+    >,
+    mut atoms: Query<(&MyAtom, &mut Transform)>,
+    mut bonds: Query<(&mut MyBond, &mut Transform), Without<MyAtom>>,
+    mut impostor_atoms: Query<(&ImpostorAtoms, &mut Impostors)>,
+    mut impostor_bonds: Query<(&ImpostorBonds, &mut Impostors), Without<ImpostorAtoms>>,
+    cartoons: Query<(&MyCartoon, &Handle<Mesh>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    // entities are added with the positions of the first frame
+    if !playback.is_changed() && !stream.is_changed() && added.is_empty() {
+        return;
+    }
+    let [mol] = scene.content.molecules(&assets, &collections)[..] else {
+        return;
+    };
+    // an attached trajectory replaces the frames of the molecule, it's shown when the frame was read
+    let positions = if scene.trajectory.is_some() {
+        stream.frame.as_ref().map(|f| &f.positions)
+    } else {
+        mol.frames.get(playback.frame.min(mol.frame_count() - 1))
+    };
+    let Some(positions) = positions.filter(|p| p.len() == mol.atoms.len()) else {
+        return;
+    };
+
+    for (atom, mut transform) in atoms.iter_mut() {
+        if let Some(position) = positions.get(atom.index) {
+            transform.translation = *position;
+        }
+    }
+    for (mut my_bond, mut transform) in bonds.iter_mut() {
+        let coords = bond_line_coords(mol, positions, my_bond.index, my_bond.line, &my_bond.side);
+        if let Some(coords) = coords {
+            let Transform {
+                translation,
+                rotation,
+                ..
+            } = bond_transform(coords.start, coords.end);
+            transform.translation = translation;
+            transform.rotation = rotation;
+            my_bond.length = coords.start.distance(coords.end);
+        }
+    }
+    for (atoms, mut impostors) in &mut impostor_atoms {
+        for (instance, atom) in impostors.instances.iter_mut().zip(&atoms.atoms) {
+            instance.start = positions[*atom];
+            instance.end = positions[*atom];
+        }
+    }
+    for (bonds, mut impostors) in &mut impostor_bonds {
+        for (instance, line) in impostors.instances.iter_mut().zip(&bonds.lines) {
+            if let Some(coords) =
+                bond_line_coords(mol, positions, line.index, line.line, &line.side)
+            {
+                instance.start = coords.start;
+                instance.end = coords.end;
+            }
+        }
+    }
+    // the secondary structure is kept, only the geometry follows the frame
+    for (cartoon, mesh) in &cartoons {
+        let MyCartoon {
+            backbone,
+            structure,
+            colors,
+        } = cartoon;
+        let mesh_for_frame = cartoon_mesh(backbone, structure, colors, |atom| positions[atom]);
+        meshes.insert(mesh, mesh_for_frame);
+    }
+}
+
+/// the coordinates of a line of a bond, for the given atom positions
+fn bond_line_coords(
+    mol: &Molecule,
+    positions: &[Vec3],
+    index: usize,
+    line: usize,
+    side: &BondSide,
+) -> Option<BondCoords> {
+    let bond = mol.bonds.get(index)?;
+    let (start, end) = (positions[bond.atom1], positions[bond.atom2]);
+    let side_position = side.position(|atom| positions[atom]);
+    bond_coords(bond, start, end, side_position, side.is_ring())
+        .into_iter()
+        .nth(line)
+}
@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use bevy::app::{App, Plugin};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetApp, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::math::Vec3;
+
+use crate::element::Element;
+use crate::mol2_asset_plugin::parse_element;
+use crate::mol_collection::MolCollection;
+use crate::molecule::{Atom, Bond, BondType, Molecule};
+use crate::pdb_asset_plugin::{column, parse_column};
+
+/// loads sdf files and mdl molfiles (V2000 and V3000).
+/// every record of the file is added as a labeled `Molecule` ("Record0", "Record1", ...)
+pub struct SdfAssetPlugin;
+
+impl Plugin for SdfAssetPlugin {
+    fn build(&self, app: &mut App) {
+        // the collection asset is initialized by `Mol2AssetPlugin`
+        app.register_asset_loader(SdfAssetLoader);
+    }
+}
+
+pub struct SdfAssetLoader;
+
+impl AssetLoader for SdfAssetLoader {
+    type Asset = MolCollection;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<MolCollection, Self::Error> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8(bytes)?;
+
+        let records = parse_sdf(&text)?;
+        println!("finished parsing sdf file: records: {}", records.len());
+
+        let molecules = records
+            .into_iter()
+            .enumerate()
+            .map(|(index, mol)| load_context.add_labeled_asset(format!("Record{}", index), mol))
+            .collect();
+        Ok(MolCollection { molecules })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sdf", "sd", "mol"]
+    }
+}
+
+/// parses all the records of a sdf file. a molfile is handled as a sdf with a single record
+pub fn parse_sdf(text: &str) -> Result<Vec<Molecule>> {
+    let mut molecules = vec![];
+    let mut record = vec![];
+
+    for line in text.lines() {
+        if line.trim_end() == "$$$$" {
+            molecules.push(
+                parse_record(&record)
+                    .map_err(|e| anyhow!("record {}: {}", molecules.len() + 1, e))?,
+            );
+            record.clear();
+        } else {
+            record.push(line);
+        }
+    }
+    // molfile, or sdf without terminator after the last record
+    if record.iter().any(|line| !line.trim().is_empty()) {
+        molecules.push(
+            parse_record(&record).map_err(|e| anyhow!("record {}: {}", molecules.len() + 1, e))?,
+        );
+    }
+
+    Ok(molecules)
+}
+
+fn parse_record(lines: &[&str]) -> Result<Molecule> {
+    if lines.len() < 4 {
+        return Err(anyhow!("Record too short: {} lines", lines.len()));
+    }
+    let name = lines[0].trim().to_string();
+    let counts_line = lines[3];
+
+    let ctab_lines = &lines[4..];
+    let ctab = if column(counts_line, 34, 39) == "V3000" {
+        parse_v3000_ctab(ctab_lines)?
+    } else {
+        parse_v2000_ctab(counts_line, ctab_lines)?
+    };
+
+    let properties = parse_data_items(&ctab_lines[ctab.end..]);
+
+    let atoms = ctab
+        .atoms
+        .into_iter()
+        .enumerate()
+        .map(|(index, atom)| Atom {
+            formal_charge: Some(atom.charge),
+            isotope: atom.isotope,
+            ..Atom::new(
+                index as i32 + 1,
+                format!("{}{}", atom.element.symbol(), index + 1),
+                atom.element,
+                Vec3::new(atom.x, atom.y, atom.z),
+            )
+        })
+        .collect();
+
+    Ok(Molecule {
+        name,
+        atoms,
+        bonds: ctab.bonds,
+        properties,
+        ..Default::default()
+    })
+}
+
+/// connection table of a record. `end` is the index of the first line after "M  END"
+struct Ctab {
+    atoms: Vec<CtabAtom>,
+    bonds: Vec<Bond>,
+    end: usize,
+}
+
+struct CtabAtom {
+    element: Element,
+    x: f32,
+    y: f32,
+    z: f32,
+    charge: i32,
+    isotope: Option<u32>,
+}
+
+fn parse_v2000_ctab(counts_line: &str, lines: &[&str]) -> Result<Ctab> {
+    let atom_count: usize = parse_column(counts_line, 0, 3)?;
+    let bond_count: usize = parse_column(counts_line, 3, 6)?;
+
+    if lines.len() < atom_count + bond_count {
+        return Err(anyhow!(
+            "Expected {} atoms and {} bonds, but record has only {} lines",
+            atom_count,
+            bond_count,
+            lines.len()
+        ));
+    }
+
+    let mut atoms = lines[..atom_count]
+        .iter()
+        .map(|line| parse_v2000_atom_line(line))
+        .collect::<Result<Vec<CtabAtom>>>()?;
+
+    let bonds = lines[atom_count..atom_count + bond_count]
+        .iter()
+        .map(|line| {
+            let atom1: usize = parse_column(line, 0, 3)?;
+            let atom2: usize = parse_column(line, 3, 6)?;
+            check_atom_ref(atom1, atom_count)?;
+            check_atom_ref(atom2, atom_count)?;
+            Ok(Bond {
+                atom1: atom1 - 1,
+                atom2: atom2 - 1,
+                type_: bond_type(parse_column(line, 6, 9)?),
+            })
+        })
+        .collect::<Result<Vec<Bond>>>()?;
+
+    let mut charges_reset = false;
+    let mut end = lines.len();
+    for (index, line) in lines.iter().enumerate().skip(atom_count + bond_count) {
+        if line.starts_with("M  END") {
+            end = index + 1;
+            break;
+        }
+        if line.starts_with("M  CHG") {
+            // the presence of a CHG line supersedes all the charges of the atom block
+            if !charges_reset {
+                atoms.iter_mut().for_each(|a| a.charge = 0);
+                charges_reset = true;
+            }
+            for (atom, value) in property_pairs(line)? {
+                check_atom_ref(atom, atom_count)?;
+                atoms[atom - 1].charge = value;
+            }
+        } else if line.starts_with("M  ISO") {
+            for (atom, value) in property_pairs(line)? {
+                check_atom_ref(atom, atom_count)?;
+                atoms[atom - 1].isotope = Some(value as u32);
+            }
+        }
+    }
+
+    Ok(Ctab { atoms, bonds, end })
+}
+
+fn parse_v2000_atom_line(line: &str) -> Result<CtabAtom> {
+    let (element, isotope) = parse_symbol(column(line, 31, 34))?;
+    // the mass difference column is deprecated in favor of "M  ISO", ignored here
+    let charge = match column(line, 36, 39) {
+        "1" => 3,
+        "2" => 2,
+        "3" => 1,
+        "5" => -1,
+        "6" => -2,
+        "7" => -3,
+        _ => 0,
+    };
+    Ok(CtabAtom {
+        element,
+        x: parse_column(line, 0, 10)?,
+        y: parse_column(line, 10, 20)?,
+        z: parse_column(line, 20, 30)?,
+        charge,
+        isotope,
+    })
+}
+
+/// "M  CHG  2   1  -1   4   1" -> [(1, -1), (4, 1)]
+fn property_pairs(line: &str) -> Result<Vec<(usize, i32)>> {
+    let parts: Vec<&str> = line.split_whitespace().skip(3).collect();
+    parts
+        .chunks(2)
+        .map(|pair| match pair {
+            [atom, value] => Ok((atom.parse()?, value.parse()?)),
+            _ => Err(anyhow!("Invalid property line: {}", line)),
+        })
+        .collect()
+}
+
+fn parse_v3000_ctab(lines: &[&str]) -> Result<Ctab> {
+    let mut atoms = vec![];
+    let mut bonds = vec![];
+    // V3000 atom indices don't have to be contiguous
+    let mut index_for_id: HashMap<usize, usize> = HashMap::new();
+    let mut pending_bonds = vec![];
+
+    let mut block = String::new();
+    let mut end = lines.len();
+    let mut continued = String::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        if line.starts_with("M  END") {
+            end = index + 1;
+            break;
+        }
+        let Some(content) = line.strip_prefix("M  V30 ") else {
+            continue;
+        };
+        // a trailing "-" continues the entry on the next line
+        if let Some(partial) = content.strip_suffix('-') {
+            continued.push_str(partial);
+            continue;
+        }
+        let entry = std::mem::take(&mut continued) + content;
+        let parts: Vec<&str> = entry.split_whitespace().collect();
+
+        match parts.as_slice() {
+            ["BEGIN", name, ..] => block = name.to_string(),
+            ["END", ..] => block.clear(),
+            _ if block == "ATOM" => {
+                if parts.len() < 6 {
+                    return Err(anyhow!("Invalid V3000 atom line: {}", entry));
+                }
+                let (element, mut isotope) = parse_symbol(parts[1])?;
+                let mut charge = 0;
+                for field in &parts[6..] {
+                    if let Some(value) = field.strip_prefix("CHG=") {
+                        charge = value.parse()?;
+                    } else if let Some(value) = field.strip_prefix("MASS=") {
+                        isotope = Some(value.parse()?);
+                    }
+                }
+                index_for_id.insert(parts[0].parse()?, atoms.len());
+                atoms.push(CtabAtom {
+                    element,
+                    x: parts[2].parse()?,
+                    y: parts[3].parse()?,
+                    z: parts[4].parse()?,
+                    charge,
+                    isotope,
+                });
+            }
+            _ if block == "BOND" => {
+                if parts.len() < 4 {
+                    return Err(anyhow!("Invalid V3000 bond line: {}", entry));
+                }
+                let type_: u32 = parts[1].parse()?;
+                let atom1: usize = parts[2].parse()?;
+                let atom2: usize = parts[3].parse()?;
+                pending_bonds.push((type_, atom1, atom2));
+            }
+            // other blocks (sgroups, collections, ...) are not used
+            _ => {}
+        }
+    }
+
+    for (type_, atom1, atom2) in pending_bonds {
+        let index1 = index_for_id
+            .get(&atom1)
+            .ok_or_else(|| anyhow!("Bond references unknown atom: {}", atom1))?;
+        let index2 = index_for_id
+            .get(&atom2)
+            .ok_or_else(|| anyhow!("Bond references unknown atom: {}", atom2))?;
+        bonds.push(Bond {
+            atom1: *index1,
+            atom2: *index2,
+            type_: bond_type(type_),
+        });
+    }
+
+    Ok(Ctab { atoms, bonds, end })
+}
+
+/// deuterium and tritium have their own symbols in molfiles
+fn parse_symbol(symbol: &str) -> Result<(Element, Option<u32>)> {
+    match symbol {
+        "D" => Ok((Element::H, Some(2))),
+        "T" => Ok((Element::H, Some(3))),
+        _ => Ok((parse_element(symbol)?, None)),
+    }
+}
+
+fn check_atom_ref(atom: usize, atom_count: usize) -> Result<()> {
+    if atom == 0 || atom > atom_count {
+        return Err(anyhow!(
+            "Atom reference {} out of range (atoms: {})",
+            atom,
+            atom_count
+        ));
+    }
+    Ok(())
+}
+
+fn bond_type(type_: u32) -> BondType {
+    match type_ {
+        1 => BondType::Single,
+        2 => BondType::Double,
+        3 => BondType::Triple,
+        4 => BondType::Aromatic,
+        // query bond types
+        _ => BondType::Unknown,
+    }
+}
+
+/// "> <NAME>" data items after the connection table
+fn parse_data_items(lines: &[&str]) -> Vec<(String, String)> {
+    let mut items = vec![];
+    let mut current: Option<(String, Vec<&str>)> = None;
+
+    for line in lines {
+        if line.starts_with('>') {
+            if let Some((name, value)) = current.take() {
+                items.push((name, value.join("\n")));
+            }
+            let name = match (line.find('<'), line.rfind('>')) {
+                (Some(start), Some(end)) if end > start => line[start + 1..end].to_string(),
+                _ => String::new(),
+            };
+            current = Some((name, vec![]));
+        } else if line.trim().is_empty() {
+            if let Some((name, value)) = current.take() {
+                items.push((name, value.join("\n")));
+            }
+        } else if let Some((_, value)) = current.as_mut() {
+            value.push(line);
+        }
+    }
+    if let Some((name, value)) = current.take() {
+        items.push((name, value.join("\n")));
+    }
+
+    items
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_sdf;
+    use crate::molecule::BondType;
+
+    const SDF: &str = "\
+acetate
+  test
+
+  4  3  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.5000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    2.1000    1.0500    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+    2.1000   -1.0500    0.0000 O   0  5  0  0  0  0  0  0  0  0  0  0
+  1  2  1  0
+  2  3  2  0
+  2  4  1  0
+M  CHG  1   4  -1
+M  ISO  1   1  13
+M  END
+> <ID>
+42
+
+> <COMMENT>
+line 1
+line 2
+
+$$$$
+water
+  test
+
+  0  0  0     0  0            999 V3000
+M  V30 BEGIN CTAB
+M  V30 COUNTS 3 2 0 0 0
+M  V30 BEGIN ATOM
+M  V30 1 O 0.0 0.0 0.0 0
+M  V30 2 D 0.96 0.0 0.0 0
+M  V30 5 H -0.24 0.93 0.0 0 -
+M  V30 CHG=1
+M  V30 END ATOM
+M  V30 BEGIN BOND
+M  V30 1 1 1 2
+M  V30 2 1 1 5
+M  V30 END BOND
+M  V30 END CTAB
+M  END
+$$$$
+";
+
+    #[test]
+    fn test_parses_v2000_record() {
+        let records = parse_sdf(SDF).unwrap();
+        assert_eq!(records.len(), 2);
+
+        let acetate = &records[0];
+        assert_eq!(acetate.name, "acetate");
+        assert_eq!(acetate.atoms.len(), 4);
+        assert_eq!(acetate.bonds.len(), 3);
+        assert_eq!(acetate.bonds[1].type_, BondType::Double);
+        // CHG line overrides the atom block charge
+        assert_eq!(acetate.atoms[3].formal_charge, Some(-1));
+        assert_eq!(acetate.atoms[0].isotope, Some(13));
+        assert_eq!(
+            acetate.properties,
+            vec![
+                ("ID".to_string(), "42".to_string()),
+                ("COMMENT".to_string(), "line 1\nline 2".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parses_v3000_record() {
+        let records = parse_sdf(SDF).unwrap();
+
+        let water = &records[1];
+        assert_eq!(water.name, "water");
+        assert_eq!(water.atoms.len(), 3);
+        assert_eq!(water.atoms[1].isotope, Some(2));
+        assert_eq!(water.atoms[2].formal_charge, Some(1));
+        // atom id 5 is mapped to the 3rd atom
+        assert_eq!(water.bonds[1].atom2, 2);
+    }
+}
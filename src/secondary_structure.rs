@@ -0,0 +1,265 @@
+use bevy::math::Vec3;
+
+use crate::molecule::Molecule;
+
+/// maximum distance between the C of a residue and the N of the next one to be connected
+const MAX_PEPTIDE_BOND_LENGTH: f32 = 2.0;
+/// residues with CA atoms further apart can't be hydrogen bonded, skips the energy calculation
+const MAX_HBOND_CA_DISTANCE: f32 = 9.0;
+/// energy (kcal/mol) below which there's a hydrogen bond, as in DSSP
+const MAX_HBOND_ENERGY: f32 = -0.5;
+
+/// the backbone atoms of an amino acid residue, indices in `Molecule::atoms`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackboneResidue {
+    /// index in `Molecule::residues`
+    pub residue: usize,
+    pub n: usize,
+    pub ca: usize,
+    pub c: usize,
+    pub o: usize,
+    /// whether there's a peptide bond to the previous residue of the backbone
+    pub connected: bool,
+    /// proline has no backbone hydrogen, so it's not a hydrogen bond donor
+    pub proline: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondaryStructure {
+    Helix,
+    Strand,
+    Coil,
+}
+
+/// the residues with backbone atoms (N, CA, C, O), in chain order
+pub fn backbone(mol: &Molecule) -> Vec<BackboneResidue> {
+    let mut backbone: Vec<BackboneResidue> = vec![];
+    let residues = mol
+        .chains
+        .iter()
+        .flat_map(|chain| chain.residues.iter().copied())
+        .chain((0..mol.residues.len()).filter(|r| mol.residues[*r].chain.is_none()));
+    for index in residues {
+        let residue = &mol.residues[index];
+        let atom = |name: &str| {
+            residue
+                .atoms
+                .iter()
+                .copied()
+                .find(|a| mol.atoms[*a].name.trim().eq_ignore_ascii_case(name))
+        };
+        let (Some(n), Some(ca), Some(c), Some(o)) = (atom("N"), atom("CA"), atom("C"), atom("O"))
+        else {
+            continue;
+        };
+        let connected = backbone.last().is_some_and(|previous| {
+            mol.residues[previous.residue].chain == residue.chain
+                && mol.atoms[previous.c]
+                    .position
+                    .distance(mol.atoms[n].position)
+                    < MAX_PEPTIDE_BOND_LENGTH
+        });
+        backbone.push(BackboneResidue {
+            residue: index,
+            n,
+            ca,
+            c,
+            o,
+            connected,
+            proline: residue.name.eq_ignore_ascii_case("PRO"),
+        });
+    }
+    backbone
+}
+
+/// secondary structure of each backbone residue, from the backbone hydrogen bonds (a simplified DSSP):
+/// residues of two consecutive 4-turns are helix, residues of consecutive bridges are strand
+pub fn assign_secondary_structure(
+    backbone: &[BackboneResidue],
+    positions: impl Fn(usize) -> Vec3,
+) -> Vec<SecondaryStructure> {
+    let len = backbone.len();
+    let hbonds = hbonds(backbone, &positions);
+    let hbond = |acceptor: usize, donor: usize| hbonds[acceptor].contains(&donor);
+    // whether the residues from `from` to `to` (inclusive) are connected
+    let connected = |from: usize, to: usize| (from + 1..=to).all(|i| backbone[i].connected);
+
+    let mut structure = vec![SecondaryStructure::Coil; len];
+
+    // n-turn at i: hydrogen bond from the C=O of i to the N-H of i + n
+    let turn = |i: usize| i + 4 < len && connected(i, i + 4) && hbond(i, i + 4);
+    for i in 1..len {
+        if turn(i - 1) && turn(i) {
+            for s in &mut structure[i..i + 4] {
+                *s = SecondaryStructure::Helix;
+            }
+        }
+    }
+
+    // bridge between i and j: hydrogen bonds of the pairs of residues of two parallel or antiparallel strands
+    let bridge = |i: usize, j: usize| {
+        let parallel = (hbond(i - 1, j) && hbond(j, i + 1)) || (hbond(j - 1, i) && hbond(i, j + 1));
+        let antiparallel =
+            (hbond(i, j) && hbond(j, i)) || (hbond(i - 1, j + 1) && hbond(j - 1, i + 1));
+        parallel || antiparallel
+    };
+    let inner = |i: usize| i > 0 && i + 1 < len && connected(i - 1, i + 1);
+    let bridged: Vec<bool> = (0..len)
+        .map(|i| inner(i) && (0..len).any(|j| i.abs_diff(j) > 2 && inner(j) && bridge(i, j)))
+        .collect();
+    // isolated bridges aren't strands
+    for i in 0..len {
+        let neighbor_bridged = (i > 0 && bridged[i - 1] && backbone[i].connected)
+            || (i + 1 < len && bridged[i + 1] && backbone[i + 1].connected);
+        if bridged[i] && neighbor_bridged && structure[i] == SecondaryStructure::Coil {
+            structure[i] = SecondaryStructure::Strand;
+        }
+    }
+    structure
+}
+
+/// for each residue (acceptor, C=O), the residues (donors, N-H) it's hydrogen bonded to
+fn hbonds(backbone: &[BackboneResidue], positions: &impl Fn(usize) -> Vec3) -> Vec<Vec<usize>> {
+    // the hydrogens aren't in all files, they're placed opposite to the C=O of the previous residue
+    let hydrogens: Vec<Option<Vec3>> = backbone
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            (i > 0 && r.connected && !r.proline).then(|| {
+                let previous = &backbone[i - 1];
+                let n = positions(r.n);
+                n + (positions(previous.c) - positions(previous.o)).normalize_or_zero()
+            })
+        })
+        .collect();
+
+    backbone
+        .iter()
+        .enumerate()
+        .map(|(i, acceptor)| {
+            let (c, o) = (positions(acceptor.c), positions(acceptor.o));
+            let ca = positions(acceptor.ca);
+            backbone
+                .iter()
+                .enumerate()
+                .filter(|(j, donor)| {
+                    // a residue can't be bonded to itself or its neighbor
+                    if i.abs_diff(*j) < 2
+                        || ca.distance(positions(donor.ca)) > MAX_HBOND_CA_DISTANCE
+                    {
+                        return false;
+                    }
+                    hydrogens[*j].is_some_and(|h| {
+                        hbond_energy(c, o, positions(donor.n), h) < MAX_HBOND_ENERGY
+                    })
+                })
+                .map(|(j, _)| j)
+                .collect()
+        })
+        .collect()
+}
+
+/// electrostatic energy (kcal/mol) of a backbone hydrogen bond, as in DSSP
+fn hbond_energy(c: Vec3, o: Vec3, n: Vec3, h: Vec3) -> f32 {
+    0.084
+        * 332.0
+        * (1.0 / o.distance(n) + 1.0 / c.distance(h) - 1.0 / o.distance(h) - 1.0 / c.distance(n))
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::{Quat, Vec3};
+
+    use super::{assign_secondary_structure, backbone, BackboneResidue, SecondaryStructure};
+    use crate::{
+        element::Element,
+        molecule::{Atom, Molecule},
+    };
+
+    /// places `d` such that it has the given distance to `c`, angle b-c-d and dihedral a-b-c-d (in degrees)
+    fn place(a: Vec3, b: Vec3, c: Vec3, distance: f32, angle: f32, dihedral: f32) -> Vec3 {
+        let bc = (c - b).normalize();
+        let normal = (b - a).cross(bc).normalize();
+        let d = Quat::from_axis_angle(normal, (180.0 - angle).to_radians()) * bc * distance;
+        c + Quat::from_axis_angle(bc, dihedral.to_radians()) * d
+    }
+
+    /// backbone of an alanine chain with the same phi / psi angles for all the residues
+    fn chain(residues: usize, phi: f32, psi: f32) -> Molecule {
+        let mut mol = Molecule::default();
+        let (mut n, mut ca, mut c) = (
+            Vec3::ZERO,
+            Vec3::new(1.458, 0.0, 0.0),
+            Vec3::new(2.0, 1.42, 0.0),
+        );
+        for i in 0..residues {
+            let next_n = place(n, ca, c, 1.329, 116.2, psi);
+            let o = place(n, ca, c, 1.231, 120.5, psi + 180.0);
+            let residue = Some((i as i32 + 1, "ALA"));
+            for (name, element, position) in [
+                ("N", Element::N, n),
+                ("CA", Element::C, ca),
+                ("C", Element::C, c),
+                ("O", Element::O, o),
+            ] {
+                let atom = Atom::new(0, name.to_string(), element, position);
+                mol.add_atom(atom, residue, Some("A"));
+            }
+            let next_ca = place(ca, c, next_n, 1.458, 121.7, 180.0);
+            let next_c = place(c, next_n, next_ca, 1.525, 111.2, phi);
+            (n, ca, c) = (next_n, next_ca, next_c);
+        }
+        mol
+    }
+
+    #[test]
+    fn test_finds_backbone() {
+        let mut mol = chain(3, -57.0, -47.0);
+        // a ligand without backbone atoms and a residue too far away to be connected
+        mol.add_atom(
+            Atom::new(0, "C1".to_string(), Element::C, Vec3::ZERO),
+            Some((1, "LIG")),
+            None,
+        );
+        let far = chain(1, -57.0, -47.0);
+        for atom in far.atoms {
+            let position = atom.position + Vec3::splat(50.0);
+            mol.add_atom(Atom { position, ..atom }, Some((9, "PRO")), Some("A"));
+        }
+
+        let backbone = backbone(&mol);
+
+        assert_eq!(backbone.len(), 4);
+        assert_eq!(
+            backbone[1],
+            BackboneResidue {
+                residue: 1,
+                n: 4,
+                ca: 5,
+                c: 6,
+                o: 7,
+                connected: true,
+                proline: false,
+            }
+        );
+        assert!(!backbone[0].connected);
+        assert!(!backbone[3].connected);
+        assert!(backbone[3].proline);
+    }
+
+    #[test]
+    fn test_assigns_helix_and_coil() {
+        let helix = chain(12, -57.0, -47.0);
+        let structure = assign_secondary_structure(&backbone(&helix), |a| helix.atoms[a].position);
+        assert_eq!(structure[0], SecondaryStructure::Coil);
+        assert!(structure[1..11]
+            .iter()
+            .all(|s| *s == SecondaryStructure::Helix));
+
+        // a single extended chain has no partner for a sheet
+        let strand = chain(12, -120.0, 130.0);
+        let structure =
+            assign_secondary_structure(&backbone(&strand), |a| strand.atoms[a].position);
+        assert!(structure.iter().all(|s| *s == SecondaryStructure::Coil));
+    }
+}
@@ -0,0 +1,538 @@
+use std::f32::consts::PI;
+
+use anyhow::{anyhow, Result};
+use bevy::math::{Vec3, Vec4};
+use chemcore::{daylight::read_smiles, molecule::Molecule as _};
+use gamma::graph::Graph;
+
+use crate::{
+    element::Element,
+    molecule::{Atom, Bond, BondType, Molecule},
+};
+
+/// builds a molecule from a smiles string, with explicit hydrogens and generated 3d coordinates
+pub fn parse_smiles(smiles: &str) -> Result<Molecule> {
+    let smiles = smiles.trim();
+    let molecule = read_smiles(smiles, None).map_err(|e| anyhow!("Invalid SMILES: {:?}", e))?;
+
+    let mut atoms: Vec<SmilesAtom> = vec![];
+    // (atom index, atom index, bond order)
+    let mut bonds: Vec<(usize, usize, f32)> = vec![];
+
+    // graph ids aren't necessarily contiguous
+    let ids: Vec<usize> = molecule.ids().collect();
+    let index_of = |id: usize| ids.iter().position(|i| *i == id);
+
+    for id in &ids {
+        let atom = molecule
+            .atom(*id)
+            .map_err(|e| anyhow!("Invalid atom: {:?}", e))?;
+        // no element is the wildcard atom ("*")
+        let element = match atom.element {
+            Some(element) => {
+                let symbol = format!("{:?}", element);
+                Element::from_symbol(&symbol)
+                    .ok_or_else(|| anyhow!("Not handled element str: {}", symbol))?
+            }
+            None => Element::Du,
+        };
+        let charge = molecule
+            .charge(*id)
+            .map_err(|e| anyhow!("Invalid atom: {:?}", e))?;
+        atoms.push(SmilesAtom {
+            element,
+            charge: charge.round() as i32,
+            isotope: atom.isotope.map(|i| i as u32),
+        });
+    }
+
+    for (sid, tid) in molecule.edges() {
+        let order = molecule
+            .bond_order(sid, tid)
+            .map_err(|e| anyhow!("Invalid bond: {:?}", e))?;
+        let atom1 = index_of(sid).ok_or_else(|| anyhow!("Bond to missing atom: {}", sid))?;
+        let atom2 = index_of(tid).ok_or_else(|| anyhow!("Bond to missing atom: {}", tid))?;
+        bonds.push((atom1, atom2, order));
+    }
+
+    // implicit hydrogens become atoms, so they can be rendered
+    for (index, id) in ids.iter().enumerate() {
+        let hydrogens = molecule
+            .atom(*id)
+            .map_err(|e| anyhow!("Invalid atom: {:?}", e))?
+            .hydrogens;
+        for _ in 0..hydrogens {
+            atoms.push(SmilesAtom {
+                element: Element::H,
+                charge: 0,
+                isotope: None,
+            });
+            bonds.push((index, atoms.len() - 1, 1.0));
+        }
+    }
+
+    let elements: Vec<Element> = atoms.iter().map(|a| a.element).collect();
+    let positions = embed(&elements, &bonds);
+
+    let atoms = atoms
+        .iter()
+        .zip(positions)
+        .enumerate()
+        .map(|(index, (atom, position))| Atom {
+            formal_charge: Some(atom.charge),
+            isotope: atom.isotope,
+            ..Atom::new(
+                index as i32 + 1,
+                format!("{}{}", atom.element.symbol(), index + 1),
+                atom.element,
+                position,
+            )
+        })
+        .collect();
+
+    let bonds = bonds
+        .iter()
+        .map(|(atom1, atom2, order)| Bond {
+            atom1: *atom1,
+            atom2: *atom2,
+            type_: bond_type(*order),
+        })
+        .collect();
+
+    Ok(Molecule {
+        name: smiles.to_string(),
+        atoms,
+        bonds,
+        ..Default::default()
+    })
+}
+
+struct SmilesAtom {
+    element: Element,
+    charge: i32,
+    isotope: Option<u32>,
+}
+
+fn bond_type(order: f32) -> BondType {
+    if order == 1.5 {
+        BondType::Aromatic
+    } else {
+        match order.round() as i32 {
+            2 => BondType::Double,
+            3 => BondType::Triple,
+            _ => BondType::Single,
+        }
+    }
+}
+
+/// a distance the embedding tries to reach between 2 atoms
+struct Restraint {
+    atom1: usize,
+    atom2: usize,
+    distance: f32,
+    stiffness: f32,
+}
+
+/// 1-4 distances across a bond between sp2 atoms, for the neighbors to be either cis or trans
+struct PlanarBond {
+    atom1: usize,
+    atom2: usize,
+    pairs: Vec<PlanarPair>,
+    /// whether the pair of first neighbors is cis, if it's known before embedding
+    reference_cis: Option<bool>,
+}
+
+struct PlanarPair {
+    atom1: usize,
+    atom2: usize,
+    cis_distance: f32,
+    trans_distance: f32,
+    /// whether this pair is cis when the pair of first neighbors is
+    same_as_reference: bool,
+}
+
+impl PlanarPair {
+    fn distance(&self, reference_cis: bool) -> f32 {
+        if reference_cis == self.same_as_reference {
+            self.cis_distance
+        } else {
+            self.trans_distance
+        }
+    }
+}
+
+/// generates 3d coordinates for a molecule graph
+///
+/// distance based embedding: bond lengths come from covalent radii (shortened for multiple bonds),
+/// angles from the hybridization (derived from the bond orders) are expressed as 1-3 distances,
+/// planarity of bonds between sp2 atoms as 1-4 distances, and atoms that aren't bonded or share a neighbor are pushed apart.
+/// starting from pseudo random (but deterministic) positions, the distances are satisfied iteratively.
+/// this is done in 4d, with the 4th coordinate gradually flattened out in the second half,
+/// which lets parts of the molecule pass each other instead of getting stuck (e.g. folded rings).
+pub fn embed(elements: &[Element], bonds: &[(usize, usize, f32)]) -> Vec<Vec3> {
+    let count = elements.len();
+    let mut neighbors: Vec<Vec<(usize, f32)>> = vec![vec![]; count];
+    for (atom1, atom2, order) in bonds {
+        neighbors[*atom1].push((*atom2, *order));
+        neighbors[*atom2].push((*atom1, *order));
+    }
+
+    let mut restraints = vec![];
+    // to know which pairs shouldn't be pushed apart
+    let mut restrained = vec![vec![false; count]; count];
+
+    for (atom1, atom2, order) in bonds {
+        restraints.push(Restraint {
+            atom1: *atom1,
+            atom2: *atom2,
+            distance: bond_length(elements[*atom1], elements[*atom2], *order),
+            stiffness: 1.0,
+        });
+        restrained[*atom1][*atom2] = true;
+        restrained[*atom2][*atom1] = true;
+    }
+
+    for (center, center_neighbors) in neighbors.iter().enumerate() {
+        let Some(angle) = ideal_angle(center_neighbors) else {
+            continue;
+        };
+        for (i, (atom1, order1)) in center_neighbors.iter().enumerate() {
+            for (atom2, order2) in center_neighbors.iter().skip(i + 1) {
+                let a = bond_length(elements[center], elements[*atom1], *order1);
+                let b = bond_length(elements[center], elements[*atom2], *order2);
+                // law of cosines
+                let distance = (a * a + b * b - 2.0 * a * b * angle.cos()).sqrt();
+                restraints.push(Restraint {
+                    atom1: *atom1,
+                    atom2: *atom2,
+                    distance,
+                    stiffness: 0.5,
+                });
+                restrained[*atom1][*atom2] = true;
+                restrained[*atom2][*atom1] = true;
+            }
+        }
+    }
+
+    // bonds between sp2 atoms are planar: the neighbors on both sides are either cis or trans.
+    // the first neighbor of each side is the reference, the other neighbor is on its opposite side,
+    // so the configuration is per bond: cis when the neighbors are in a small ring,
+    // otherwise decided while embedding, by taking the closest
+    let mut planar = vec![];
+    // (also conjugated single bonds, i.e. between 2 sp2 atoms)
+    for (atom1, atom2, order) in bonds {
+        let (Some(angle1), Some(angle2)) = (
+            planar_angle(&neighbors[*atom1]),
+            planar_angle(&neighbors[*atom2]),
+        ) else {
+            continue;
+        };
+        let length = bond_length(elements[*atom1], elements[*atom2], *order);
+        let neighbors1 = neighbors[*atom1].iter().filter(|(n, _)| n != atom2);
+        let mut pairs = vec![];
+        let mut reference_cis = None;
+        for (index1, (neighbor1, order1)) in neighbors1.enumerate() {
+            let neighbors2 = neighbors[*atom2].iter().filter(|(n, _)| n != atom1);
+            for (index2, (neighbor2, order2)) in neighbors2.enumerate() {
+                if neighbor1 == neighbor2 {
+                    continue;
+                }
+                // both sides in the xy plane, bond along x
+                let length1 = bond_length(elements[*atom1], elements[*neighbor1], *order1);
+                let length2 = bond_length(elements[*atom2], elements[*neighbor2], *order2);
+                let pos1 = Vec3::new(angle1.cos(), angle1.sin(), 0.0) * length1;
+                let cis = Vec3::new(length - angle2.cos() * length2, angle2.sin() * length2, 0.0);
+                let trans = cis * Vec3::new(1.0, -1.0, 1.0);
+
+                let same_as_reference = (index1 == 0) == (index2 == 0);
+                if reference_cis.is_none()
+                    && connected_within(&neighbors, *neighbor1, *neighbor2, [*atom1, *atom2], 6)
+                {
+                    reference_cis = Some(same_as_reference);
+                }
+                pairs.push(PlanarPair {
+                    atom1: *neighbor1,
+                    atom2: *neighbor2,
+                    cis_distance: pos1.distance(cis),
+                    trans_distance: pos1.distance(trans),
+                    same_as_reference,
+                });
+                restrained[*neighbor1][*neighbor2] = true;
+                restrained[*neighbor2][*neighbor1] = true;
+            }
+        }
+        planar.push(PlanarBond {
+            atom1: *atom1,
+            atom2: *atom2,
+            pairs,
+            reference_cis,
+        });
+    }
+
+    let mut positions = initial_positions(count);
+
+    let iterations = 1000;
+    for iteration in 0..iterations {
+        for restraint in &restraints {
+            apply_distance(
+                &mut positions,
+                restraint.atom1,
+                restraint.atom2,
+                restraint.distance,
+                restraint.stiffness,
+            );
+        }
+
+        for bond in &planar {
+            let reference_cis = bond.reference_cis.unwrap_or_else(|| {
+                let error = |reference_cis: bool| {
+                    bond.pairs
+                        .iter()
+                        .map(|pair| {
+                            let current = positions[pair.atom1].distance(positions[pair.atom2]);
+                            (current - pair.distance(reference_cis)).abs()
+                        })
+                        .sum::<f32>()
+                };
+                error(true) <= error(false)
+            });
+            for pair in &bond.pairs {
+                apply_distance(
+                    &mut positions,
+                    pair.atom1,
+                    pair.atom2,
+                    pair.distance(reference_cis),
+                    0.3,
+                );
+                // distances alone flatten slowly, as they barely change for small out of plane offsets
+                if iteration > iterations / 2 {
+                    apply_coplanar(
+                        &mut positions,
+                        pair.atom1,
+                        bond.atom1,
+                        bond.atom2,
+                        pair.atom2,
+                    );
+                }
+            }
+        }
+
+        if iteration > iterations / 2 {
+            for position in positions.iter_mut() {
+                position.w *= 0.9;
+            }
+        }
+
+        // repulsion is weaker at the start, so the bonded structure can form first
+        let repulsion = 0.1 + 0.3 * iteration as f32 / iterations as f32;
+        for atom1 in 0..count {
+            for atom2 in (atom1 + 1)..count {
+                if restrained[atom1][atom2] {
+                    continue;
+                }
+                let min_distance = 0.7
+                    * (elements[atom1].van_der_waals_radius()
+                        + elements[atom2].van_der_waals_radius());
+                if positions[atom1].distance(positions[atom2]) < min_distance {
+                    apply_distance(&mut positions, atom1, atom2, min_distance, repulsion);
+                }
+            }
+        }
+    }
+
+    let mut positions: Vec<Vec3> = positions.iter().map(|p| p.truncate()).collect();
+
+    // center at origin
+    if count > 0 {
+        let center = positions.iter().sum::<Vec3>() / count as f32;
+        for position in positions.iter_mut() {
+            *position -= center;
+        }
+    }
+
+    positions
+}
+
+/// moves `atom4` towards the plane of the other 3 atoms (and these slightly the other way)
+/// ignores the 4th dimension, meant to be used when it's being flattened
+fn apply_coplanar(positions: &mut [Vec4], atom1: usize, atom2: usize, atom3: usize, atom4: usize) {
+    let p1 = positions[atom1].truncate();
+    let p2 = positions[atom2].truncate();
+    let p3 = positions[atom3].truncate();
+    let p4 = positions[atom4].truncate();
+    let normal = (p2 - p1).cross(p3 - p2).normalize_or_zero();
+    let offset = (p4 - p2).dot(normal);
+    let correction = (normal * offset * 0.5).extend(0.0);
+    positions[atom4] -= correction;
+    positions[atom2] += correction * 0.5;
+    positions[atom3] += correction * 0.5;
+}
+
+/// whether there's a path of at most `max_steps` bonds between 2 atoms, not going through `excluded`
+fn connected_within(
+    neighbors: &[Vec<(usize, f32)>],
+    from: usize,
+    to: usize,
+    excluded: [usize; 2],
+    max_steps: usize,
+) -> bool {
+    let mut visited = vec![false; neighbors.len()];
+    visited[from] = true;
+    for atom in excluded {
+        visited[atom] = true;
+    }
+    let mut current = vec![from];
+    for _ in 0..max_steps {
+        let mut next = vec![];
+        for atom in current {
+            for (neighbor, _) in &neighbors[atom] {
+                if *neighbor == to {
+                    return true;
+                }
+                if !visited[*neighbor] {
+                    visited[*neighbor] = true;
+                    next.push(*neighbor);
+                }
+            }
+        }
+        current = next;
+    }
+    false
+}
+
+/// moves both atoms along their connecting line, towards the target distance
+fn apply_distance(
+    positions: &mut [Vec4],
+    atom1: usize,
+    atom2: usize,
+    distance: f32,
+    stiffness: f32,
+) {
+    let delta = positions[atom2] - positions[atom1];
+    let current = delta.length();
+    if current < 1e-6 {
+        // same position, no direction: nudge apart
+        positions[atom2] += Vec4::new(0.01, 0.02, 0.03, 0.04);
+        return;
+    }
+    let correction = delta * ((current - distance) / current) * 0.5 * stiffness;
+    positions[atom1] += correction;
+    positions[atom2] -= correction;
+}
+
+fn bond_length(element1: Element, element2: Element, order: f32) -> f32 {
+    // dummy atoms have no radius
+    let single = element1.covalent_radius().max(0.3) + element2.covalent_radius().max(0.3);
+    let factor = if order >= 3.0 {
+        0.78
+    } else if order >= 2.0 {
+        0.87
+    } else if order > 1.0 {
+        0.93
+    } else {
+        1.0
+    };
+    single * factor
+}
+
+/// angle between bonds of an atom, based on the hybridization implied by its bonds
+/// none when it can't be derived from the bonds alone (e.g. hypervalent atoms), these are only spread by repulsion
+fn ideal_angle(neighbors: &[(usize, f32)]) -> Option<f32> {
+    if neighbors.len() < 2 || neighbors.len() > 4 {
+        return None;
+    }
+    let triple = neighbors.iter().any(|(_, order)| *order >= 3.0);
+    let doubles = neighbors.iter().filter(|(_, order)| *order >= 2.0).count();
+    let multiple = neighbors.iter().any(|(_, order)| *order > 1.0);
+
+    let degrees: f32 = if neighbors.len() == 4 {
+        // sp3, also hypervalent atoms with double bonds (e.g. sulfates, phosphates)
+        109.47
+    } else if triple || doubles >= 2 {
+        // sp
+        180.0
+    } else if multiple {
+        // sp2
+        120.0
+    } else {
+        // sp3
+        109.47
+    };
+    Some(degrees * PI / 180.0)
+}
+
+/// bond angle of an sp2 atom, none for other hybridizations
+fn planar_angle(neighbors: &[(usize, f32)]) -> Option<f32> {
+    ideal_angle(neighbors).filter(|angle| (angle.to_degrees() - 120.0).abs() < 1.0)
+}
+
+/// deterministic pseudo random positions in a box that grows with the atom count
+fn initial_positions(count: usize) -> Vec<Vec4> {
+    let size = 1.5 * (count as f32).cbrt().max(1.0);
+    // linear congruential generator
+    let mut state: u32 = 12345;
+    let mut next = move || {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        (state >> 8) as f32 / (1 << 24) as f32 - 0.5
+    };
+    (0..count)
+        .map(|_| Vec4::new(next(), next(), next(), next()) * size)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_embeds_water_with_bond_lengths_and_angle() {
+        let elements = [Element::O, Element::H, Element::H];
+        let bonds = [(0, 1, 1.0), (0, 2, 1.0)];
+
+        let positions = embed(&elements, &bonds);
+
+        let expected_length = bond_length(Element::O, Element::H, 1.0);
+        let oh1 = positions[1] - positions[0];
+        let oh2 = positions[2] - positions[0];
+        assert!((oh1.length() - expected_length).abs() < 0.05);
+        assert!((oh2.length() - expected_length).abs() < 0.05);
+        let angle = oh1.angle_between(oh2).to_degrees();
+        assert!((angle - 109.47).abs() < 3.0, "angle: {}", angle);
+    }
+
+    #[test]
+    fn test_embeds_benzene_ring_planar() {
+        let mut elements = vec![Element::C; 6];
+        elements.extend([Element::H; 6]);
+        let mut bonds = vec![];
+        for i in 0..6 {
+            let order = if i % 2 == 0 { 2.0 } else { 1.0 };
+            bonds.push((i, (i + 1) % 6, order));
+            bonds.push((i, i + 6, 1.0));
+        }
+
+        let positions = embed(&elements, &bonds);
+
+        // all ring atoms in the plane through the first 3
+        let normal = (positions[1] - positions[0])
+            .cross(positions[2] - positions[0])
+            .normalize();
+        for position in &positions[3..6] {
+            assert!((*position - positions[0]).dot(normal).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn test_parses_smiles_with_implicit_hydrogens() {
+        let mol = parse_smiles("CC=O").unwrap();
+
+        // 3 heavy atoms + 4 hydrogens
+        assert_eq!(mol.atoms.len(), 7);
+        assert_eq!(mol.bonds.len(), 6);
+        assert_eq!(
+            mol.atoms.iter().filter(|a| a.element == Element::H).count(),
+            4
+        );
+        assert!(mol.bonds.iter().any(|b| b.type_ == BondType::Double));
+    }
+}
@@ -0,0 +1,428 @@
+use bevy::{
+    math::{IVec3, Vec3},
+    utils::HashMap,
+};
+
+use crate::molecule::Atom;
+
+/// upper limit for the points of the grid, the resolution is reduced for large molecules to stay below it
+const MAX_GRID_POINTS: usize = 4_000_000;
+/// decay of the gaussian of each atom (relative to its radius), higher is closer to the van der waals surface
+const GAUSSIAN_DECAY: f32 = 2.0;
+/// distance (in radii) after which the gaussian of an atom is ignored
+const GAUSSIAN_CUTOFF: f32 = 2.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceKind {
+    /// traced by the center of the probe rolling over the atoms
+    SolventAccessible,
+    /// traced by the surface of the probe rolling over the atoms (connolly surface)
+    SolventExcluded,
+    /// isosurface of the sum of a gaussian density per atom
+    Gaussian,
+}
+
+/// triangle mesh of a surface
+#[derive(Debug, Default)]
+pub struct SurfaceGeometry {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub indices: Vec<u32>,
+    /// for each vertex, the index of the atom with the closest van der waals surface (e.g. for coloring)
+    pub nearest_atoms: Vec<usize>,
+}
+
+/// surface of the atoms (with van der waals radii): a field is sampled on a grid with `resolution` (angstrom) spacing,
+/// negative inside of the surface, and the surface is extracted where it's 0 with marching tetrahedra
+/// (marching cubes with each cube split in 6 tetrahedra, which needs no case tables).
+/// `probe_radius` is used for the solvent accessible / excluded surfaces
+pub fn compute_surface(
+    atoms: &[Atom],
+    kind: SurfaceKind,
+    probe_radius: f32,
+    resolution: f32,
+) -> SurfaceGeometry {
+    let radii: Vec<f32> = atoms
+        .iter()
+        .map(|a| a.element.van_der_waals_radius())
+        .collect();
+    let Some(max_radius) = radii.iter().copied().reduce(f32::max) else {
+        return SurfaceGeometry::default();
+    };
+    let reach = match kind {
+        SurfaceKind::Gaussian => max_radius * GAUSSIAN_CUTOFF,
+        _ => max_radius + probe_radius,
+    };
+    let mut grid = Grid::enclosing(atoms, reach, resolution);
+
+    match kind {
+        SurfaceKind::SolventAccessible => fill_distance(&mut grid, atoms, &radii, probe_radius),
+        SurfaceKind::SolventExcluded => {
+            fill_distance(&mut grid, atoms, &radii, probe_radius);
+            exclude_probe(&mut grid, probe_radius);
+        }
+        SurfaceKind::Gaussian => fill_gaussian(&mut grid, atoms, &radii),
+    }
+
+    let mut geometry = march(&grid);
+    let atom_grid = AtomGrid::new(atoms, max_radius + probe_radius);
+    geometry.nearest_atoms = geometry
+        .positions
+        .iter()
+        .map(|p| atom_grid.nearest(atoms, &radii, *p))
+        .collect();
+    geometry
+}
+
+/// scalar field sampled on a regular grid
+struct Grid {
+    origin: Vec3,
+    /// spacing of the points
+    cell: f32,
+    size: [usize; 3],
+    values: Vec<f32>,
+}
+
+impl Grid {
+    /// grid with the bounding box of the atoms extended by `reach` (and 2 cells, such that the surface is closed).
+    /// the values are initialized to `reach`, outside of the surface
+    fn enclosing(atoms: &[Atom], reach: f32, resolution: f32) -> Grid {
+        let (min, max) = atoms.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), a| (min.min(a.position), max.max(a.position)),
+        );
+        let mut cell = resolution;
+        let size = |cell: f32| {
+            let extent = max - min + Vec3::splat(2.0 * (reach + 2.0 * cell));
+            (extent / cell)
+                .ceil()
+                .as_uvec3()
+                .to_array()
+                .map(|n| n as usize + 1)
+        };
+        while size(cell).iter().product::<usize>() > MAX_GRID_POINTS {
+            cell *= 1.25;
+        }
+        let size = size(cell);
+        Grid {
+            origin: min - Vec3::splat(reach + 2.0 * cell),
+            cell,
+            size,
+            values: vec![reach; size.iter().product()],
+        }
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        x + self.size[0] * (y + self.size[1] * z)
+    }
+
+    fn position(&self, index: usize) -> Vec3 {
+        let x = index % self.size[0];
+        let y = index / self.size[0] % self.size[1];
+        let z = index / (self.size[0] * self.size[1]);
+        self.origin + Vec3::new(x as f32, y as f32, z as f32) * self.cell
+    }
+
+    /// calls `f` with the index and position of the points within `radius` of `center`
+    fn for_each_near(&self, center: Vec3, radius: f32, mut f: impl FnMut(usize, Vec3)) {
+        let min = ((center - radius - self.origin) / self.cell)
+            .floor()
+            .max(Vec3::ZERO);
+        let max = ((center + radius - self.origin) / self.cell).ceil();
+        let [min_x, min_y, min_z] = min.to_array().map(|v| v as usize);
+        let [max_x, max_y, max_z] = max.to_array().map(|v| v as usize);
+        for z in min_z..=max_z.min(self.size[2] - 1) {
+            for y in min_y..=max_y.min(self.size[1] - 1) {
+                for x in min_x..=max_x.min(self.size[0] - 1) {
+                    let index = self.index(x, y, z);
+                    let position = self.position(index);
+                    if position.distance_squared(center) <= radius * radius {
+                        f(index, position);
+                    }
+                }
+            }
+        }
+    }
+
+    /// indices of the neighbors along the axes
+    fn neighbors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let x = index % self.size[0];
+        let y = index / self.size[0] % self.size[1];
+        let z = index / (self.size[0] * self.size[1]);
+        let layer = self.size[0] * self.size[1];
+        [
+            (x > 0).then(|| index - 1),
+            (x + 1 < self.size[0]).then(|| index + 1),
+            (y > 0).then(|| index - self.size[0]),
+            (y + 1 < self.size[1]).then(|| index + self.size[0]),
+            (z > 0).then(|| index - layer),
+            (z + 1 < self.size[2]).then(|| index + layer),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// distance to the surface of the atoms inflated by `probe_radius` (the solvent accessible surface)
+fn fill_distance(grid: &mut Grid, atoms: &[Atom], radii: &[f32], probe_radius: f32) {
+    let margin = 2.0 * grid.cell;
+    for (atom, radius) in atoms.iter().zip(radii) {
+        let radius = radius + probe_radius;
+        let mut updates = vec![];
+        grid.for_each_near(atom.position, radius + margin, |index, position| {
+            updates.push((index, position.distance(atom.position) - radius));
+        });
+        for (index, value) in updates {
+            grid.values[index] = grid.values[index].min(value);
+        }
+    }
+}
+
+/// turns the solvent accessible field into the solvent excluded one: the points of the solvent accessible volume
+/// closer than `probe_radius` to its surface can be reached by the probe, so they're outside
+fn exclude_probe(grid: &mut Grid, probe_radius: f32) {
+    // the solvent accessible surface, interpolated on the edges between inside and outside points
+    let mut surface_points = vec![];
+    for (index, value) in grid.values.iter().enumerate() {
+        if *value >= 0.0 {
+            continue;
+        }
+        for neighbor in grid.neighbors(index) {
+            let neighbor_value = grid.values[neighbor];
+            if neighbor_value >= 0.0 {
+                let t = value / (value - neighbor_value);
+                surface_points.push(grid.position(index).lerp(grid.position(neighbor), t));
+            }
+        }
+    }
+
+    // points further than this stay inside
+    let max_distance = probe_radius + 2.0 * grid.cell;
+    let mut distances = vec![max_distance; grid.values.len()];
+    for point in surface_points {
+        grid.for_each_near(point, max_distance, |index, position| {
+            distances[index] = distances[index].min(position.distance(point));
+        });
+    }
+    for (value, distance) in grid.values.iter_mut().zip(distances) {
+        *value = if *value < 0.0 {
+            probe_radius - distance
+        } else {
+            probe_radius + *value
+        };
+    }
+}
+
+/// 1 (the isovalue) minus the sum of the gaussians of the atoms, which are 1 at their radius
+fn fill_gaussian(grid: &mut Grid, atoms: &[Atom], radii: &[f32]) {
+    let mut density = vec![0.0; grid.values.len()];
+    for (atom, radius) in atoms.iter().zip(radii) {
+        grid.for_each_near(
+            atom.position,
+            radius * GAUSSIAN_CUTOFF,
+            |index, position| {
+                let d2 = position.distance_squared(atom.position) / (radius * radius);
+                density[index] += (-GAUSSIAN_DECAY * (d2 - 1.0)).exp();
+            },
+        );
+    }
+    for (value, density) in grid.values.iter_mut().zip(density) {
+        *value = 1.0 - density;
+    }
+}
+
+/// corners of a grid cube, offsets in x, y, z
+const CUBE_CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [0, 1, 0],
+    [1, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [0, 1, 1],
+    [1, 1, 1],
+];
+/// split of the cube in tetrahedra (corner indices) along the diagonal from corner 0 to 7,
+/// the same in all the cubes such that the faces of the neighbor cubes match
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 3, 2, 7],
+    [0, 2, 6, 7],
+    [0, 6, 4, 7],
+    [0, 4, 5, 7],
+    [0, 5, 1, 7],
+];
+
+/// triangles where the field of the grid is 0, with the vertices shared by the triangles and normals pointing outside
+fn march(grid: &Grid) -> SurfaceGeometry {
+    let mut geometry = SurfaceGeometry::default();
+    // vertex on the edge between two grid points
+    let mut edge_vertices: HashMap<(usize, usize), u32> = HashMap::new();
+    let mut vertex = |geometry: &mut SurfaceGeometry, a: usize, b: usize| {
+        *edge_vertices
+            .entry((a.min(b), a.max(b)))
+            .or_insert_with(|| {
+                let (va, vb) = (grid.values[a], grid.values[b]);
+                let t = va / (va - vb);
+                geometry
+                    .positions
+                    .push(grid.position(a).lerp(grid.position(b), t));
+                geometry.positions.len() as u32 - 1
+            })
+    };
+
+    let [size_x, size_y, size_z] = grid.size;
+    for z in 0..size_z - 1 {
+        for y in 0..size_y - 1 {
+            for x in 0..size_x - 1 {
+                let corners = CUBE_CORNERS.map(|[dx, dy, dz]| grid.index(x + dx, y + dy, z + dz));
+                for tetrahedron in TETRAHEDRA {
+                    let points = tetrahedron.map(|corner| corners[corner]);
+                    let (inside, outside): (Vec<usize>, Vec<usize>) =
+                        points.iter().partition(|p| grid.values[**p] < 0.0);
+                    let polygon = match (inside.len(), &inside[..], &outside[..]) {
+                        (1, [i], [o0, o1, o2]) => vec![(*i, *o0), (*i, *o1), (*i, *o2)],
+                        (3, [i0, i1, i2], [o]) => vec![(*i0, *o), (*i1, *o), (*i2, *o)],
+                        (2, [i0, i1], [o0, o1]) => {
+                            vec![(*i0, *o0), (*i0, *o1), (*i1, *o1), (*i1, *o0)]
+                        }
+                        _ => continue,
+                    };
+                    let polygon: Vec<u32> = polygon
+                        .into_iter()
+                        .map(|(a, b)| vertex(&mut geometry, a, b))
+                        .collect();
+                    let outward = grid.position(outside[0]) - grid.position(inside[0]);
+                    for triangle in [[0, 1, 2], [0, 2, 3]].iter().take(polygon.len() - 2) {
+                        let [a, b, c] = triangle.map(|i| polygon[i]);
+                        let [pa, pb, pc] = [a, b, c].map(|v| geometry.positions[v as usize]);
+                        if (pb - pa).cross(pc - pa).dot(outward) < 0.0 {
+                            geometry.indices.extend([a, c, b]);
+                        } else {
+                            geometry.indices.extend([a, b, c]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // area weighted normals of the triangles around the vertices
+    geometry.normals = vec![Vec3::ZERO; geometry.positions.len()];
+    for triangle in geometry.indices.chunks(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|v| v as usize);
+        let [pa, pb, pc] = [a, b, c].map(|v| geometry.positions[v]);
+        let normal = (pb - pa).cross(pc - pa);
+        for v in [a, b, c] {
+            geometry.normals[v] += normal;
+        }
+    }
+    for normal in &mut geometry.normals {
+        *normal = normal.normalize_or_zero();
+    }
+    geometry
+}
+
+/// atoms in a grid, to find the nearest one to a point
+struct AtomGrid {
+    cell: f32,
+    cells: HashMap<IVec3, Vec<usize>>,
+}
+
+impl AtomGrid {
+    fn new(atoms: &[Atom], cell: f32) -> AtomGrid {
+        let mut cells: HashMap<IVec3, Vec<usize>> = HashMap::new();
+        for (index, atom) in atoms.iter().enumerate() {
+            cells
+                .entry((atom.position / cell).floor().as_ivec3())
+                .or_default()
+                .push(index);
+        }
+        AtomGrid { cell, cells }
+    }
+
+    /// the atom with the closest van der waals surface, looking first in the neighboring cells
+    fn nearest(&self, atoms: &[Atom], radii: &[f32], point: Vec3) -> usize {
+        let distance = |a: &usize| point.distance(atoms[*a].position) - radii[*a];
+        let center = (point / self.cell).floor().as_ivec3();
+        let neighbors = (-1..=1)
+            .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z))))
+            .filter_map(|offset| self.cells.get(&(center + offset)))
+            .flatten();
+        neighbors
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .copied()
+            .or_else(|| (0..atoms.len()).min_by(|a, b| distance(a).total_cmp(&distance(b))))
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{math::Vec3, utils::HashMap};
+
+    use super::{compute_surface, SurfaceGeometry, SurfaceKind};
+    use crate::{element::Element, molecule::Atom};
+
+    fn atom(position: Vec3) -> Atom {
+        Atom::new(1, "O".to_string(), Element::O, position)
+    }
+
+    /// every edge is shared by 2 triangles, in opposite directions
+    fn assert_closed(geometry: &SurfaceGeometry) {
+        let mut edges: HashMap<(u32, u32), i32> = HashMap::new();
+        for triangle in geometry.indices.chunks(3) {
+            for (a, b) in [
+                (triangle[0], triangle[1]),
+                (triangle[1], triangle[2]),
+                (triangle[2], triangle[0]),
+            ] {
+                *edges.entry((a.min(b), a.max(b))).or_default() += if a < b { 1 } else { -1 };
+            }
+        }
+        assert!(edges.values().all(|count| *count == 0));
+    }
+
+    #[test]
+    fn test_surfaces_of_single_atom_are_spheres() {
+        let center = Vec3::new(1.0, 2.0, 3.0);
+        let radius = Element::O.van_der_waals_radius();
+        for (kind, expected_radius) in [
+            (SurfaceKind::SolventAccessible, radius + 1.4),
+            (SurfaceKind::SolventExcluded, radius),
+            (SurfaceKind::Gaussian, radius),
+        ] {
+            let geometry = compute_surface(&[atom(center)], kind, 1.4, 0.25);
+
+            assert!(!geometry.indices.is_empty());
+            assert_closed(&geometry);
+            for (position, normal) in geometry.positions.iter().zip(&geometry.normals) {
+                assert!((position.distance(center) - expected_radius).abs() < 0.1);
+                assert!(normal.dot((*position - center).normalize()) > 0.9);
+            }
+            assert!(geometry.nearest_atoms.iter().all(|a| *a == 0));
+        }
+    }
+
+    #[test]
+    fn test_solvent_excluded_surface_fills_gap_between_atoms() {
+        // the probe doesn't fit between the atoms, so the middle is inside of the surface
+        let atoms = [atom(Vec3::ZERO), atom(Vec3::new(4.0, 0.0, 0.0))];
+        let geometry = compute_surface(&atoms, SurfaceKind::SolventExcluded, 1.4, 0.25);
+
+        assert_closed(&geometry);
+        let in_gap = |p: &&Vec3| (p.x - 2.0).abs() < 0.1;
+        let gap_radius = geometry
+            .positions
+            .iter()
+            .filter(in_gap)
+            .map(|p| p.y.hypot(p.z))
+            .fold(f32::MAX, f32::min);
+        assert!(gap_radius > 0.5);
+        let nearest = &geometry.nearest_atoms;
+        let positions = &geometry.positions;
+        assert!((0..positions.len())
+            .all(|v| (positions[v].x > 2.0) == (nearest[v] == 1)
+                || (positions[v].x - 2.0).abs() < 0.01));
+    }
+}
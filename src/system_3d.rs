@@ -3,9 +3,14 @@ use std::f32::consts::PI;
 use crate::bounding_box::BoundingBox;
 use crate::defocus::DefocusPlugin;
 use crate::embedded_asset_plugin::EmbeddedAssetPlugin;
+use crate::file_open_plugin::FileOpenPlugin;
 use crate::mol2_asset_plugin::Mol2AssetPlugin;
+use crate::pdb_asset_plugin::PdbAssetPlugin;
 use crate::rotator::RotatorPlugin;
 use crate::scene::event::AddedBoundingBox;
+use crate::sdf_asset_plugin::SdfAssetPlugin;
+use crate::trajectory_asset_plugin::TrajectoryAssetPlugin;
+use crate::xyz_asset_plugin::XyzAssetPlugin;
 use bevy::pbr::CascadeShadowConfigBuilder;
 use bevy::prelude::*;
 use sim_controls::{
@@ -16,9 +21,15 @@ use sim_controls::{
 #[allow(dead_code)]
 pub fn add_3d_space(app: &mut App) {
     app.add_plugins((
+        // registers an asset source, has to be added before DefaultPlugins
+        FileOpenPlugin,
         DefaultPlugins,
         EmbeddedAssetPlugin,
         Mol2AssetPlugin,
+        PdbAssetPlugin,
+        SdfAssetPlugin,
+        XyzAssetPlugin,
+        TrajectoryAssetPlugin,
         CameraControllerPlugin,
         RotatorPlugin,
         DefocusPlugin,
@@ -0,0 +1,845 @@
+use std::io::SeekFrom;
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use bevy::app::{App, Plugin};
+use bevy::asset::io::Reader;
+use bevy::asset::{Asset, AssetApp, AssetLoader, AssetPath, AssetServer, LoadContext};
+use bevy::math::Vec3;
+use bevy::reflect::TypePath;
+use bevy::tasks::futures_lite::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::molecule::UnitCell;
+
+/// loads binary trajectories (dcd, xtc).
+/// only the position of the frames is read when loading, the frames are read from the file when shown
+pub struct TrajectoryAssetPlugin;
+
+impl Plugin for TrajectoryAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Trajectory>()
+            .register_asset_loader(DcdAssetLoader)
+            .register_asset_loader(XtcAssetLoader);
+    }
+}
+
+/// frames of a trajectory file, for the atoms of a separately loaded topology (e.g. pdb)
+#[derive(Debug, Clone, Asset, TypePath)]
+pub struct Trajectory {
+    /// the file the frames are read from
+    pub path: AssetPath<'static>,
+    pub format: TrajectoryFormat,
+    pub atom_count: usize,
+    /// byte range of each frame in the file
+    pub frames: Vec<Range<u64>>,
+}
+
+impl Trajectory {
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrajectoryFormat {
+    Dcd { big_endian: bool, unit_cell: bool },
+    Xtc,
+}
+
+/// a frame read from a trajectory file, positions in Å
+#[derive(Debug, Clone, Default)]
+pub struct TrajectoryFrame {
+    pub positions: Vec<Vec3>,
+    /// periodic box
+    pub unit_cell: Option<UnitCell>,
+}
+
+pub struct DcdAssetLoader;
+
+impl AssetLoader for DcdAssetLoader {
+    type Asset = Trajectory;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Trajectory, Self::Error> {
+        let trajectory = index_dcd(reader, load_context.asset_path().clone()).await?;
+        println!(
+            "finished indexing dcd file: atoms: {}, frames: {}",
+            trajectory.atom_count,
+            trajectory.frame_count()
+        );
+        Ok(trajectory)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dcd"]
+    }
+}
+
+pub struct XtcAssetLoader;
+
+impl AssetLoader for XtcAssetLoader {
+    type Asset = Trajectory;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Trajectory, Self::Error> {
+        let trajectory = index_xtc(reader, load_context.asset_path().clone()).await?;
+        println!(
+            "finished indexing xtc file: atoms: {}, frames: {}",
+            trajectory.atom_count,
+            trajectory.frame_count()
+        );
+        Ok(trajectory)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xtc"]
+    }
+}
+
+/// reads a frame from the trajectory file, with the asset source the trajectory was loaded from
+pub async fn load_frame(
+    asset_server: AssetServer,
+    path: AssetPath<'static>,
+    format: TrajectoryFormat,
+    atom_count: usize,
+    range: Range<u64>,
+) -> Result<TrajectoryFrame> {
+    let source = asset_server
+        .get_source(path.source())
+        .map_err(|e| anyhow!("{}", e))?;
+    let mut reader = source.reader().read(path.path()).await?;
+    read_frame(&mut reader, format, atom_count, range).await
+}
+
+/// reads the frame at `range` (see `Trajectory::frames`)
+pub async fn read_frame<R: AsyncRead + AsyncSeek + Unpin + ?Sized>(
+    reader: &mut R,
+    format: TrajectoryFormat,
+    atom_count: usize,
+    range: Range<u64>,
+) -> Result<TrajectoryFrame> {
+    reader.seek(SeekFrom::Start(range.start)).await?;
+    let mut bytes = vec![0; (range.end - range.start) as usize];
+    reader.read_exact(&mut bytes).await?;
+    match format {
+        TrajectoryFormat::Dcd {
+            big_endian,
+            unit_cell,
+        } => decode_dcd_frame(&bytes, big_endian, unit_cell, atom_count),
+        TrajectoryFormat::Xtc => decode_xtc_frame(&bytes),
+    }
+}
+
+fn read_i32(bytes: &[u8], offset: usize, big_endian: bool) -> i32 {
+    let b = [
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ];
+    if big_endian {
+        i32::from_be_bytes(b)
+    } else {
+        i32::from_le_bytes(b)
+    }
+}
+
+fn read_f32(bytes: &[u8], offset: usize, big_endian: bool) -> f32 {
+    f32::from_bits(read_i32(bytes, offset, big_endian) as u32)
+}
+
+fn read_f64(bytes: &[u8], offset: usize, big_endian: bool) -> f64 {
+    let mut b = [0; 8];
+    b.copy_from_slice(&bytes[offset..offset + 8]);
+    if big_endian {
+        f64::from_be_bytes(b)
+    } else {
+        f64::from_le_bytes(b)
+    }
+}
+
+/// size of the dcd header record (84 bytes between the fortran record markers)
+const DCD_HEADER_SIZE: usize = 92;
+/// the unit cell record: 6 doubles between the record markers
+const DCD_UNIT_CELL_SIZE: u64 = 56;
+
+/// dcd (CHARMM, NAMD, X-PLOR) files are fortran records:
+/// header ("CORD" and control ints), title, atom count, then per frame: [unit cell], x, y, z (, w)
+async fn index_dcd<R: AsyncRead + AsyncSeek + Unpin + ?Sized>(
+    reader: &mut R,
+    path: AssetPath<'static>,
+) -> Result<Trajectory> {
+    let mut header = [0; DCD_HEADER_SIZE];
+    reader
+        .read_exact(&mut header)
+        .await
+        .map_err(|_| anyhow!("File is too short for a dcd header."))?;
+    // the first record marker is the size of the header, which tells the byte order
+    let big_endian = match (read_i32(&header, 0, false), read_i32(&header, 0, true)) {
+        (84, _) => false,
+        (_, 84) => true,
+        _ => return Err(anyhow!("Not a dcd file (unexpected header size).")),
+    };
+    let int = |bytes: &[u8], offset: usize| read_i32(bytes, offset, big_endian);
+    if &header[4..8] != b"CORD" || int(&header, 88) != 84 {
+        return Err(anyhow!("Not a dcd coordinates file."));
+    }
+    let control = |index: usize| int(&header, 8 + 4 * index);
+    // a CHARMM version is set by CHARMM and NAMD, X-PLOR files have neither unit cell nor 4th dimension
+    let charmm = control(19) != 0;
+    let unit_cell = charmm && control(10) != 0;
+    let four_dims = charmm && control(11) != 0;
+    if control(8) != 0 {
+        return Err(anyhow!("Dcd files with fixed atoms are not supported."));
+    }
+
+    let mut marker = [0; 4];
+    reader.read_exact(&mut marker).await?;
+    let title_size = int(&marker, 0);
+    if title_size < 0 {
+        return Err(anyhow!("Invalid dcd title size: {}", title_size));
+    }
+    reader
+        .seek(SeekFrom::Current(title_size as i64 + 4))
+        .await?;
+
+    let mut record = [0; 12];
+    reader.read_exact(&mut record).await?;
+    let atom_count = int(&record, 4);
+    if int(&record, 0) != 4 || atom_count <= 0 {
+        return Err(anyhow!("Invalid dcd atom count record."));
+    }
+    let atom_count = atom_count as usize;
+
+    let header_size = (DCD_HEADER_SIZE + 8 + title_size as usize + 12) as u64;
+    let coords_size = 8 + 4 * atom_count as u64;
+    let frame_size = if unit_cell { DCD_UNIT_CELL_SIZE } else { 0 }
+        + 3 * coords_size
+        + if four_dims { coords_size } else { 0 };
+    let file_size = reader.seek(SeekFrom::End(0)).await?;
+    // the frame count of the header isn't reliable (e.g. with files still being written), the file size is
+    let frame_count = file_size.saturating_sub(header_size) / frame_size;
+    if frame_count == 0 {
+        return Err(anyhow!("File has no frames."));
+    }
+
+    Ok(Trajectory {
+        path,
+        format: TrajectoryFormat::Dcd {
+            big_endian,
+            unit_cell,
+        },
+        atom_count,
+        frames: (0..frame_count)
+            .map(|i| {
+                let start = header_size + i * frame_size;
+                start..start + frame_size
+            })
+            .collect(),
+    })
+}
+
+fn decode_dcd_frame(
+    bytes: &[u8],
+    big_endian: bool,
+    unit_cell: bool,
+    atom_count: usize,
+) -> Result<TrajectoryFrame> {
+    let mut offset = 0;
+    let unit_cell = if unit_cell {
+        // order: a, gamma, b, beta, alpha, c
+        let value = |i: usize| read_f64(bytes, 4 + 8 * i, big_endian) as f32;
+        let mut angles = [value(4), value(3), value(1)];
+        // newer NAMD versions write the cosines of the angles
+        if angles.iter().all(|a| a.abs() <= 1.0) {
+            angles = angles.map(|cos| cos.acos().to_degrees());
+        }
+        offset = DCD_UNIT_CELL_SIZE as usize;
+        Some(UnitCell {
+            lengths: Vec3::new(value(0), value(2), value(5)),
+            angles: Vec3::from(angles),
+            space_group: None,
+        })
+    } else {
+        None
+    };
+
+    let coords_size = 8 + 4 * atom_count;
+    if bytes.len() < offset + 3 * coords_size {
+        return Err(anyhow!("Dcd frame is too short."));
+    }
+    let mut axes = [0, 1, 2].map(|axis| {
+        let start = offset + axis * coords_size;
+        (read_i32(bytes, start, big_endian), start + 4)
+    });
+    if axes
+        .iter()
+        .any(|(size, _)| *size as usize != 4 * atom_count)
+    {
+        return Err(anyhow!("Invalid dcd coordinates record."));
+    }
+    let positions = (0..atom_count)
+        .map(|_| {
+            let [x, y, z] = axes.each_mut().map(|(_, offset)| {
+                let value = read_f32(bytes, *offset, big_endian);
+                *offset += 4;
+                value
+            });
+            Vec3::new(x, y, z)
+        })
+        .collect();
+
+    Ok(TrajectoryFrame {
+        positions,
+        unit_cell,
+    })
+}
+
+const XTC_MAGIC: i32 = 1995;
+/// magic, atom count, step, time, box, atom count
+const XTC_HEADER_SIZE: usize = 56;
+/// header and precision, min and max ints, small index and byte count of the compressed coordinates
+const XTC_COMPRESSED_HEADER_SIZE: usize = 92;
+/// up to this count the coordinates aren't compressed
+const XTC_MAX_UNCOMPRESSED_ATOMS: usize = 9;
+
+/// xtc (GROMACS) files are a sequence of XDR (big endian) frames with compressed coordinates in nm
+async fn index_xtc<R: AsyncRead + AsyncSeek + Unpin + ?Sized>(
+    reader: &mut R,
+    path: AssetPath<'static>,
+) -> Result<Trajectory> {
+    let file_size = reader.seek(SeekFrom::End(0)).await?;
+    let mut frames = vec![];
+    let mut atom_count = None;
+    let mut start = 0;
+
+    while start + XTC_HEADER_SIZE as u64 <= file_size {
+        reader.seek(SeekFrom::Start(start)).await?;
+        let mut header = [0; XTC_COMPRESSED_HEADER_SIZE];
+        let header_size = XTC_COMPRESSED_HEADER_SIZE.min((file_size - start) as usize);
+        reader.read_exact(&mut header[..header_size]).await?;
+        if read_i32(&header, 0, true) != XTC_MAGIC {
+            return Err(anyhow!("Frame {}: not an xtc frame.", frames.len() + 1));
+        }
+        let count = read_i32(&header, 4, true).max(0) as usize;
+        if count != *atom_count.get_or_insert(count) {
+            return Err(anyhow!(
+                "Frame {} has {} atoms, the first frame {}",
+                frames.len() + 1,
+                count,
+                atom_count.unwrap_or_default()
+            ));
+        }
+
+        let size = if count <= XTC_MAX_UNCOMPRESSED_ATOMS {
+            XTC_HEADER_SIZE + 12 * count
+        } else if header_size == XTC_COMPRESSED_HEADER_SIZE {
+            // the compressed bytes are padded to 4
+            let byte_count = read_i32(&header, 88, true).max(0) as usize;
+            XTC_COMPRESSED_HEADER_SIZE + byte_count.div_ceil(4) * 4
+        } else {
+            break;
+        } as u64;
+        // a truncated last frame (e.g. a simulation still running) is ignored
+        if start + size > file_size {
+            break;
+        }
+        frames.push(start..start + size);
+        start += size;
+    }
+
+    match atom_count {
+        Some(atom_count) if !frames.is_empty() => Ok(Trajectory {
+            path,
+            format: TrajectoryFormat::Xtc,
+            atom_count,
+            frames,
+        }),
+        _ => Err(anyhow!("File has no frames.")),
+    }
+}
+
+fn decode_xtc_frame(bytes: &[u8]) -> Result<TrajectoryFrame> {
+    if bytes.len() < XTC_HEADER_SIZE || read_i32(bytes, 0, true) != XTC_MAGIC {
+        return Err(anyhow!("Not an xtc frame."));
+    }
+    let atom_count = read_i32(bytes, 4, true).max(0) as usize;
+    let float = |offset: usize| read_f32(bytes, offset, true);
+
+    // nm to Å
+    let box_vectors = [0, 1, 2]
+        .map(|i| Vec3::new(float(16 + 12 * i), float(20 + 12 * i), float(24 + 12 * i)) * 10.0);
+    let unit_cell = (box_vectors != [Vec3::ZERO; 3]).then(|| {
+        let [a, b, c] = box_vectors;
+        UnitCell {
+            lengths: Vec3::new(a.length(), b.length(), c.length()),
+            angles: Vec3::new(b.angle_between(c), a.angle_between(c), a.angle_between(b))
+                .to_array()
+                .map(|a| a.to_degrees())
+                .into(),
+            space_group: None,
+        }
+    });
+
+    let positions = if atom_count <= XTC_MAX_UNCOMPRESSED_ATOMS {
+        if bytes.len() < XTC_HEADER_SIZE + 12 * atom_count {
+            return Err(anyhow!("Xtc frame is too short."));
+        }
+        (0..atom_count)
+            .map(|i| {
+                let offset = XTC_HEADER_SIZE + 12 * i;
+                Vec3::new(float(offset), float(offset + 4), float(offset + 8)) * 10.0
+            })
+            .collect()
+    } else {
+        decompress_xtc_coords(&bytes[XTC_HEADER_SIZE..], atom_count)?
+    };
+
+    Ok(TrajectoryFrame {
+        positions,
+        unit_cell,
+    })
+}
+
+/// the sizes of the small differences between consecutive atoms, indexed by their bit count
+const MAGIC_INTS: [u32; 73] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 10, 12, 16, 20, 25, 32, 40, 50, 64, 80, 101, 128, 161, 203, 256,
+    322, 406, 512, 645, 812, 1024, 1290, 1625, 2048, 2580, 3250, 4096, 5060, 6501, 8192, 10321,
+    13003, 16384, 20642, 26007, 32768, 41285, 52015, 65536, 82570, 104031, 131072, 165140, 208063,
+    262144, 330280, 416127, 524287, 660561, 832255, 1048576, 1321122, 1664510, 2097152, 2642245,
+    3329021, 4194304, 5284491, 6658042, 8388607, 10568983, 13316085, 16777216,
+];
+const FIRST_MAGIC_INT: usize = 9;
+
+/// port of `xdr3dfcoord` (xdrfile): atoms are stored as integers (coordinates * precision),
+/// either with all the bits of the coordinate range or as runs of small differences to the previous atom
+fn decompress_xtc_coords(bytes: &[u8], atom_count: usize) -> Result<Vec<Vec3>> {
+    if bytes.len() < XTC_COMPRESSED_HEADER_SIZE - XTC_HEADER_SIZE {
+        return Err(anyhow!("Xtc frame is too short."));
+    }
+    let int = |offset: usize| read_i32(bytes, offset, true);
+    let precision = read_f32(bytes, 0, true);
+    let min = [int(4), int(8), int(12)];
+    let max = [int(16), int(20), int(24)];
+    if precision <= 0.0 || (0..3).any(|i| max[i] < min[i]) {
+        return Err(anyhow!("Invalid xtc coordinates range."));
+    }
+    let sizes = [0, 1, 2].map(|i| (max[i] as i64 - min[i] as i64 + 1).min(u32::MAX as i64) as u32);
+    // very large ranges are stored per coordinate, otherwise the 3 coordinates are one number
+    let large = sizes.iter().any(|s| *s > 0xffffff);
+    let bit_sizes = sizes.map(size_of_int);
+    let bit_size = size_of_ints(sizes);
+
+    let mut small_index = int(28).max(0) as usize;
+    if !(FIRST_MAGIC_INT..MAGIC_INTS.len()).contains(&small_index) {
+        return Err(anyhow!("Invalid xtc small index: {}", small_index));
+    }
+    let mut smaller = MAGIC_INTS[FIRST_MAGIC_INT.max(small_index - 1)] / 2;
+    let mut small_num = MAGIC_INTS[small_index] / 2;
+
+    let byte_count = int(32).max(0) as usize;
+    let data = bytes
+        .get(36..36 + byte_count)
+        .ok_or_else(|| anyhow!("Xtc frame is too short."))?;
+    let mut reader = BitReader::new(data);
+
+    // nm to Å
+    let scale = 10.0 / precision;
+    let position = |c: [i32; 3]| Vec3::new(c[0] as f32, c[1] as f32, c[2] as f32) * scale;
+
+    let mut positions = Vec::with_capacity(atom_count);
+    let mut run = 0;
+    while positions.len() < atom_count {
+        let mut coord = if large {
+            [
+                reader.bits(bit_sizes[0])? as i32,
+                reader.bits(bit_sizes[1])? as i32,
+                reader.bits(bit_sizes[2])? as i32,
+            ]
+        } else {
+            reader.ints(bit_size, sizes)?
+        };
+        for (c, min) in coord.iter_mut().zip(min) {
+            *c = c.wrapping_add(min);
+        }
+        let mut prev = coord;
+
+        let mut is_smaller = 0;
+        if reader.bits(1)? == 1 {
+            run = reader.bits(5)?;
+            is_smaller = (run % 3) as i32;
+            run -= is_smaller as u32;
+            is_smaller -= 1;
+        }
+        if run > 0 {
+            for k in (0..run).step_by(3) {
+                let mut this = reader.ints(small_index as u32, [MAGIC_INTS[small_index]; 3])?;
+                for (t, p) in this.iter_mut().zip(prev) {
+                    *t = t.wrapping_add(p).wrapping_sub(small_num as i32);
+                }
+                if k == 0 {
+                    // the first two atoms are swapped, for better compression of water molecules
+                    std::mem::swap(&mut this, &mut prev);
+                    positions.push(position(prev));
+                } else {
+                    prev = this;
+                }
+                positions.push(position(this));
+            }
+        } else {
+            positions.push(position(coord));
+        }
+
+        small_index = (small_index as i32 + is_smaller) as usize;
+        if !(FIRST_MAGIC_INT..MAGIC_INTS.len()).contains(&small_index) {
+            return Err(anyhow!("Invalid xtc small index: {}", small_index));
+        }
+        if is_smaller < 0 {
+            small_num = smaller;
+            smaller = if small_index > FIRST_MAGIC_INT {
+                MAGIC_INTS[small_index - 1] / 2
+            } else {
+                0
+            };
+        } else if is_smaller > 0 {
+            smaller = small_num;
+            small_num = MAGIC_INTS[small_index] / 2;
+        }
+    }
+    positions.truncate(atom_count);
+    Ok(positions)
+}
+
+/// bits needed to store values up to `size`
+fn size_of_int(size: u32) -> u32 {
+    let mut num: u64 = 1;
+    let mut bits = 0;
+    while size as u64 >= num && bits < 32 {
+        bits += 1;
+        num <<= 1;
+    }
+    bits
+}
+
+/// bits needed to store the 3 values as one number (x * sizes[1] + y) * sizes[2] + z
+fn size_of_ints(sizes: [u32; 3]) -> u32 {
+    let mut bytes = [0u32; 32];
+    bytes[0] = 1;
+    let mut byte_count = 1;
+    for size in sizes {
+        let mut tmp: u64 = 0;
+        let mut i = 0;
+        while i < byte_count {
+            tmp += bytes[i] as u64 * size as u64;
+            bytes[i] = (tmp & 0xff) as u32;
+            tmp >>= 8;
+            i += 1;
+        }
+        while tmp != 0 {
+            bytes[i] = (tmp & 0xff) as u32;
+            tmp >>= 8;
+            i += 1;
+        }
+        byte_count = i;
+    }
+    let mut num = 1;
+    let mut bits = 0;
+    while bytes[byte_count - 1] >= num {
+        bits += 1;
+        num *= 2;
+    }
+    bits + (byte_count as u32 - 1) * 8
+}
+
+/// reads the compressed xtc coordinates, most significant bits first
+struct BitReader<'a> {
+    data: &'a [u8],
+    index: usize,
+    last_bits: u32,
+    last_byte: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            index: 0,
+            last_bits: 0,
+            last_byte: 0,
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<u32> {
+        let byte = self
+            .data
+            .get(self.index)
+            .ok_or_else(|| anyhow!("Xtc compressed coordinates end unexpectedly."))?;
+        self.index += 1;
+        Ok(*byte as u32)
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        let mask = if count >= 32 {
+            u32::MAX
+        } else {
+            (1 << count) - 1
+        };
+        let mut remaining = count;
+        let mut num = 0;
+        while remaining >= 8 {
+            self.last_byte = (self.last_byte << 8) | self.next_byte()?;
+            num |= (self.last_byte >> self.last_bits) << (remaining - 8);
+            remaining -= 8;
+        }
+        if remaining > 0 {
+            if self.last_bits < remaining {
+                self.last_bits += 8;
+                self.last_byte = (self.last_byte << 8) | self.next_byte()?;
+            }
+            self.last_bits -= remaining;
+            num |= (self.last_byte >> self.last_bits) & ((1 << remaining) - 1);
+        }
+        Ok(num & mask)
+    }
+
+    /// 3 values stored as one number of `bit_count` bits, see `size_of_ints`
+    fn ints(&mut self, bit_count: u32, sizes: [u32; 3]) -> Result<[i32; 3]> {
+        let mut bytes = [0u32; 32];
+        let mut byte_count = 0;
+        let mut remaining = bit_count;
+        while remaining > 8 {
+            bytes[byte_count] = self.bits(8)?;
+            byte_count += 1;
+            remaining -= 8;
+        }
+        if remaining > 0 {
+            bytes[byte_count] = self.bits(remaining)?;
+            byte_count += 1;
+        }
+
+        let mut nums = [0; 3];
+        for i in [2, 1] {
+            let size = sizes[i] as u64;
+            let mut num: u64 = 0;
+            for byte in bytes[..byte_count].iter_mut().rev() {
+                num = (num << 8) | *byte as u64;
+                let quotient = num / size;
+                *byte = quotient as u32;
+                num -= quotient * size;
+            }
+            nums[i] = num as i32;
+        }
+        nums[0] = (bytes[0] | (bytes[1] << 8) | (bytes[2] << 16) | (bytes[3] << 24)) as i32;
+        Ok(nums)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{index_dcd, index_xtc, read_frame, size_of_ints, Trajectory, TrajectoryFrame};
+    use bevy::asset::AssetPath;
+    use bevy::math::Vec3;
+    use bevy::tasks::{block_on, futures_lite::io::Cursor};
+
+    fn read(trajectory: &Trajectory, bytes: &[u8], frame: usize) -> TrajectoryFrame {
+        let mut reader = Cursor::new(bytes.to_vec());
+        let range = trajectory.frames[frame].clone();
+        block_on(read_frame(
+            &mut reader,
+            trajectory.format,
+            trajectory.atom_count,
+            range,
+        ))
+        .unwrap()
+    }
+
+    fn dcd_record(out: &mut Vec<u8>, data: &[u8]) {
+        out.extend((data.len() as i32).to_le_bytes());
+        out.extend(data);
+        out.extend((data.len() as i32).to_le_bytes());
+    }
+
+    #[test]
+    fn test_reads_dcd_frames_with_unit_cell() {
+        let mut bytes = vec![];
+        let mut header = b"CORD".to_vec();
+        let mut control = [0i32; 20];
+        control[0] = 2;
+        control[10] = 1;
+        control[19] = 24;
+        header.extend(control.iter().flat_map(|c| c.to_le_bytes()));
+        dcd_record(&mut bytes, &header);
+        let mut title = 1i32.to_le_bytes().to_vec();
+        title.extend([b' '; 80]);
+        dcd_record(&mut bytes, &title);
+        dcd_record(&mut bytes, &2i32.to_le_bytes());
+        for frame in 0..2 {
+            // a, gamma, b, beta, alpha, c
+            let cell = [10.0f64, 90.0, 20.0, 90.0, 90.0, 30.0];
+            dcd_record(
+                &mut bytes,
+                &cell
+                    .iter()
+                    .flat_map(|c| c.to_le_bytes())
+                    .collect::<Vec<_>>(),
+            );
+            for axis in 0..3 {
+                let coords = [frame as f32, 1.0 + axis as f32];
+                dcd_record(
+                    &mut bytes,
+                    &coords
+                        .iter()
+                        .flat_map(|c| c.to_le_bytes())
+                        .collect::<Vec<_>>(),
+                );
+            }
+        }
+        // a frame still being written
+        bytes.extend([0; 10]);
+
+        let trajectory = block_on(index_dcd(
+            &mut Cursor::new(&bytes),
+            AssetPath::from("a.dcd"),
+        ))
+        .unwrap();
+        assert_eq!(trajectory.atom_count, 2);
+        assert_eq!(trajectory.frame_count(), 2);
+
+        let frame = read(&trajectory, &bytes, 1);
+        assert_eq!(frame.positions, vec![Vec3::ONE, Vec3::new(1.0, 2.0, 3.0)]);
+        let cell = frame.unit_cell.unwrap();
+        assert_eq!(cell.lengths, Vec3::new(10.0, 20.0, 30.0));
+        assert_eq!(cell.angles, Vec3::splat(90.0));
+    }
+
+    /// xtc frame header, with a cubic box of 2 nm
+    fn xtc_header(atom_count: i32) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend(1995i32.to_be_bytes());
+        bytes.extend(atom_count.to_be_bytes());
+        bytes.extend(0i32.to_be_bytes());
+        bytes.extend(0f32.to_be_bytes());
+        for i in 0..9 {
+            let value: f32 = if i % 4 == 0 { 2.0 } else { 0.0 };
+            bytes.extend(value.to_be_bytes());
+        }
+        bytes.extend(atom_count.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_reads_uncompressed_xtc_frames() {
+        let mut bytes = vec![];
+        for frame in 0..3 {
+            bytes.extend(xtc_header(2));
+            for value in [0.1f32, 0.2, 0.3, frame as f32, 0.0, 0.0] {
+                bytes.extend(value.to_be_bytes());
+            }
+        }
+
+        let trajectory = block_on(index_xtc(
+            &mut Cursor::new(&bytes),
+            AssetPath::from("a.xtc"),
+        ))
+        .unwrap();
+        assert_eq!(trajectory.atom_count, 2);
+        assert_eq!(trajectory.frame_count(), 3);
+
+        let frame = read(&trajectory, &bytes, 2);
+        // nm to Å
+        assert!(frame.positions[0].distance(Vec3::new(1.0, 2.0, 3.0)) < 1e-5);
+        assert_eq!(frame.positions[1], Vec3::new(20.0, 0.0, 0.0));
+        let cell = frame.unit_cell.unwrap();
+        assert_eq!(cell.lengths, Vec3::splat(20.0));
+        assert_eq!(cell.angles, Vec3::splat(90.0));
+    }
+
+    /// writes most significant bits first, like the xtc compression
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        bit_count: usize,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, value: u64, count: u32) {
+            for i in (0..count).rev() {
+                if self.bit_count == 8 * self.bytes.len() {
+                    self.bytes.push(0);
+                }
+                if (value >> i) & 1 == 1 {
+                    *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bit_count % 8);
+                }
+                self.bit_count += 1;
+            }
+        }
+
+        fn write_ints(&mut self, nums: [u32; 3], sizes: [u32; 3]) {
+            let mut num = (nums[0] as u128 * sizes[1] as u128 + nums[1] as u128) * sizes[2] as u128
+                + nums[2] as u128;
+            let mut remaining = size_of_ints(sizes);
+            while remaining > 8 {
+                self.write((num & 0xff) as u64, 8);
+                num >>= 8;
+                remaining -= 8;
+            }
+            self.write(num as u64, remaining);
+        }
+    }
+
+    #[test]
+    fn test_reads_compressed_xtc_frame() {
+        // coordinates * precision (1000 per nm)
+        let coords: Vec<[i32; 3]> = (0..12)
+            .map(|i| [i * 100, i * 50 + 7, 1000 - i * 30])
+            .collect();
+        let min = [0, 7, 670];
+        let max = [1100, 557, 1000];
+        let sizes = [0, 1, 2].map(|i| (max[i] - min[i] + 1) as u32);
+
+        let mut writer = BitWriter::default();
+        for coord in &coords {
+            let nums = [0, 1, 2].map(|i| (coord[i] - min[i]) as u32);
+            writer.write_ints(nums, sizes);
+            // no run of small differences
+            writer.write(0, 1);
+        }
+
+        let mut bytes = xtc_header(12);
+        bytes.extend(1000f32.to_be_bytes());
+        for value in min.iter().chain(&max) {
+            bytes.extend(value.to_be_bytes());
+        }
+        bytes.extend(9i32.to_be_bytes());
+        bytes.extend((writer.bytes.len() as i32).to_be_bytes());
+        bytes.extend(&writer.bytes);
+        bytes.resize(bytes.len().div_ceil(4) * 4, 0);
+
+        let trajectory = block_on(index_xtc(
+            &mut Cursor::new(&bytes),
+            AssetPath::from("a.xtc"),
+        ))
+        .unwrap();
+        assert_eq!(trajectory.frame_count(), 1);
+        assert_eq!(trajectory.frames[0].end, bytes.len() as u64);
+
+        let frame = read(&trajectory, &bytes, 0);
+        assert_eq!(frame.positions.len(), 12);
+        for (position, coord) in frame.positions.iter().zip(&coords) {
+            let expected = Vec3::new(coord[0] as f32, coord[1] as f32, coord[2] as f32) / 100.0;
+            assert!(position.distance(expected) < 1e-4);
+        }
+    }
+}
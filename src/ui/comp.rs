@@ -1,7 +1,9 @@
 use bevy::{
-    color::palettes::css::{BLACK, WHITE},
+    color::palettes::css::{BLACK, GRAY, WHITE},
     prelude::*,
+    ui::RelativeCursorPosition,
 };
+use bevy_simple_text_input::TextInputBundle;
 
 use super::component::PopupMarker;
 
@@ -62,6 +64,85 @@ pub fn row() -> NodeBundle {
     }
 }
 
+pub fn column() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Relative,
+            flex_direction: FlexDirection::Column,
+            width: Val::Percent(100.0),
+            ..default()
+        },
+        ..default()
+    }
+}
+
+/// panel docked to the bottom left corner, hidden until something is inspected
+pub fn inspector_panel() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            flex_direction: FlexDirection::Column,
+            left: Val::Px(0.0),
+            bottom: Val::Px(0.0),
+            width: Val::Px(260.0),
+            padding: UiRect::all(Val::Px(10.0)),
+            display: Display::None,
+            ..default()
+        },
+        background_color: BLACK.into(),
+        ..default()
+    }
+}
+
+/// column with the playback controls, hidden until a trajectory is loaded
+pub fn playback_container() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Relative,
+            flex_direction: FlexDirection::Column,
+            width: Val::Percent(100.0),
+            display: Display::None,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+/// track of a horizontal slider, the position is read with `RelativeCursorPosition`
+pub fn slider_track() -> (NodeBundle, Interaction, RelativeCursorPosition) {
+    (
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(60.0),
+                height: Val::Px(8.0),
+                margin: UiRect {
+                    top: Val::Px(11.0),
+                    right: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            background_color: GRAY.into(),
+            ..default()
+        },
+        Interaction::default(),
+        RelativeCursorPosition::default(),
+    )
+}
+
+/// filled part of a slider, the width is the value
+pub fn slider_fill() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Percent(0.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        background_color: WHITE.into(),
+        ..default()
+    }
+}
+
 pub fn bottom_row() -> NodeBundle {
     NodeBundle {
         style: Style {
@@ -196,6 +277,31 @@ pub fn generate_label(font: &Handle<Font>, label: &str) -> TextBundle {
     }
 }
 
+/// single line text input, inactive until clicked (see `focus`)
+pub fn text_input(font: &Handle<Font>, placeholder: &str) -> (NodeBundle, TextInputBundle) {
+    (
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                border: UiRect::all(Val::Px(1.0)),
+                padding: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            border_color: GRAY.into(),
+            background_color: BLACK.into(),
+            ..default()
+        },
+        TextInputBundle::default()
+            .with_text_style(TextStyle {
+                font: font.clone(),
+                font_size: 14.0,
+                color: Color::WHITE,
+            })
+            .with_placeholder(placeholder, None)
+            .with_inactive(true),
+    )
+}
+
 pub fn add_controls_box(commands: &mut Commands, font: &Handle<Font>) {
     let fullscreen_parent = NodeBundle {
         style: Style {
@@ -217,7 +323,7 @@ pub fn add_controls_box(commands: &mut Commands, font: &Handle<Font>) {
             left: Val::Auto,
             right: Val::Auto,
             width: Val::Px(250.),
-            height: Val::Px(200.),
+            height: Val::Auto,
             padding: UiRect::all(Val::Px(10.0)),
             ..default()
         },
@@ -241,13 +347,31 @@ pub fn add_controls_box(commands: &mut Commands, font: &Handle<Font>) {
     let label7_id = commands
         .spawn(control_row(font, "Rotate around x: x / shift-x"))
         .id();
+    let label8_id = commands
+        .spawn(control_row(font, "Select: click / shift-click"))
+        .id();
+    let label9_id = commands
+        .spawn(control_row(font, "Box select: right drag"))
+        .id();
+    let label10_id = commands
+        .spawn(control_row(font, "Lasso select: alt-right drag"))
+        .id();
+    let label11_id = commands
+        .spawn(control_row(font, "Measure: D / A / T buttons"))
+        .id();
+    let label12_id = commands
+        .spawn(control_row(font, "  in panel, then click atoms"))
+        .id();
 
     let full_screen_parent_id = commands.spawn((fullscreen_parent, PopupMarker)).id();
-    let parent_id = commands.spawn(parent).id();
+    let parent_id = commands
+        .spawn((parent, RelativeCursorPosition::default()))
+        .id();
 
     commands.entity(full_screen_parent_id).add_child(parent_id);
     commands.entity(parent_id).push_children(&[
         header_id, label1_id, label2_id, label3_id, label4_id, label5_id, label6_id, label7_id,
+        label8_id, label9_id, label10_id, label11_id, label12_id,
     ]);
 }
 
@@ -1,5 +1,7 @@
 use bevy::{ecs::query::QueryData, prelude::Component};
 
+use crate::scene::measurement::MeasureMode;
+
 #[derive(Component, Default, QueryData)]
 pub struct RotXLabelMarker;
 #[derive(Component, Default)]
@@ -7,6 +9,12 @@ pub struct RotYLabelMarker;
 #[derive(Component, Default)]
 pub struct RotZLabelMarker;
 
+/// the side panel's content, moved up and down with the mouse wheel while the cursor is over the panel
+#[derive(Component, Default)]
+pub struct SidePanelScroll {
+    pub position: f32,
+}
+
 #[derive(Component, Default)]
 pub struct SmilesInputMarker;
 
@@ -14,7 +22,7 @@ pub struct SmilesInputMarker;
 pub struct LoadMol2ButtonMarker;
 
 #[derive(Component, Default)]
-pub struct TooltipMarker;
+pub struct ExportMol2ButtonMarker;
 
 #[derive(Component, Default)]
 pub struct StyleBallStickMarker;
@@ -22,6 +30,27 @@ pub struct StyleBallStickMarker;
 pub struct StyleBallMarker;
 #[derive(Component, Default)]
 pub struct StyleStickMarker;
+#[derive(Component, Default)]
+pub struct StyleCartoonMarker;
+#[derive(Component, Default)]
+pub struct StyleSurfaceMarker;
+#[derive(Component, Default)]
+pub struct CartoonColorButtonMarker;
+
+/// buttons of the surface settings
+#[derive(Component)]
+pub enum SurfaceButton {
+    Kind,
+    Color,
+    ProbeDown,
+    ProbeUp,
+    ResolutionDown,
+    ResolutionUp,
+    OpacityDown,
+    OpacityUp,
+}
+#[derive(Component, Default)]
+pub struct SurfaceLabelMarker;
 
 #[derive(Component, Default)]
 pub struct ControlsButtonMarker;
@@ -32,11 +61,109 @@ pub struct PopupMarker;
 #[derive(Component, Default)]
 pub struct MolNameMarker;
 
+#[derive(Component, Default)]
+pub struct LoadErrorLabelMarker;
+
+#[derive(Component, Default)]
+pub struct PrevRecordButtonMarker;
+#[derive(Component, Default)]
+pub struct NextRecordButtonMarker;
+#[derive(Component, Default)]
+pub struct OverlayRecordsButtonMarker;
+#[derive(Component, Default)]
+pub struct RecordLabelMarker;
+
 #[derive(Component, Default)]
 pub enum MolExampleFile {
     #[default]
     Benzene,
     _117,
     _1ubq,
+    _1ubqPdb,
     _2bbv,
+    Sdf,
 }
+
+/// toggles `MolScene::force_bond_perception`
+#[derive(Component, Default)]
+pub struct PerceiveBondsButtonMarker;
+
+/// toggles `MolScene::impostors`
+#[derive(Component, Default)]
+pub struct ImpostorsButtonMarker;
+
+/// toggles `LodStyle::enabled`
+#[derive(Component, Default)]
+pub struct LodButtonMarker;
+
+/// cycles `Selection::by`
+#[derive(Component, Default)]
+pub struct SelectByButtonMarker;
+#[derive(Component, Default)]
+pub struct ClearSelectionButtonMarker;
+/// number of selected atoms
+#[derive(Component, Default)]
+pub struct SelectionLabelMarker;
+/// selects the atoms matching a query, see `parse_atom_query`
+#[derive(Component, Default)]
+pub struct AtomQueryInputMarker;
+#[derive(Component, Default)]
+pub struct AtomQueryErrorLabelMarker;
+#[derive(Component, Default)]
+pub struct HideSelectedButtonMarker;
+#[derive(Component, Default)]
+pub struct ColorSelectedButtonMarker;
+#[derive(Component, Default)]
+pub struct ZoomSelectedButtonMarker;
+/// shows the hidden atoms and resets the colors
+#[derive(Component, Default)]
+pub struct ResetAtomsButtonMarker;
+
+/// switches to the measure mode, or back to selecting if it's active
+#[derive(Component)]
+pub struct MeasureModeButton(pub MeasureMode);
+/// what to click in the current measure mode
+#[derive(Component, Default)]
+pub struct MeasureModeLabelMarker;
+/// contains a row per measurement
+#[derive(Component, Default)]
+pub struct MeasurementListMarker;
+/// by `Measurement::id`
+#[derive(Component)]
+pub struct MeasurementRowMarker(pub usize);
+#[derive(Component)]
+pub struct MeasurementRowLabelMarker(pub usize);
+#[derive(Component)]
+pub enum MeasurementButton {
+    Copy(usize),
+    Delete(usize),
+}
+
+/// docked panel with the record of the hovered or selected atom (or hovered bond), hidden if there's none
+#[derive(Component, Default)]
+pub struct InspectorMarker;
+#[derive(Component, Default)]
+pub struct InspectorLabelMarker;
+
+/// contains the playback controls, only shown for trajectories
+#[derive(Component, Default)]
+pub struct PlaybackContainerMarker;
+#[derive(Component, Default)]
+pub struct PlayButtonMarker;
+#[derive(Component, Default)]
+pub struct LoopButtonMarker;
+#[derive(Component, Default)]
+pub struct FpsDownButtonMarker;
+#[derive(Component, Default)]
+pub struct FpsUpButtonMarker;
+#[derive(Component, Default)]
+pub struct PlaybackLabelMarker;
+#[derive(Component, Default)]
+pub struct FrameSliderMarker;
+#[derive(Component, Default)]
+pub struct FrameSliderFillMarker;
+#[derive(Component, Default)]
+pub struct FrameLabelMarker;
+/// periodic box of the shown trajectory frame
+#[derive(Component, Default)]
+pub struct BoxLabelMarker;
@@ -1,17 +1,34 @@
 use bevy::prelude::Commands;
 
 use crate::{
+    scene::{
+        measurement::{MeasureMode, Measurement},
+        resource::{SurfaceColor, SurfaceStyle},
+    },
+    surface::SurfaceKind,
     ui::comp::{
-        button_bg, button_text, generate_header, generate_info_label, row, spacer,
-        square_button_bg, square_button_text, tooltip,
+        button_bg, button_text, column, generate_header, generate_info_label, inspector_panel,
+        playback_container, row, slider_fill, slider_track, spacer, square_button_bg,
+        square_button_text, text_input, tooltip,
     },
-    ui::component::TooltipMarker,
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, ui::RelativeCursorPosition};
 
 use super::{
     comp::{bottom_row, generate_label},
-    component::{ControlsButtonMarker, StyleBallMarker, StyleBallStickMarker, StyleStickMarker},
+    component::{
+        AtomQueryErrorLabelMarker, AtomQueryInputMarker, BoxLabelMarker,
+        ClearSelectionButtonMarker, ColorSelectedButtonMarker, ControlsButtonMarker,
+        FpsDownButtonMarker, FpsUpButtonMarker, FrameLabelMarker, FrameSliderFillMarker,
+        FrameSliderMarker, HideSelectedButtonMarker, InspectorLabelMarker, InspectorMarker,
+        LoopButtonMarker, MeasureModeButton, MeasureModeLabelMarker, MeasurementButton,
+        MeasurementListMarker, MeasurementRowLabelMarker, MeasurementRowMarker,
+        NextRecordButtonMarker, OverlayRecordsButtonMarker, PlayButtonMarker,
+        PlaybackContainerMarker, PlaybackLabelMarker, PrevRecordButtonMarker, RecordLabelMarker,
+        ResetAtomsButtonMarker, SelectByButtonMarker, SelectionLabelMarker, StyleBallMarker,
+        StyleBallStickMarker, StyleCartoonMarker, StyleStickMarker, StyleSurfaceMarker,
+        SurfaceButton, SurfaceLabelMarker, ZoomSelectedButtonMarker,
+    },
 };
 
 /// adds a generic vertical spacer element with fixed height
@@ -29,6 +46,223 @@ pub fn add_style_row(commands: &mut Commands, font: &Handle<Font>, root_id: Enti
     add_square_button(commands, row_id, font, "BS", StyleBallStickMarker);
     add_square_button(commands, row_id, font, "S", StyleStickMarker);
     add_square_button(commands, row_id, font, "B", StyleBallMarker);
+    add_square_button(commands, row_id, font, "C", StyleCartoonMarker);
+    add_square_button(commands, row_id, font, "Sf", StyleSurfaceMarker);
+}
+
+/// surface type, color, probe radius, resolution and opacity buttons, and a label with the values
+pub fn add_surface_rows(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    root_id: Entity,
+    style: &SurfaceStyle,
+) {
+    add_button(
+        commands,
+        root_id,
+        font,
+        &surface_kind_text(style.kind),
+        SurfaceButton::Kind,
+    );
+    add_button(
+        commands,
+        root_id,
+        font,
+        &surface_color_text(style.color),
+        SurfaceButton::Color,
+    );
+
+    for (label, down, up) in [
+        ("P", SurfaceButton::ProbeDown, SurfaceButton::ProbeUp),
+        (
+            "R",
+            SurfaceButton::ResolutionDown,
+            SurfaceButton::ResolutionUp,
+        ),
+        ("O", SurfaceButton::OpacityDown, SurfaceButton::OpacityUp),
+    ] {
+        let row_id = commands.spawn(row()).id();
+        commands.entity(root_id).add_child(row_id);
+        let label_id = commands.spawn(generate_label(font, label)).id();
+        commands.entity(row_id).add_child(label_id);
+        add_square_button(commands, row_id, font, "-", down);
+        add_square_button(commands, row_id, font, "+", up);
+    }
+    add_label_with_marker(
+        commands,
+        root_id,
+        font,
+        &surface_values_text(style),
+        SurfaceLabelMarker,
+    );
+}
+
+pub fn surface_kind_text(kind: SurfaceKind) -> String {
+    let kind = match kind {
+        SurfaceKind::SolventAccessible => "SAS",
+        SurfaceKind::SolventExcluded => "SES",
+        SurfaceKind::Gaussian => "Gaussian",
+    };
+    format!("Surface: {}", kind)
+}
+
+pub fn surface_color_text(color: SurfaceColor) -> String {
+    let color = match color {
+        SurfaceColor::Element => "element",
+        SurfaceColor::Charge => "charge",
+        SurfaceColor::Hydrophobicity => "hydrophob.",
+    };
+    format!("Color: {}", color)
+}
+
+/// probe radius, resolution and opacity
+pub fn surface_values_text(style: &SurfaceStyle) -> String {
+    format!(
+        "probe: {:.1}\ngrid: {:.2}\nopacity: {:.1}",
+        style.probe_radius, style.resolution, style.opacity
+    )
+}
+
+/// prev / next / overlay buttons and position label, to step through the molecules of a file
+pub fn add_record_row(commands: &mut Commands, font: &Handle<Font>, root_id: Entity) {
+    let row = row();
+
+    let row_id = commands.spawn(row).id();
+    commands.entity(root_id).add_child(row_id);
+
+    add_square_button(commands, row_id, font, "<", PrevRecordButtonMarker);
+    add_square_button(commands, row_id, font, ">", NextRecordButtonMarker);
+    add_square_button(commands, row_id, font, "All", OverlayRecordsButtonMarker);
+    add_label_with_marker(commands, row_id, font, "", RecordLabelMarker);
+}
+
+/// play / loop / fps buttons and a frame slider, to play trajectories
+pub fn add_playback_rows(commands: &mut Commands, font: &Handle<Font>, root_id: Entity) {
+    let container_id = commands
+        .spawn((playback_container(), PlaybackContainerMarker))
+        .id();
+    commands.entity(root_id).add_child(container_id);
+
+    add_header(commands, container_id, font, "Trajectory:");
+
+    let row_id = commands.spawn(row()).id();
+    commands.entity(container_id).add_child(row_id);
+    add_square_button(commands, row_id, font, ">", PlayButtonMarker);
+    add_square_button(commands, row_id, font, "L", LoopButtonMarker);
+    add_square_button(commands, row_id, font, "-", FpsDownButtonMarker);
+    add_square_button(commands, row_id, font, "+", FpsUpButtonMarker);
+    add_label_with_marker(commands, row_id, font, "", PlaybackLabelMarker);
+
+    let row_id = commands.spawn(row()).id();
+    commands.entity(container_id).add_child(row_id);
+    let slider_id = commands
+        .spawn((slider_track(), FrameSliderMarker))
+        .with_children(|parent| {
+            parent.spawn((slider_fill(), FrameSliderFillMarker));
+        })
+        .id();
+    commands.entity(row_id).add_child(slider_id);
+    add_label_with_marker(commands, row_id, font, "", FrameLabelMarker);
+
+    add_label_with_marker(commands, container_id, font, "", BoxLabelMarker);
+}
+
+/// the number of selected atoms, a button to clear them, what clicking selects, a query input
+/// and what can be done with the selected atoms
+pub fn add_selection_rows(commands: &mut Commands, font: &Handle<Font>, root_id: Entity) {
+    add_header(commands, root_id, font, "Selection:");
+
+    let row_id = commands.spawn(row()).id();
+    commands.entity(root_id).add_child(row_id);
+    add_square_button(commands, row_id, font, "x", ClearSelectionButtonMarker);
+    add_label_with_marker(commands, row_id, font, "none", SelectionLabelMarker);
+
+    add_button(
+        commands,
+        root_id,
+        font,
+        "Select by: atom",
+        SelectByButtonMarker,
+    );
+    add_text_input(
+        commands,
+        root_id,
+        font,
+        "e.g. chain A",
+        AtomQueryInputMarker,
+    );
+    add_label_with_marker(commands, root_id, font, "", AtomQueryErrorLabelMarker);
+    add_button(commands, root_id, font, "Hide", HideSelectedButtonMarker);
+    add_button(commands, root_id, font, "Color", ColorSelectedButtonMarker);
+    add_button(commands, root_id, font, "Zoom to", ZoomSelectedButtonMarker);
+    add_button(commands, root_id, font, "Show all", ResetAtomsButtonMarker);
+}
+
+/// distance / angle / dihedral (torsion) mode buttons, what to pick, and the list of measurements
+pub fn add_measurement_rows(commands: &mut Commands, font: &Handle<Font>, root_id: Entity) {
+    add_header(commands, root_id, font, "Measure:");
+
+    let row_id = commands.spawn(row()).id();
+    commands.entity(root_id).add_child(row_id);
+    for (label, mode) in [
+        ("D", MeasureMode::Distance),
+        ("A", MeasureMode::Angle),
+        ("T", MeasureMode::Dihedral),
+    ] {
+        add_square_button(commands, row_id, font, label, MeasureModeButton(mode));
+    }
+    add_label_with_marker(
+        commands,
+        root_id,
+        font,
+        &measure_mode_text(MeasureMode::Off, 0),
+        MeasureModeLabelMarker,
+    );
+
+    let list_id = commands.spawn((column(), MeasurementListMarker)).id();
+    commands.entity(root_id).add_child(list_id);
+}
+
+/// the value of a measurement, with copy and delete buttons
+pub fn add_measurement_row(
+    commands: &mut Commands,
+    list_id: Entity,
+    font: &Handle<Font>,
+    measurement: &Measurement,
+) {
+    let id = measurement.id;
+    let row_id = commands.spawn((row(), MeasurementRowMarker(id))).id();
+    commands.entity(list_id).add_child(row_id);
+    add_label_with_marker(
+        commands,
+        row_id,
+        font,
+        &measurement.text(),
+        MeasurementRowLabelMarker(id),
+    );
+    add_square_button(commands, row_id, font, "c", MeasurementButton::Copy(id));
+    add_square_button(commands, row_id, font, "x", MeasurementButton::Delete(id));
+}
+
+pub fn measure_mode_text(mode: MeasureMode, picked: usize) -> String {
+    match mode {
+        MeasureMode::Off => "off".to_string(),
+        _ => format!("pick atom {} / {}", picked + 1, mode.atom_count()),
+    }
+}
+
+/// the inspector, docked outside of the right column
+pub fn add_inspector(commands: &mut Commands, font: &Handle<Font>) {
+    let panel_id = commands
+        .spawn((
+            inspector_panel(),
+            InspectorMarker,
+            // clicks on the panel don't select atoms behind it
+            RelativeCursorPosition::default(),
+        ))
+        .id();
+    add_header(commands, panel_id, font, "Inspector:");
+    add_label_with_marker(commands, panel_id, font, "", InspectorLabelMarker);
 }
 
 pub fn add_controls_row(commands: &mut Commands, font: &Handle<Font>, root_id: Entity) {
@@ -70,6 +304,22 @@ where
     spawned_label
 }
 
+/// adds text input to container
+pub fn add_text_input<T>(
+    commands: &mut Commands,
+    container_id: Entity,
+    font: &Handle<Font>,
+    placeholder: &str,
+    marker: T,
+) -> Entity
+where
+    T: Component,
+{
+    let input = commands.spawn((text_input(font, placeholder), marker)).id();
+    commands.entity(container_id).add_child(input);
+    input
+}
+
 /// adds a square button to container
 pub fn add_square_button<T>(
     commands: &mut Commands,
@@ -89,8 +339,12 @@ pub fn add_square_button<T>(
     commands.entity(container_id).add_child(button);
 }
 
-pub fn add_tooltip(commands: &mut Commands, pos: Vec2, text: String) {
-    commands.spawn((tooltip(pos, text), TooltipMarker));
+/// adds a label at a position of the viewport, moved by setting its style
+pub fn add_floating_label<T>(commands: &mut Commands, pos: Vec2, text: String, marker: T)
+where
+    T: Component,
+{
+    commands.spawn((tooltip(pos, text), marker));
 }
 
 pub fn add_button<T>(
@@ -6,21 +6,42 @@ pub mod resource;
 pub mod system;
 
 use self::{
-    helper::{add_controls_row, add_style_row},
+    helper::{
+        add_controls_row, add_inspector, add_measurement_rows, add_playback_rows, add_record_row,
+        add_selection_rows, add_style_row, add_surface_rows,
+    },
     system::{
-        close_popup_on_esc, controls_button_handler, focus, style_ball_button_handler,
-        style_ball_stick_button_handler, style_stick_button_handler,
+        close_popup_on_esc, controls_button_handler, focus, next_record_button_handler,
+        overlay_records_button_handler, prev_record_button_handler, style_ball_button_handler,
+        style_ball_stick_button_handler, style_cartoon_button_handler, style_stick_button_handler,
+        style_surface_button_handler, surface_button_handler,
     },
 };
+use crate::scene::resource::SurfaceStyle;
 use crate::ui::{
     helper::{add_button, add_header, add_spacer},
-    system::{load_file_button_handler, setup_info_labels},
+    system::{export_mol2_button_handler, load_file_button_handler, setup_info_labels},
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, ui::RelativeCursorPosition};
 use bevy_simple_text_input::{TextInputPlugin, TextInputSystem};
-use component::{MolExampleFile, MolNameMarker};
-use helper::add_label_with_marker;
-use system::{file_example_button_handler, update_ui_for_scene};
+use component::{
+    CartoonColorButtonMarker, ExportMol2ButtonMarker, ImpostorsButtonMarker, LoadErrorLabelMarker,
+    LoadMol2ButtonMarker, LodButtonMarker, MolExampleFile, MolNameMarker,
+    PerceiveBondsButtonMarker, SidePanelScroll, SmilesInputMarker,
+};
+use helper::{add_label_with_marker, add_text_input};
+use system::{
+    atom_query_submit_handler, clear_selection_button_handler, color_selected_button_handler,
+    hide_selected_button_handler, measure_mode_button_handler, measurement_button_handler,
+    reset_atoms_button_handler, scroll_side_panel, select_by_button_handler, update_inspector,
+    update_measurement_panel, update_selection_label, zoom_selected_button_handler,
+};
+use system::{
+    cartoon_color_button_handler, file_example_button_handler, fps_down_button_handler,
+    fps_up_button_handler, frame_slider_handler, impostors_button_handler, lod_button_handler,
+    loop_button_handler, perceive_bonds_button_handler, play_button_handler, smiles_submit_handler,
+    update_load_error_label, update_playback_ui, update_ui_for_scene,
+};
 
 pub fn add_ui(app: &mut App) {
     app.add_plugins(TextInputPlugin)
@@ -28,42 +49,122 @@ pub fn add_ui(app: &mut App) {
             Update,
             (
                 load_file_button_handler,
+                export_mol2_button_handler,
                 style_ball_stick_button_handler,
                 style_stick_button_handler,
                 style_ball_button_handler,
+                style_cartoon_button_handler,
+                cartoon_color_button_handler,
+                style_surface_button_handler,
+                surface_button_handler,
                 controls_button_handler,
                 close_popup_on_esc,
                 update_ui_for_scene,
+                update_load_error_label,
                 file_example_button_handler,
+                prev_record_button_handler,
+                next_record_button_handler,
+                overlay_records_button_handler,
+                smiles_submit_handler,
+                (
+                    perceive_bonds_button_handler,
+                    impostors_button_handler,
+                    lod_button_handler,
+                    select_by_button_handler,
+                    clear_selection_button_handler,
+                    update_selection_label,
+                    atom_query_submit_handler,
+                    hide_selected_button_handler,
+                    color_selected_button_handler,
+                    zoom_selected_button_handler,
+                    reset_atoms_button_handler,
+                    measure_mode_button_handler,
+                    measurement_button_handler,
+                    update_measurement_panel,
+                    update_inspector,
+                    scroll_side_panel,
+                ),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                play_button_handler,
+                loop_button_handler,
+                fps_down_button_handler,
+                fps_up_button_handler,
+                frame_slider_handler,
+                update_playback_ui,
             ),
         )
         .add_systems(Startup, (setup_ui, setup_info_labels))
         .add_systems(Update, focus.before(TextInputSystem));
 }
 
-/// adds right column with ui elements to scene
+/// adds right column with ui elements to scene.
+/// the column is taller than most windows, so its content is clipped and scrolled with the mouse wheel
 pub fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     let font = asset_server.load("embedded://mol/asset/fonts/FiraMono-Medium.ttf");
-    let root = commands.spawn(NodeBundle {
-        style: Style {
-            position_type: PositionType::Absolute,
-            flex_direction: FlexDirection::Column,
-            top: Val::Px(0.0),
-            right: Val::Px(0.0),
-            width: Val::Px(150.0),
-            height: Val::Percent(100.0),
-            padding: UiRect::all(Val::Px(10.0)),
-            ..default()
-        },
-        background_color: BackgroundColor(Color::BLACK),
-        ..default()
-    });
+    let panel_id = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    top: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    width: Val::Px(150.0),
+                    height: Val::Percent(100.0),
+                    overflow: Overflow::clip_y(),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::BLACK),
+                ..default()
+            },
+            RelativeCursorPosition::default(),
+        ))
+        .id();
 
-    let root_id = root.id();
+    let root_id = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    flex_shrink: 0.0,
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            SidePanelScroll::default(),
+        ))
+        .id();
+    commands.entity(panel_id).add_child(root_id);
 
     add_header(&mut commands, root_id, &font, "Mol name:");
     add_label_with_marker(&mut commands, root_id, &font, "", MolNameMarker);
+    add_label_with_marker(&mut commands, root_id, &font, "", LoadErrorLabelMarker);
+    add_spacer(&mut commands, root_id);
+    add_spacer(&mut commands, root_id);
+
+    add_button(
+        &mut commands,
+        root_id,
+        &font,
+        "Open file...",
+        LoadMol2ButtonMarker,
+    );
+    add_button(
+        &mut commands,
+        root_id,
+        &font,
+        "Export mol2",
+        ExportMol2ButtonMarker,
+    );
     add_spacer(&mut commands, root_id);
+
+    add_header(&mut commands, root_id, &font, "SMILES:");
+    add_text_input(&mut commands, root_id, &font, "e.g. CCO", SmilesInputMarker);
     add_spacer(&mut commands, root_id);
 
     add_header(&mut commands, root_id, &font, "Load example:");
@@ -88,6 +189,13 @@ pub fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
         "1ubq.mol2",
         MolExampleFile::_1ubq,
     );
+    add_button(
+        &mut commands,
+        root_id,
+        &font,
+        "1ubq.pdb",
+        MolExampleFile::_1ubqPdb,
+    );
     add_button(
         &mut commands,
         root_id,
@@ -95,12 +203,60 @@ pub fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
         "2bbv.mol2",
         MolExampleFile::_2bbv,
     );
+    add_button(
+        &mut commands,
+        root_id,
+        &font,
+        "examples.sdf",
+        MolExampleFile::Sdf,
+    );
+    add_record_row(&mut commands, &font, root_id);
+
+    add_spacer(&mut commands, root_id);
+    add_playback_rows(&mut commands, &font, root_id);
 
     add_spacer(&mut commands, root_id);
     add_spacer(&mut commands, root_id);
     add_header(&mut commands, root_id, &font, "Style:");
     add_style_row(&mut commands, &font, root_id);
+    add_button(
+        &mut commands,
+        root_id,
+        &font,
+        "Perceive bonds: off",
+        PerceiveBondsButtonMarker,
+    );
+    add_button(
+        &mut commands,
+        root_id,
+        &font,
+        "Impostors: off",
+        ImpostorsButtonMarker,
+    );
+    add_button(
+        &mut commands,
+        root_id,
+        &font,
+        "Level of detail: on",
+        LodButtonMarker,
+    );
+    add_button(
+        &mut commands,
+        root_id,
+        &font,
+        "Cartoon: chain",
+        CartoonColorButtonMarker,
+    );
+    add_surface_rows(&mut commands, &font, root_id, &SurfaceStyle::default());
+
+    add_spacer(&mut commands, root_id);
+    add_selection_rows(&mut commands, &font, root_id);
+
+    add_spacer(&mut commands, root_id);
+    add_measurement_rows(&mut commands, &font, root_id);
 
     add_spacer(&mut commands, root_id);
     add_controls_row(&mut commands, &font, root_id);
+
+    add_inspector(&mut commands, &font);
 }
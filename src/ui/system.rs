@@ -1,25 +1,71 @@
+use std::path::Path;
+
 use crate::{
-    mol2_asset_plugin::Mol2Molecule,
+    bounding_box::bounding_box_for,
+    clipboard::copy_text,
+    file_open_plugin::open_file_dialog,
+    file_save::save_file,
+    mol2_writer::write_mol2,
+    mol_collection::MolCollection,
+    molecule::Molecule,
     scene::{
-        event::UpdateSceneEvent,
-        resource::{MolRender, MolScene, MolSceneContent},
+        atom_query::parse_atom_query,
+        component::{MyAtom, MyBond},
+        event::{AddedBoundingBox, SelectionChangedEvent, UpdateSceneEvent, UpdateStyleEvent},
+        inspector::{atom_record, bond_record, Hovered, Inspected},
+        measurement::{MeasureMode, Measurement, Measurements},
+        resource::{
+            AtomOverrides, CartoonColor, MolEntities, MolRender, MolScene, MolSceneContent,
+            Playback, SurfaceColor, TrajectoryStream,
+        },
+        selection::{AtomRef, SelectBy, SelectMode, Selection},
+    },
+    smiles::parse_smiles,
+    surface::SurfaceKind,
+    ui::{
+        component::{ExportMol2ButtonMarker, LoadMol2ButtonMarker},
+        helper::{
+            add_info_labels, add_measurement_row, measure_mode_text, surface_color_text,
+            surface_kind_text, surface_values_text,
+        },
     },
-    ui::{component::LoadMol2ButtonMarker, helper::add_info_labels},
 };
+use anyhow::anyhow;
 use bevy::{
-    color::palettes::css::{BLUE, GRAY},
+    asset::{AssetLoadError, LoadState},
+    color::palettes::css::{AQUA, BLUE, FUCHSIA, GRAY, HOT_PINK, LIME, ORANGE, YELLOW},
+    input::mouse::{MouseScrollUnit, MouseWheel},
     prelude::*,
+    ui::RelativeCursorPosition,
 };
-use bevy_simple_text_input::TextInputInactive;
+use bevy_simple_text_input::{TextInputInactive, TextInputSubmitEvent};
 
 use super::{
     comp::add_controls_box,
     component::{
-        ControlsButtonMarker, MolExampleFile, MolNameMarker, PopupMarker, StyleBallMarker,
-        StyleBallStickMarker, StyleStickMarker,
+        AtomQueryErrorLabelMarker, AtomQueryInputMarker, BoxLabelMarker, CartoonColorButtonMarker,
+        ClearSelectionButtonMarker, ColorSelectedButtonMarker, ControlsButtonMarker,
+        FpsDownButtonMarker, FpsUpButtonMarker, FrameLabelMarker, FrameSliderFillMarker,
+        FrameSliderMarker, HideSelectedButtonMarker, ImpostorsButtonMarker, InspectorLabelMarker,
+        InspectorMarker, LoadErrorLabelMarker, LodButtonMarker, LoopButtonMarker,
+        MeasureModeButton, MeasureModeLabelMarker, MeasurementButton, MeasurementListMarker,
+        MeasurementRowLabelMarker, MeasurementRowMarker, MolExampleFile, MolNameMarker,
+        NextRecordButtonMarker, OverlayRecordsButtonMarker, PerceiveBondsButtonMarker,
+        PlayButtonMarker, PlaybackContainerMarker, PlaybackLabelMarker, PopupMarker,
+        PrevRecordButtonMarker, RecordLabelMarker, ResetAtomsButtonMarker, SelectByButtonMarker,
+        SelectionLabelMarker, SidePanelScroll, SmilesInputMarker, StyleBallMarker,
+        StyleBallStickMarker, StyleCartoonMarker, StyleStickMarker, StyleSurfaceMarker,
+        SurfaceButton, SurfaceLabelMarker, ZoomSelectedButtonMarker,
     },
 };
 
+/// colors of `color_selected_button_handler`, each press uses the next one
+const SELECTION_COLORS: [Srgba; 6] = [ORANGE, LIME, AQUA, FUCHSIA, YELLOW, HOT_PINK];
+/// margin (angstrom) around the atoms zoomed to, so a single atom isn't zoomed into
+const ZOOM_MARGIN: f32 = 2.0;
+/// pixels the side panel scrolls per mouse wheel line
+const SCROLL_LINE_HEIGHT: f32 = 30.0;
+
 /// removes all entities matching a query (1 filter)
 pub fn despawn_all_entities<T>(commands: &mut Commands, query: &Query<Entity, With<T>>)
 where
@@ -34,12 +80,39 @@ where
 #[allow(clippy::type_complexity)]
 pub fn load_file_button_handler(
     mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<LoadMol2ButtonMarker>)>,
-    asset_server: Res<AssetServer>,
-    mut scene: ResMut<MolScene>,
 ) {
     for interaction in &mut interaction_query {
         if interaction == &Interaction::Pressed {
-            load_example_file(&asset_server, &mut scene, &MolExampleFile::Benzene);
+            open_file_dialog();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn export_mol2_button_handler(
+    mut interaction_query: Query<
+        &Interaction,
+        (Changed<Interaction>, With<ExportMol2ButtonMarker>),
+    >,
+    scene: Res<MolScene>,
+    assets: Res<Assets<Molecule>>,
+    collections: Res<Assets<MolCollection>>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            match scene.content.molecule(&assets, &collections) {
+                Some(mol) => {
+                    let name = if mol.name.trim().is_empty() {
+                        "molecule".to_string()
+                    } else {
+                        mol.name.trim().replace(|c: char| !c.is_alphanumeric(), "_")
+                    };
+                    if let Err(e) = save_file(&format!("{}.mol2", name), &write_mol2(mol)) {
+                        eprintln!("Couldn't export molecule: {}", e);
+                    }
+                }
+                None => println!("No molecule to export."),
+            }
         }
     }
 }
@@ -53,34 +126,188 @@ fn load_example_file(
         MolExampleFile::Benzene => "benzene.mol2",
         MolExampleFile::_117 => "117_ideal.mol2",
         MolExampleFile::_1ubq => "1ubq.mol2",
+        MolExampleFile::_1ubqPdb => "1ubq.pdb",
         MolExampleFile::_2bbv => "2bbv.mol2",
+        MolExampleFile::Sdf => "examples.sdf",
     };
 
     let path = format!("embedded://mol/asset/{}", file_name);
-    let handle: Handle<Mol2Molecule> = asset_server.load(path);
 
-    scene.content = MolSceneContent::Mol2 {
-        handle,
-        // don't trigger update scene as the file may not be ready
-        // an Update system polls the handle instead
-        // this flag is set back to false when the file is ready
-        // the file stays in the scene state to be available for other re-building events
-        // (like changing the mol rendering type)
-        waiting_for_async_handle: true,
+    if let Err(e) = load_mol_file(asset_server, scene, path) {
+        eprintln!("Couldn't load example: {}", e);
+    }
+}
+
+/// starts loading a molecule file and sets it as scene content
+/// the scene content type depends on the loader, which is selected by the extension.
+/// trajectories (dcd, xtc) have no topology, they are played over the shown molecule
+pub fn load_mol_file(
+    asset_server: &AssetServer,
+    scene: &mut MolScene,
+    path: String,
+) -> anyhow::Result<()> {
+    let result = start_loading_mol_file(asset_server, scene, path);
+    // shown by `update_load_error_label`
+    scene.open_error = result.as_ref().err().map(|e| e.to_string());
+    result
+}
+
+fn start_loading_mol_file(
+    asset_server: &AssetServer,
+    scene: &mut MolScene,
+    path: String,
+) -> anyhow::Result<()> {
+    let extension = Path::new(&path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if let "dcd" | "xtc" = extension.as_str() {
+        if let MolSceneContent::Empty = scene.content {
+            return Err(anyhow!(
+                "Open a structure (e.g. pdb) before its trajectory."
+            ));
+        }
+        scene.trajectory = Some(asset_server.load(path));
+        return Ok(());
+    }
+
+    // don't trigger update scene as the file may not be ready
+    // an Update system polls the handle instead
+    // this flag is set back to false when the file is ready
+    // the file stays in the scene state to be available for other re-building events
+    // (like changing the mol rendering type)
+    scene.content = match extension.as_str() {
+        "pdb" | "ent" | "cif" | "mmcif" | "xyz" => MolSceneContent::Molecule {
+            handle: asset_server.load(path),
+            waiting_for_async_handle: true,
+        },
+        "mol2" | "sdf" | "sd" | "mol" => MolSceneContent::Collection {
+            handle: asset_server.load(path),
+            selected: 0,
+            overlay: false,
+            waiting_for_async_handle: true,
+        },
+        _ => return Err(anyhow!("Not supported file extension: {:?}", extension)),
     };
+    scene.trajectory = None;
+    Ok(())
+}
+
+/// builds a molecule from the submitted smiles and shows it
+pub fn smiles_submit_handler(
+    mut events: EventReader<TextInputSubmitEvent>,
+    smiles_input_query: Query<Entity, With<SmilesInputMarker>>,
+    mut mols: ResMut<Assets<Molecule>>,
+    mut scene: ResMut<MolScene>,
+) {
+    for event in events.read() {
+        if smiles_input_query.get(event.entity).is_err() {
+            continue;
+        }
+        match parse_smiles(&event.value) {
+            Ok(mol) => {
+                // the asset is available immediately,
+                // the flag just lets the loaded file poll rebuild the scene like with files
+                scene.content = MolSceneContent::Molecule {
+                    handle: mols.add(mol),
+                    waiting_for_async_handle: true,
+                };
+                scene.trajectory = None;
+                scene.open_error = None;
+            }
+            Err(e) => eprintln!("Couldn't build molecule from SMILES: {}", e),
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn prev_record_button_handler(
+    mut interaction_query: Query<
+        &Interaction,
+        (Changed<Interaction>, With<PrevRecordButtonMarker>),
+    >,
+    mut scene: ResMut<MolScene>,
+    collections: Res<Assets<MolCollection>>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            step_record(&mut scene, &collections, -1);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn next_record_button_handler(
+    mut interaction_query: Query<
+        &Interaction,
+        (Changed<Interaction>, With<NextRecordButtonMarker>),
+    >,
+    mut scene: ResMut<MolScene>,
+    collections: Res<Assets<MolCollection>>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            step_record(&mut scene, &collections, 1);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn overlay_records_button_handler(
+    mut interaction_query: Query<
+        &Interaction,
+        (Changed<Interaction>, With<OverlayRecordsButtonMarker>),
+    >,
+    mut scene: ResMut<MolScene>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            if let MolSceneContent::Collection {
+                overlay,
+                waiting_for_async_handle,
+                ..
+            } = &mut scene.content
+            {
+                *overlay = !*overlay;
+                *waiting_for_async_handle = true;
+            }
+        }
+    }
+}
+
+/// selects the previous / next molecule of a collection, wrapping around
+fn step_record(scene: &mut ResMut<MolScene>, collections: &Res<Assets<MolCollection>>, step: i32) {
+    if let MolSceneContent::Collection {
+        handle,
+        selected,
+        overlay,
+        waiting_for_async_handle,
+    } = &mut scene.content
+    {
+        if let Some(collection) = collections.get(handle) {
+            let len = collection.molecules.len() as i32;
+            if len > 0 {
+                *selected = (*selected as i32 + step).rem_euclid(len) as usize;
+                *overlay = false;
+                // re-uses the loading path, which also re-centers the camera on the new molecule
+                *waiting_for_async_handle = true;
+            }
+        }
+    }
 }
 
 #[allow(clippy::type_complexity)]
 pub fn style_ball_stick_button_handler(
     mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<StyleBallStickMarker>)>,
     mut scene: ResMut<MolScene>,
-    mut event_writer: EventWriter<UpdateSceneEvent>,
+    mut event_writer: EventWriter<UpdateStyleEvent>,
 ) {
     for interaction in &mut interaction_query {
         if interaction == &Interaction::Pressed {
             println!("setting render to ball stick");
             scene.render = MolRender::BallStick;
-            event_writer.send(UpdateSceneEvent);
+            event_writer.send(UpdateStyleEvent);
         }
     }
 }
@@ -89,13 +316,13 @@ pub fn style_ball_stick_button_handler(
 pub fn style_stick_button_handler(
     mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<StyleStickMarker>)>,
     mut scene: ResMut<MolScene>,
-    mut event_writer: EventWriter<UpdateSceneEvent>,
+    mut event_writer: EventWriter<UpdateStyleEvent>,
 ) {
     for interaction in &mut interaction_query {
         if interaction == &Interaction::Pressed {
             println!("setting render to stick");
             scene.render = MolRender::Stick;
-            event_writer.send(UpdateSceneEvent);
+            event_writer.send(UpdateStyleEvent);
         }
     }
 }
@@ -104,17 +331,623 @@ pub fn style_stick_button_handler(
 pub fn style_ball_button_handler(
     mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<StyleBallMarker>)>,
     mut scene: ResMut<MolScene>,
-    mut event_writer: EventWriter<UpdateSceneEvent>,
+    mut event_writer: EventWriter<UpdateStyleEvent>,
 ) {
     for interaction in &mut interaction_query {
         if interaction == &Interaction::Pressed {
             println!("setting render to ball");
             scene.render = MolRender::Ball;
+            event_writer.send(UpdateStyleEvent);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn style_cartoon_button_handler(
+    mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<StyleCartoonMarker>)>,
+    mut scene: ResMut<MolScene>,
+    mut event_writer: EventWriter<UpdateStyleEvent>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            println!("setting render to cartoon");
+            scene.render = MolRender::Cartoon;
+            event_writer.send(UpdateStyleEvent);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn style_surface_button_handler(
+    mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<StyleSurfaceMarker>)>,
+    mut scene: ResMut<MolScene>,
+    mut event_writer: EventWriter<UpdateStyleEvent>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            println!("setting render to surface");
+            scene.render = MolRender::Surface;
+            event_writer.send(UpdateStyleEvent);
+        }
+    }
+}
+
+/// changes the surface settings. the surface is rebuilt, except for the opacity, which is set on its material,
+/// and the coloring, which is set on its vertices
+pub fn surface_button_handler(
+    interaction_query: Query<(&Interaction, &SurfaceButton, &Children), Changed<Interaction>>,
+    mut texts: Query<&mut Text, Without<SurfaceLabelMarker>>,
+    mut labels: Query<&mut Text, With<SurfaceLabelMarker>>,
+    mut scene: ResMut<MolScene>,
+    mut event_writer: EventWriter<UpdateSceneEvent>,
+) {
+    for (interaction, button, children) in &interaction_query {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        let surface = &mut scene.surface;
+        let mut button_text = None;
+        match button {
+            SurfaceButton::Kind => {
+                surface.kind = match surface.kind {
+                    SurfaceKind::SolventExcluded => SurfaceKind::SolventAccessible,
+                    SurfaceKind::SolventAccessible => SurfaceKind::Gaussian,
+                    SurfaceKind::Gaussian => SurfaceKind::SolventExcluded,
+                };
+                button_text = Some(surface_kind_text(surface.kind));
+            }
+            SurfaceButton::Color => {
+                surface.color = match surface.color {
+                    SurfaceColor::Element => SurfaceColor::Charge,
+                    SurfaceColor::Charge => SurfaceColor::Hydrophobicity,
+                    SurfaceColor::Hydrophobicity => SurfaceColor::Element,
+                };
+                button_text = Some(surface_color_text(surface.color));
+            }
+            SurfaceButton::ProbeDown => {
+                surface.probe_radius = (surface.probe_radius - 0.2).max(0.2)
+            }
+            SurfaceButton::ProbeUp => surface.probe_radius = (surface.probe_radius + 0.2).min(3.0),
+            SurfaceButton::ResolutionDown => {
+                surface.resolution = (surface.resolution * 0.8).max(0.2)
+            }
+            SurfaceButton::ResolutionUp => {
+                surface.resolution = (surface.resolution * 1.25).min(2.0)
+            }
+            SurfaceButton::OpacityDown => surface.opacity = (surface.opacity - 0.1).max(0.1),
+            SurfaceButton::OpacityUp => surface.opacity = (surface.opacity + 0.1).min(1.0),
+        }
+
+        if let Some(button_text) = button_text {
+            for child in children {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value = button_text.clone();
+                }
+            }
+        }
+        for mut label in &mut labels {
+            label.sections[0].value = surface_values_text(&scene.surface);
+        }
+        let rebuild = !matches!(
+            button,
+            SurfaceButton::OpacityDown | SurfaceButton::OpacityUp | SurfaceButton::Color
+        );
+        if scene.render == MolRender::Surface && rebuild {
+            event_writer.send(UpdateSceneEvent);
+        }
+    }
+}
+
+/// switches the cartoon between coloring by chain and by secondary structure
+#[allow(clippy::type_complexity)]
+pub fn cartoon_color_button_handler(
+    interaction_query: Query<
+        (&Interaction, &Children),
+        (Changed<Interaction>, With<CartoonColorButtonMarker>),
+    >,
+    mut texts: Query<&mut Text>,
+    mut scene: ResMut<MolScene>,
+    mut event_writer: EventWriter<UpdateSceneEvent>,
+) {
+    for (interaction, children) in &interaction_query {
+        if interaction == &Interaction::Pressed {
+            let (color, label) = match scene.cartoon_color {
+                CartoonColor::Chain => (CartoonColor::SecondaryStructure, "structure"),
+                CartoonColor::SecondaryStructure => (CartoonColor::Chain, "chain"),
+            };
+            scene.cartoon_color = color;
+            for child in children {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value = format!("Cartoon: {}", label);
+                }
+            }
+            if scene.render == MolRender::Cartoon {
+                event_writer.send(UpdateSceneEvent);
+            }
+        }
+    }
+}
+
+/// switches between the bonds of the file and bonds perceived from the distances
+#[allow(clippy::type_complexity)]
+pub fn perceive_bonds_button_handler(
+    interaction_query: Query<
+        (&Interaction, &Children),
+        (Changed<Interaction>, With<PerceiveBondsButtonMarker>),
+    >,
+    mut texts: Query<&mut Text>,
+    mut scene: ResMut<MolScene>,
+) {
+    for (interaction, children) in &interaction_query {
+        if interaction == &Interaction::Pressed {
+            scene.force_bond_perception = !scene.force_bond_perception;
+            let state = if scene.force_bond_perception {
+                "on"
+            } else {
+                "off"
+            };
+            for child in children {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value = format!("Perceive bonds: {}", state);
+                }
+            }
+        }
+    }
+}
+
+/// switches between an entity per atom / bond and impostors (always used for large molecules)
+#[allow(clippy::type_complexity)]
+pub fn impostors_button_handler(
+    interaction_query: Query<
+        (&Interaction, &Children),
+        (Changed<Interaction>, With<ImpostorsButtonMarker>),
+    >,
+    mut texts: Query<&mut Text>,
+    mut scene: ResMut<MolScene>,
+    mut event_writer: EventWriter<UpdateSceneEvent>,
+) {
+    for (interaction, children) in &interaction_query {
+        if interaction == &Interaction::Pressed {
+            scene.impostors = !scene.impostors;
+            let state = if scene.impostors { "on" } else { "off" };
+            for child in children {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value = format!("Impostors: {}", state);
+                }
+            }
+            event_writer.send(UpdateSceneEvent);
+        }
+    }
+}
+
+/// switches between meshes chosen by their size on screen and the most detailed ones
+#[allow(clippy::type_complexity)]
+pub fn lod_button_handler(
+    interaction_query: Query<
+        (&Interaction, &Children),
+        (Changed<Interaction>, With<LodButtonMarker>),
+    >,
+    mut texts: Query<&mut Text>,
+    mut scene: ResMut<MolScene>,
+) {
+    for (interaction, children) in &interaction_query {
+        if interaction == &Interaction::Pressed {
+            scene.lod.enabled = !scene.lod.enabled;
+            let state = if scene.lod.enabled { "on" } else { "off" };
+            for child in children {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value = format!("Level of detail: {}", state);
+                }
+            }
+        }
+    }
+}
+
+/// cycles what clicking or dragging over atoms selects
+#[allow(clippy::type_complexity)]
+pub fn select_by_button_handler(
+    interaction_query: Query<
+        (&Interaction, &Children),
+        (Changed<Interaction>, With<SelectByButtonMarker>),
+    >,
+    mut texts: Query<&mut Text>,
+    mut selection: ResMut<Selection>,
+) {
+    for (interaction, children) in &interaction_query {
+        if interaction == &Interaction::Pressed {
+            let (by, label) = match selection.by {
+                SelectBy::Atom => (SelectBy::Residue, "residue"),
+                SelectBy::Residue => (SelectBy::Chain, "chain"),
+                SelectBy::Chain => (SelectBy::Element, "element"),
+                SelectBy::Element => (SelectBy::Atom, "atom"),
+            };
+            selection.by = by;
+            for child in children {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value = format!("Select by: {}", label);
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn clear_selection_button_handler(
+    interaction_query: Query<
+        &Interaction,
+        (Changed<Interaction>, With<ClearSelectionButtonMarker>),
+    >,
+    mut selection: ResMut<Selection>,
+    mut selection_events: EventWriter<SelectionChangedEvent>,
+) {
+    for interaction in &interaction_query {
+        if interaction == &Interaction::Pressed && !selection.atoms.is_empty() {
+            selection.atoms.clear();
+            selection_events.send(SelectionChangedEvent);
+        }
+    }
+}
+
+/// selects the atoms of the shown molecules matching the query, shows the error if it's invalid
+#[allow(clippy::too_many_arguments)]
+pub fn atom_query_submit_handler(
+    mut events: EventReader<TextInputSubmitEvent>,
+    query_input: Query<Entity, With<AtomQueryInputMarker>>,
+    mut error_label: Query<&mut Text, With<AtomQueryErrorLabelMarker>>,
+    scene: Res<MolScene>,
+    assets: Res<Assets<Molecule>>,
+    collections: Res<Assets<MolCollection>>,
+    mut selection: ResMut<Selection>,
+    mut selection_events: EventWriter<SelectionChangedEvent>,
+) {
+    for event in events.read() {
+        if query_input.get(event.entity).is_err() {
+            continue;
+        }
+        let message = match parse_atom_query(&event.value) {
+            Ok(query) => {
+                let mut atoms = vec![];
+                for id in scene.content.molecule_ids(&collections) {
+                    if let Some(mol) = assets.get(id) {
+                        let indices = query.atoms(mol);
+                        atoms.extend(indices.into_iter().map(|index| AtomRef { mol: id, index }));
+                    }
+                }
+                if selection
+                    .bypass_change_detection()
+                    .apply(atoms, SelectMode::Replace)
+                {
+                    selection.set_changed();
+                    selection_events.send(SelectionChangedEvent);
+                }
+                String::new()
+            }
+            Err(e) => format!("Error: {}", e),
+        };
+        if let Ok(mut label) = error_label.get_single_mut() {
+            label.sections[0].value = message;
+        }
+    }
+}
+
+/// hides the selected atoms and their bonds
+#[allow(clippy::type_complexity)]
+pub fn hide_selected_button_handler(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<HideSelectedButtonMarker>)>,
+    selection: Res<Selection>,
+    mut scene: ResMut<MolScene>,
+    mut event_writer: EventWriter<UpdateSceneEvent>,
+) {
+    for interaction in &interaction_query {
+        if interaction == &Interaction::Pressed && !selection.atoms.is_empty() {
+            scene
+                .overrides
+                .hidden
+                .extend(selection.atoms.iter().copied());
+            event_writer.send(UpdateSceneEvent);
+        }
+    }
+}
+
+/// colors the selected atoms, with the next of `SELECTION_COLORS`
+#[allow(clippy::type_complexity)]
+pub fn color_selected_button_handler(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<ColorSelectedButtonMarker>)>,
+    selection: Res<Selection>,
+    mut scene: ResMut<MolScene>,
+    mut event_writer: EventWriter<UpdateSceneEvent>,
+    mut next_color: Local<usize>,
+) {
+    for interaction in &interaction_query {
+        if interaction == &Interaction::Pressed && !selection.atoms.is_empty() {
+            let color = SELECTION_COLORS[*next_color % SELECTION_COLORS.len()];
+            *next_color += 1;
+            for atom in &selection.atoms {
+                scene.overrides.colors.insert(*atom, color);
+            }
             event_writer.send(UpdateSceneEvent);
         }
     }
 }
 
+/// centers the selected atoms and fits them in the view
+#[allow(clippy::type_complexity)]
+pub fn zoom_selected_button_handler(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<ZoomSelectedButtonMarker>)>,
+    selection: Res<Selection>,
+    assets: Res<Assets<Molecule>>,
+    mut event_writer: EventWriter<AddedBoundingBox>,
+) {
+    for interaction in &interaction_query {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        let positions: Vec<[f32; 3]> = selection
+            .atoms
+            .iter()
+            .filter_map(|atom| assets.get(atom.mol)?.atoms.get(atom.index))
+            .map(|atom| atom.position.to_array())
+            .collect();
+        if !positions.is_empty() {
+            event_writer.send(AddedBoundingBox(
+                bounding_box_for(&positions).padded(ZOOM_MARGIN),
+            ));
+        }
+    }
+}
+
+/// shows the hidden atoms and resets the colors
+#[allow(clippy::type_complexity)]
+pub fn reset_atoms_button_handler(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<ResetAtomsButtonMarker>)>,
+    mut scene: ResMut<MolScene>,
+    mut event_writer: EventWriter<UpdateSceneEvent>,
+) {
+    for interaction in &interaction_query {
+        let overrides = &scene.overrides;
+        if interaction == &Interaction::Pressed
+            && (!overrides.hidden.is_empty() || !overrides.colors.is_empty())
+        {
+            scene.overrides = AtomOverrides::default();
+            event_writer.send(UpdateSceneEvent);
+        }
+    }
+}
+
+/// shows the number of selected atoms
+pub fn update_selection_label(
+    mut selection_events: EventReader<SelectionChangedEvent>,
+    selection: Res<Selection>,
+    mut label: Query<&mut Text, With<SelectionLabelMarker>>,
+) {
+    if selection_events.is_empty() {
+        return;
+    }
+    selection_events.clear();
+    let Ok(mut label) = label.get_single_mut() else {
+        return;
+    };
+    label.sections[0].value = match selection.atoms.len() {
+        0 => "none".to_string(),
+        1 => "1 atom".to_string(),
+        count => format!("{} atoms", count),
+    };
+}
+
+/// switches to the measure mode of the button, or back to selecting if it's active
+pub fn measure_mode_button_handler(
+    interaction_query: Query<(&Interaction, &MeasureModeButton), Changed<Interaction>>,
+    mut measurements: ResMut<Measurements>,
+) {
+    for (interaction, button) in &interaction_query {
+        if interaction == &Interaction::Pressed {
+            let mode = if measurements.mode == button.0 {
+                MeasureMode::Off
+            } else {
+                button.0
+            };
+            measurements.set_mode(mode);
+        }
+    }
+}
+
+/// copies or deletes a measurement
+pub fn measurement_button_handler(
+    interaction_query: Query<(&Interaction, &MeasurementButton), Changed<Interaction>>,
+    mut measurements: ResMut<Measurements>,
+    mols: Res<Assets<Molecule>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        match button {
+            MeasurementButton::Copy(id) => {
+                if let Some(measurement) = measurements.get(*id) {
+                    copy_text(&measurement_descr(measurement, &mols));
+                }
+            }
+            MeasurementButton::Delete(id) => measurements.remove(*id),
+        }
+    }
+}
+
+/// e.g. "angle N 1 - CA 2 - C 3: 109.5°"
+fn measurement_descr(measurement: &Measurement, mols: &Assets<Molecule>) -> String {
+    let kind = match measurement.mode() {
+        MeasureMode::Distance => "distance",
+        MeasureMode::Angle => "angle",
+        _ => "dihedral",
+    };
+    let atoms: Vec<String> = measurement
+        .atoms
+        .iter()
+        .filter_map(|atom| mols.get(atom.mol)?.atoms.get(atom.index))
+        .map(|atom| format!("{} {}", atom.name, atom.id))
+        .collect();
+    format!(
+        "{} {}: {}",
+        kind,
+        atoms.join(" - "),
+        measurement.value_text()
+    )
+}
+
+/// shows what to pick in the measure mode, and a row per measurement
+#[allow(clippy::type_complexity)]
+pub fn update_measurement_panel(
+    mut commands: Commands,
+    measurements: Res<Measurements>,
+    asset_server: Res<AssetServer>,
+    mut mode_label: Query<
+        &mut Text,
+        (
+            With<MeasureModeLabelMarker>,
+            Without<MeasurementRowLabelMarker>,
+        ),
+    >,
+    list: Query<Entity, With<MeasurementListMarker>>,
+    rows: Query<(Entity, &MeasurementRowMarker)>,
+    mut row_labels: Query<(&MeasurementRowLabelMarker, &mut Text)>,
+) {
+    if !measurements.is_changed() {
+        return;
+    }
+    if let Ok(mut label) = mode_label.get_single_mut() {
+        label.sections[0].value = measure_mode_text(measurements.mode, measurements.picked.len());
+    }
+    let Ok(list_id) = list.get_single() else {
+        return;
+    };
+
+    let row_ids: Vec<usize> = rows.iter().map(|(_, row)| row.0).collect();
+    let ids: Vec<usize> = measurements.list.iter().map(|m| m.id).collect();
+    if row_ids != ids {
+        for (entity, _) in &rows {
+            commands.entity(entity).despawn_recursive();
+        }
+        let font = asset_server.load("embedded://mol/asset/fonts/FiraMono-Medium.ttf");
+        for measurement in &measurements.list {
+            add_measurement_row(&mut commands, list_id, &font, measurement);
+        }
+        return;
+    }
+    for (row_label, mut text) in &mut row_labels {
+        if let Some(measurement) = measurements.get(row_label.0) {
+            let value = measurement.text();
+            if text.sections[0].value != value {
+                text.sections[0].value = value;
+            }
+        }
+    }
+}
+
+/// shows the record of the hovered atom or bond, otherwise of the selected atom if it's only one
+#[allow(clippy::too_many_arguments)]
+pub fn update_inspector(
+    hovered: Res<Hovered>,
+    selection: Res<Selection>,
+    playback: Res<Playback>,
+    mols: Res<Assets<Molecule>>,
+    entities: Res<MolEntities>,
+    atoms: Query<&Transform, With<MyAtom>>,
+    bonds: Query<&MyBond>,
+    mut panel: Query<&mut Style, With<InspectorMarker>>,
+    mut label: Query<&mut Text, With<InspectorLabelMarker>>,
+) {
+    // positions and bond lengths change with the trajectory frame
+    if !hovered.is_changed()
+        && !selection.is_changed()
+        && !playback.is_changed()
+        && !entities.is_changed()
+    {
+        return;
+    }
+    let selected = match selection.atoms.iter().collect::<Vec<_>>()[..] {
+        [atom] => Some(Inspected::Atom(*atom)),
+        _ => None,
+    };
+    let record = hovered
+        .0
+        .or(selected)
+        .and_then(|inspected| inspector_record(inspected, &mols, &entities, &atoms, &bonds));
+
+    if let Ok(mut style) = panel.get_single_mut() {
+        let display = if record.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+    if let (Some(record), Ok(mut label)) = (record, label.get_single_mut()) {
+        label.sections[0].value = record;
+    }
+}
+
+/// positions are the ones of the atom entities (following the trajectory frame) if there are, bond lengths from `MyBond`.
+/// none if the molecule isn't loaded anymore, or the bond isn't drawn
+fn inspector_record(
+    inspected: Inspected,
+    mols: &Assets<Molecule>,
+    entities: &MolEntities,
+    atoms: &Query<&Transform, With<MyAtom>>,
+    bonds: &Query<&MyBond>,
+) -> Option<String> {
+    match inspected {
+        Inspected::Atom(atom) => {
+            let mol = mols
+                .get(atom.mol)
+                .filter(|mol| atom.index < mol.atoms.len())?;
+            let map = entities.mols.get(&atom.mol);
+            let position = |index: usize| {
+                map.and_then(|map| map.atoms.get(&index))
+                    .and_then(|entity| atoms.get(*entity).ok())
+                    .map(|transform| transform.translation)
+                    .unwrap_or(mol.atoms[index].position)
+            };
+            Some(atom_record(mol, atom.index, position))
+        }
+        Inspected::Bond(bond) => {
+            let mol = mols
+                .get(bond.mol)
+                .filter(|mol| bond.index < mol.bonds.len())?;
+            let lines = entities.mols.get(&bond.mol)?.bonds.get(&bond.index)?;
+            let my_bond = bonds.get(*lines.first()?).ok()?;
+            let length = my_bond.bond_length(&mol.bonds[bond.index]);
+            Some(bond_record(mol, bond.index, length))
+        }
+    }
+}
+
+/// scrolls the side panel's content while the cursor is over the panel,
+/// limited to the part of the content that doesn't fit in the panel
+pub fn scroll_side_panel(
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mut content_query: Query<(&mut SidePanelScroll, &mut Style, &Node, &Parent)>,
+    panel_query: Query<(&Node, &RelativeCursorPosition)>,
+) {
+    for event in mouse_wheel_events.read() {
+        for (mut scroll, mut style, content_node, parent) in &mut content_query {
+            let Ok((panel_node, cursor)) = panel_query.get(parent.get()) else {
+                continue;
+            };
+            if !cursor.mouse_over() {
+                continue;
+            }
+            let dy = match event.unit {
+                MouseScrollUnit::Line => event.y * SCROLL_LINE_HEIGHT,
+                MouseScrollUnit::Pixel => event.y,
+            };
+            let max_scroll = (content_node.size().y - panel_node.size().y).max(0.0);
+            scroll.position = (scroll.position + dy).clamp(-max_scroll, 0.0);
+            style.top = Val::Px(scroll.position);
+        }
+    }
+}
+
 pub fn setup_info_labels(commands: Commands, asset_server: Res<AssetServer>) {
     let font = asset_server.load("embedded://mol/asset/fonts/FiraMono-Medium.ttf");
     add_info_labels(commands, &font);
@@ -163,24 +996,82 @@ pub fn close_popup_on_esc(
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn update_ui_for_scene(
     scene: ResMut<MolScene>,
-    mut mol_name_label: Query<&mut Text, With<MolNameMarker>>,
-    assets: Res<Assets<Mol2Molecule>>,
+    mut mol_name_label: Query<&mut Text, (With<MolNameMarker>, Without<RecordLabelMarker>)>,
+    mut record_label: Query<&mut Text, (With<RecordLabelMarker>, Without<MolNameMarker>)>,
+    assets: Res<Assets<Molecule>>,
+    collections: Res<Assets<MolCollection>>,
 ) {
     if let Ok(mut label) = mol_name_label.get_single_mut() {
         match &scene.content {
             MolSceneContent::Empty => {
                 label.sections[0].value = "Empty".to_string();
             }
-            MolSceneContent::Mol2 { handle, .. } => {
-                if let Some(mol) = assets.get(handle) {
+            MolSceneContent::Molecule { .. } | MolSceneContent::Collection { .. } => {
+                if let Some(mol) = scene.content.molecule(&assets, &collections) {
                     label.sections[0].value = mol.name.to_string();
                 }
                 // don't do anything for other states
             }
         }
     }
+
+    if let Ok(mut label) = record_label.get_single_mut() {
+        label.sections[0].value = match &scene.content {
+            MolSceneContent::Collection {
+                handle,
+                selected,
+                overlay,
+                ..
+            } => match collections.get(handle) {
+                Some(collection) if *overlay => format!("all/{}", collection.molecules.len()),
+                Some(collection) => format!("{}/{}", selected + 1, collection.molecules.len()),
+                None => "".to_string(),
+            },
+            _ => "".to_string(),
+        };
+    }
+}
+
+/// shows why the file of the scene couldn't be loaded (e.g. a parse error) or opened, empty otherwise
+pub fn update_load_error_label(
+    scene: Res<MolScene>,
+    asset_server: Res<AssetServer>,
+    mut label: Query<&mut Text, With<LoadErrorLabelMarker>>,
+) {
+    let Ok(mut label) = label.get_single_mut() else {
+        return;
+    };
+    let trajectory_id = scene.trajectory.as_ref().map(|h| h.id().untyped());
+    let message = scene
+        .content
+        .asset_id()
+        .into_iter()
+        .chain(trajectory_id)
+        .find_map(|id| match asset_server.get_load_state(id) {
+            Some(LoadState::Failed(e)) => Some(format!("Error: {}", load_error_message(&e))),
+            _ => None,
+        })
+        .or_else(|| scene.open_error.as_ref().map(|e| format!("Error: {}", e)))
+        .unwrap_or_default();
+    if label.sections[0].value != message {
+        label.sections[0].value = message;
+    }
+}
+
+/// the error of the loader, without the asset path and loader name
+fn load_error_message(error: &AssetLoadError) -> String {
+    let message = error.to_string();
+    match error {
+        AssetLoadError::AssetLoaderError(_) => message
+            .split_once("' with asset loader '")
+            .and_then(|(_, rest)| rest.split_once("': "))
+            .map(|(_, message)| message.to_string())
+            .unwrap_or(message),
+        _ => message,
+    }
 }
 
 #[allow(clippy::type_complexity)]
@@ -198,3 +1089,173 @@ pub fn file_example_button_handler(
         }
     }
 }
+
+#[allow(clippy::type_complexity)]
+pub fn play_button_handler(
+    mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<PlayButtonMarker>)>,
+    mut playback: ResMut<Playback>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            // start again if stopped at the end
+            if !playback.playing && playback.frame + 1 >= playback.frame_count {
+                playback.frame = 0;
+            }
+            playback.playing = !playback.playing;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn loop_button_handler(
+    mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<LoopButtonMarker>)>,
+    mut playback: ResMut<Playback>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            playback.looping = !playback.looping;
+        }
+    }
+}
+
+/// the fps that can be selected with the -/+ buttons
+const FPS_STEPS: [u32; 8] = [1, 2, 5, 10, 15, 24, 30, 60];
+
+#[allow(clippy::type_complexity)]
+pub fn fps_down_button_handler(
+    mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<FpsDownButtonMarker>)>,
+    mut playback: ResMut<Playback>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            if let Some(fps) = FPS_STEPS.iter().rev().find(|f| **f < playback.fps) {
+                playback.fps = *fps;
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn fps_up_button_handler(
+    mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<FpsUpButtonMarker>)>,
+    mut playback: ResMut<Playback>,
+) {
+    for interaction in &mut interaction_query {
+        if interaction == &Interaction::Pressed {
+            if let Some(fps) = FPS_STEPS.iter().find(|f| **f > playback.fps) {
+                playback.fps = *fps;
+            }
+        }
+    }
+}
+
+/// jumps to the frame at the cursor while the slider is pressed (so it can be dragged)
+pub fn frame_slider_handler(
+    slider_query: Query<(&Interaction, &RelativeCursorPosition), With<FrameSliderMarker>>,
+    mut playback: ResMut<Playback>,
+) {
+    for (interaction, cursor) in &slider_query {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        if let Some(position) = cursor.normalized {
+            let last_frame = playback.frame_count - 1;
+            let frame = (position.x.clamp(0.0, 1.0) * last_frame as f32).round() as usize;
+            if frame != playback.frame {
+                playback.frame = frame;
+            }
+        }
+    }
+}
+
+/// shows the playback controls for trajectories and updates them with the playback state
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_playback_ui(
+    playback: Res<Playback>,
+    stream: Res<TrajectoryStream>,
+    mut container: Query<
+        &mut Style,
+        (
+            With<PlaybackContainerMarker>,
+            Without<FrameSliderFillMarker>,
+        ),
+    >,
+    mut fill: Query<
+        &mut Style,
+        (
+            With<FrameSliderFillMarker>,
+            Without<PlaybackContainerMarker>,
+        ),
+    >,
+    mut labels: ParamSet<(
+        Query<&mut Text, With<PlaybackLabelMarker>>,
+        Query<&mut Text, With<FrameLabelMarker>>,
+        Query<&mut Text, With<BoxLabelMarker>>,
+    )>,
+    play_button: Query<&Children, With<PlayButtonMarker>>,
+    mut texts: Query<
+        &mut Text,
+        (
+            Without<PlaybackLabelMarker>,
+            Without<FrameLabelMarker>,
+            Without<BoxLabelMarker>,
+        ),
+    >,
+) {
+    let frame_count = playback.frame_count;
+
+    if let Ok(mut style) = container.get_single_mut() {
+        let display = if frame_count > 1 {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    if stream.is_changed() {
+        if let Ok(mut label) = labels.p2().get_single_mut() {
+            label.sections[0].value = stream
+                .frame
+                .as_ref()
+                .and_then(|f| f.unit_cell.as_ref())
+                .map(|cell| {
+                    format!(
+                        "box: {:.1} {:.1} {:.1}\n{:.1} {:.1} {:.1}",
+                        cell.lengths.x,
+                        cell.lengths.y,
+                        cell.lengths.z,
+                        cell.angles.x,
+                        cell.angles.y,
+                        cell.angles.z
+                    )
+                })
+                .unwrap_or_default();
+        }
+    }
+
+    if !playback.is_changed() {
+        return;
+    }
+
+    let frame = playback.frame.min(frame_count - 1);
+    if let Ok(mut style) = fill.get_single_mut() {
+        style.width = Val::Percent(100.0 * frame as f32 / (frame_count - 1).max(1) as f32);
+    }
+    if let Ok(mut label) = labels.p0().get_single_mut() {
+        let mode = if playback.looping { "loop" } else { "once" };
+        label.sections[0].value = format!("{} fps, {}", playback.fps, mode);
+    }
+    if let Ok(mut label) = labels.p1().get_single_mut() {
+        label.sections[0].value = format!("{}/{}", frame + 1, frame_count);
+    }
+    for children in &play_button {
+        for child in children {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = if playback.playing { "||" } else { ">" }.to_string();
+            }
+        }
+    }
+}
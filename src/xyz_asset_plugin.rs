@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Result};
+use bevy::app::{App, Plugin};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetApp, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::math::Vec3;
+
+use crate::bond_perception::perceive_bonds;
+use crate::element::Element;
+use crate::molecule::{Atom, Molecule};
+
+/// loads xyz files, with one or more frames (trajectories)
+pub struct XyzAssetPlugin;
+
+impl Plugin for XyzAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_asset_loader(XyzAssetLoader);
+    }
+}
+
+pub struct XyzAssetLoader;
+
+impl AssetLoader for XyzAssetLoader {
+    type Asset = Molecule;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Molecule, Self::Error> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8(bytes)?;
+
+        let name = load_context
+            .path()
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mol = parse_xyz(&text, &name)?;
+        println!(
+            "finished parsing xyz file: atoms: {}, bonds: {}, frames: {}",
+            mol.atoms.len(),
+            mol.bonds.len(),
+            mol.frames.len().max(1)
+        );
+        Ok(mol)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xyz"]
+    }
+}
+
+/// a frame is: number of atoms / comment / a line per atom: element x y z [...].
+/// all frames must have the same atoms. bonds are inferred from the first frame.
+/// the comment of the first frame is added as property, as it's often not a name (e.g. energy)
+pub fn parse_xyz(text: &str, default_name: &str) -> Result<Molecule> {
+    let mut lines = text.lines().enumerate().peekable();
+    let mut mol = Molecule {
+        name: default_name.to_string(),
+        ..Default::default()
+    };
+    let mut frames: Vec<Vec<Vec3>> = vec![];
+
+    loop {
+        // blank lines between frames
+        while lines.next_if(|(_, l)| l.trim().is_empty()).is_some() {}
+        let Some((index, count_line)) = lines.next() else {
+            break;
+        };
+        let count: usize = count_line.trim().parse().map_err(|_| {
+            anyhow!(
+                "Line {}: invalid number of atoms: {}",
+                index + 1,
+                count_line
+            )
+        })?;
+        if !frames.is_empty() && count != mol.atoms.len() {
+            return Err(anyhow!(
+                "Line {}: frame {} has {} atoms, the first frame {}",
+                index + 1,
+                frames.len() + 1,
+                count,
+                mol.atoms.len()
+            ));
+        }
+        let comment = lines.next().map(|(_, l)| l.trim()).unwrap_or_default();
+        if frames.is_empty() && !comment.is_empty() {
+            mol.properties
+                .push(("comment".to_string(), comment.to_string()));
+        }
+
+        let mut positions = Vec::with_capacity(count);
+        for atom_index in 0..count {
+            let (index, line) = lines.next().ok_or_else(|| {
+                anyhow!(
+                    "Frame {}: expected {} atoms, found {}",
+                    frames.len() + 1,
+                    count,
+                    atom_index
+                )
+            })?;
+            let (element, position) =
+                parse_atom_line(line).map_err(|e| anyhow!("Line {}: {}", index + 1, e))?;
+
+            if frames.is_empty() {
+                mol.atoms.push(Atom::new(
+                    atom_index as i32 + 1,
+                    element.symbol().to_string(),
+                    element,
+                    position,
+                ));
+            } else if mol.atoms[atom_index].element != element {
+                return Err(anyhow!(
+                    "Line {}: atom {} differs from the first frame",
+                    index + 1,
+                    atom_index + 1
+                ));
+            }
+            positions.push(position);
+        }
+
+        frames.push(positions);
+    }
+
+    if frames.is_empty() {
+        return Err(anyhow!("File has no atoms."));
+    }
+    mol.bonds = perceive_bonds(&mol.atoms);
+    // a single structure has no frames
+    if frames.len() > 1 {
+        mol.frames = frames;
+    }
+    Ok(mol)
+}
+
+/// element (symbol or atomic number) x y z, further columns (e.g. velocities) are ignored
+fn parse_atom_line(line: &str) -> Result<(Element, Vec3)> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 4 {
+        return Err(anyhow!("expected element and coordinates: {}", line));
+    }
+    let element = parse_element(parts[0])?;
+    let coord = |i: usize| {
+        parts[i]
+            .parse::<f32>()
+            .map_err(|_| anyhow!("invalid coordinate: {}", parts[i]))
+    };
+    Ok((element, Vec3::new(coord(1)?, coord(2)?, coord(3)?)))
+}
+
+/// symbols are case insensitive ("CL", "cl", "Cl")
+fn parse_element(s: &str) -> Result<Element> {
+    let element = match s.parse::<u32>() {
+        Ok(number) => Element::from_number(number),
+        Err(_) => {
+            let mut chars = s.chars();
+            let symbol = chars
+                .next()
+                .map(|first| {
+                    first
+                        .to_uppercase()
+                        .chain(chars.flat_map(|c| c.to_lowercase()))
+                })
+                .map(|c| c.collect::<String>())
+                .unwrap_or_default();
+            Element::from_symbol(&symbol)
+        }
+    };
+    element.ok_or_else(|| anyhow!("unknown element: {}", s))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_xyz;
+    use crate::element::Element;
+
+    #[test]
+    fn test_parses_frames_and_infers_bonds() {
+        let text = "\
+3
+water
+O 0.0 0.0 0.0
+H 0.96 0.0 0.0
+h -0.24 0.93 0.0
+
+3
+t=1
+8 0.0 0.0 0.1
+H 0.97 0.0 0.1
+H -0.25 0.92 0.1
+";
+        let mol = parse_xyz(text, "file").unwrap();
+        assert_eq!(mol.name, "file");
+        assert_eq!(mol.properties[0].1, "water");
+        assert_eq!(mol.atoms.len(), 3);
+        assert_eq!(mol.atoms[2].element, Element::H);
+        assert_eq!(mol.bonds.len(), 2);
+        assert_eq!(mol.frames.len(), 2);
+        assert_eq!(mol.frames[1][0].z, 0.1);
+    }
+
+    #[test]
+    fn test_rejects_frame_with_other_atoms() {
+        let text = "1\n\nO 0 0 0\n1\n\nN 0 0 0\n";
+        assert!(parse_xyz(text, "file").is_err());
+    }
+}